@@ -0,0 +1,516 @@
+//! Command line arguments
+
+use {
+    crate::parse::*,
+    blt_teleport::{
+        instruction::{AddressType, Chain, TeleportOutEntry},
+        lockup::LockupStep,
+        state::{BLT_MINT_KEY, OWNER_WALLET_KEY},
+    },
+    blt_teleport_client::OfflineSignature,
+    clap::{ArgGroup, Args, Parser, Subcommand},
+    solana_sdk::{instruction::AccountMeta, pubkey::Pubkey},
+    std::path::PathBuf,
+};
+
+/// Manages the BLT teleport program. Amounts are in Flow UFix64 units unless
+/// noted, every command prints JSON.
+#[derive(Debug, Parser)]
+#[clap(name = "blt-teleport", version)]
+pub struct Cli {
+    /// JSON RPC URL of the cluster.
+    #[clap(long, short = 'u', default_value = "http://127.0.0.1:8899")]
+    pub url: String,
+    /// Keypair file of the fee payer, the Solana CLI default when unset.
+    #[clap(long, short = 'k')]
+    pub keypair: Option<PathBuf>,
+    /// Keypair file of the program owner, the fee payer when unset. With
+    /// `--sign-only` the owner pubkey is enough.
+    #[clap(long)]
+    pub owner: Option<String>,
+    /// Multisig wallet whose PDA acts as the owner. Owner instructions go
+    /// through the wallet invoke instruction, `--owner` being the wallet
+    /// signer.
+    #[clap(long)]
+    pub owner_wallet: Option<Pubkey>,
+    /// The owner PDA of `--owner-wallet`.
+    #[clap(long, default_value = OWNER_WALLET_KEY)]
+    pub owner_wallet_pda: Pubkey,
+    #[clap(long, default_value_t = blt_teleport::id())]
+    pub program_id: Pubkey,
+    /// Simulate the transaction instead of sending it.
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+    /// Durable nonce account to build the transaction on instead of a recent
+    /// blockhash.
+    #[clap(long)]
+    pub nonce: Option<Pubkey>,
+    /// Keypair file of the nonce authority, the fee payer when unset.
+    #[clap(long, requires = "nonce")]
+    pub nonce_authority: Option<PathBuf>,
+    /// Print the message and the signatures made so far instead of sending
+    /// the transaction, for the missing ones to be made offline.
+    #[clap(long, requires = "nonce", conflicts_with = "dry-run")]
+    pub sign_only: bool,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+/// The multisig wallet and mint of the config.
+#[derive(Debug, Args)]
+pub struct RouteArgs {
+    #[clap(long)]
+    pub wallet: Pubkey,
+    /// Signer of the wallet that holds the mint authority.
+    #[clap(long)]
+    pub mint_auth: Pubkey,
+    #[clap(long, default_value = BLT_MINT_KEY)]
+    pub mint: Pubkey,
+    /// SPL Token or Token-2022.
+    #[clap(long, default_value_t = spl_token::id())]
+    pub token_program: Pubkey,
+}
+
+/// The admin acting on the config and its auth keypair file.
+#[derive(Debug, Args)]
+pub struct AdminArgs {
+    #[clap(long)]
+    pub config: Pubkey,
+    #[clap(long)]
+    pub admin: Pubkey,
+    #[clap(long)]
+    pub auth: PathBuf,
+}
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("key").required(true).args(&["solana", "flow", "evm"])))]
+pub struct DenylistArgs {
+    #[clap(long)]
+    pub solana: Option<Pubkey>,
+    /// Flow address in hex.
+    #[clap(long, value_parser = parse_flow_address)]
+    pub flow: Option<[u8; 8]>,
+    /// EVM address in hex.
+    #[clap(long, value_parser = parse_evm_address)]
+    pub evm: Option<[u8; 20]>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    GetOwner,
+    /// Creates the config account and initializes it.
+    InitConfig {
+        /// Keypair file of the new config account.
+        #[clap(long)]
+        config_keypair: PathBuf,
+    },
+    /// Creates an admin account, `add-admin` lets it act on the config.
+    InitAdmin {
+        #[clap(long)]
+        config: Pubkey,
+        /// Keypair file of the new admin account.
+        #[clap(long)]
+        admin_keypair: PathBuf,
+        #[clap(long)]
+        auth: Pubkey,
+        #[clap(long)]
+        allowance: u64,
+    },
+    /// Initializes a version 1 teleport out record account.
+    InitTeleportOutRecord {
+        #[clap(long)]
+        record: Pubkey,
+    },
+    AddAdmin {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+    },
+    RemoveAdmin {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+    },
+    Freeze {
+        #[clap(long)]
+        config: Pubkey,
+    },
+    Unfreeze {
+        #[clap(long)]
+        config: Pubkey,
+    },
+    /// Burns `amount` from a token account for `to` on `chain`.
+    TeleportIn {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(flatten)]
+        route: RouteArgs,
+        /// Token account to burn from.
+        #[clap(long)]
+        from: Pubkey,
+        /// Keypair file of the token account owner.
+        #[clap(long)]
+        from_auth: PathBuf,
+        #[clap(long)]
+        nonce: u64,
+        #[clap(long)]
+        amount: u64,
+        #[clap(long, value_parser = parse_chain, default_value = "flow")]
+        chain: Chain,
+        /// Address on the chain in hex.
+        #[clap(long, value_parser = parse_hex)]
+        to: Vec<u8>,
+        #[clap(long, value_parser = parse_hex)]
+        payload: Option<Vec<u8>>,
+    },
+    /// Mints `amount` for a Flow tx hash.
+    TeleportOut {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(flatten)]
+        route: RouteArgs,
+        #[clap(long)]
+        to: Pubkey,
+        #[clap(long, value_parser = parse_address_type, default_value = "spl")]
+        to_address_type: AddressType,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+        #[clap(long)]
+        amount: u64,
+        /// Forwarded to `receiver`, a whitelisted program.
+        #[clap(long, value_parser = parse_hex, requires = "receiver")]
+        payload: Option<Vec<u8>>,
+        #[clap(long, requires = "payload")]
+        receiver: Option<Pubkey>,
+        /// Accounts passed on to the receiver, `PUBKEY` or `PUBKEY:w`.
+        #[clap(long, value_parser = parse_account_meta, requires = "receiver")]
+        receiver_account: Vec<AccountMeta>,
+    },
+    DepositAllowance {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        allowance: u64,
+    },
+    /// Closes the teleport out record of a Flow tx hash.
+    CloseTeleportOutRecord {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+        /// Receives the rent, the fee payer when unset.
+        #[clap(long)]
+        target: Option<Pubkey>,
+    },
+    TeleportOutBatch {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(flatten)]
+        route: RouteArgs,
+        /// `TX_HASH:AMOUNT:RECIPIENT`, the recipient is a token account.
+        #[clap(long = "entry", value_parser = parse_teleport_out_entry, required = true)]
+        entries: Vec<TeleportOutEntry>,
+    },
+    /// Amounts in lamports.
+    SetGasDrop {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        per_transfer: u64,
+        #[clap(long)]
+        daily_cap: u64,
+    },
+    RefillGasTank {
+        #[clap(long)]
+        lamports: u64,
+    },
+    WithdrawGasTank {
+        #[clap(long)]
+        lamports: u64,
+        /// The fee payer when unset.
+        #[clap(long)]
+        target: Option<Pubkey>,
+    },
+    SetDelayedSettlement {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        threshold: u64,
+        /// In seconds.
+        #[clap(long)]
+        delay: i64,
+    },
+    SetPauser {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        pauser: Pubkey,
+    },
+    /// Mints a pending teleport out once its challenge delay is over.
+    FinalizeTeleportOut {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(flatten)]
+        route: RouteArgs,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+    },
+    CancelTeleportOut {
+        #[clap(long)]
+        config: Pubkey,
+        /// Keypair file of the owner or the pauser.
+        #[clap(long)]
+        authority: PathBuf,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+    },
+    /// In seconds, zero disables it.
+    SetRecordRetention {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        retention: i64,
+    },
+    CloseExpiredTeleportOutRecords {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long = "tx-hash", value_parser = parse_tx_hash, required = true)]
+        tx_hashes: Vec<[u8; 32]>,
+    },
+    CheckInvariants {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long, default_value = BLT_MINT_KEY)]
+        mint: Pubkey,
+    },
+    RotateAdminAuth {
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        new_auth: Pubkey,
+        /// Keypair of the current auth when the admin initiates the
+        /// rotation, the owner co-signs.
+        #[clap(long)]
+        auth: Option<PathBuf>,
+    },
+    SetAllowance {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        allowance: u64,
+    },
+    DecreaseAllowance {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        allowance: u64,
+    },
+    CloseAdmin {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        admin: Pubkey,
+        /// Receives the rent, the fee payer when unset.
+        #[clap(long)]
+        target: Option<Pubkey>,
+    },
+    MigrateConfig {
+        #[clap(long)]
+        config: Pubkey,
+        /// Its supply seeds the minted counter.
+        #[clap(long, default_value = BLT_MINT_KEY)]
+        mint: Pubkey,
+    },
+    /// Migrate the config first, it counts the admin's allowance as granted.
+    MigrateAdmin {
+        #[clap(long)]
+        admin: Pubkey,
+        #[clap(long)]
+        config: Pubkey,
+    },
+    AddToDenylist {
+        #[clap(flatten)]
+        key: DenylistArgs,
+    },
+    RemoveFromDenylist {
+        #[clap(flatten)]
+        key: DenylistArgs,
+    },
+    /// A max of zero means no maximum.
+    SetAmountLimits {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long, default_value_t = 0)]
+        teleport_in_min: u64,
+        #[clap(long, default_value_t = 0)]
+        teleport_in_max: u64,
+        #[clap(long, default_value_t = 0)]
+        teleport_out_min: u64,
+        #[clap(long, default_value_t = 0)]
+        teleport_out_max: u64,
+    },
+    SetChainEnabled {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long, value_parser = parse_chain)]
+        chain: Chain,
+        #[clap(long, value_parser)]
+        enabled: bool,
+    },
+    AddReceiver {
+        #[clap(long)]
+        receiver: Pubkey,
+    },
+    RemoveReceiver {
+        #[clap(long)]
+        receiver: Pubkey,
+    },
+    SetLockupSchedule {
+        #[clap(long)]
+        id: u32,
+        /// `TIMESTAMP:LOCKED_RATIO`, the ratio in UFix64.
+        #[clap(long = "step", value_parser = parse_lockup_step, required = true)]
+        steps: Vec<LockupStep>,
+    },
+    /// Mints a teleport out into a vesting escrow, SPL Token only.
+    TeleportOutVesting {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(flatten)]
+        route: RouteArgs,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+        #[clap(long)]
+        amount: u64,
+        #[clap(long)]
+        beneficiary: Pubkey,
+        #[clap(long)]
+        schedule_id: u32,
+    },
+    /// Claims the unlocked tokens of a vesting escrow.
+    ClaimVesting {
+        /// Keypair file of the beneficiary.
+        #[clap(long)]
+        beneficiary: PathBuf,
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+        /// Token account receiving the tokens.
+        #[clap(long)]
+        to: Pubkey,
+    },
+    MarkTeleportInUnlocked {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(long)]
+        from_auth: Pubkey,
+        #[clap(long)]
+        nonce: u64,
+    },
+    RefundTeleportIn {
+        #[clap(flatten)]
+        admin: AdminArgs,
+        #[clap(flatten)]
+        route: RouteArgs,
+        #[clap(long)]
+        from_auth: Pubkey,
+        #[clap(long)]
+        nonce: u64,
+    },
+    SetTokenRoute {
+        #[clap(long)]
+        config: Pubkey,
+        #[clap(long)]
+        mint: Pubkey,
+        #[clap(long)]
+        token_program: Pubkey,
+    },
+    /// Signs a message exported by `--sign-only`, on the offline machine.
+    SignMessage {
+        /// The message in base64.
+        #[clap(long)]
+        message: String,
+        /// Keypair file of the signer.
+        #[clap(long)]
+        signer: PathBuf,
+    },
+    /// Sends a message exported by `--sign-only` with the signatures made
+    /// offline, signing it as the fee payer if needed.
+    SendMessage {
+        /// The message in base64.
+        #[clap(long)]
+        message: String,
+        /// `PUBKEY=SIGNATURE`, as printed by `--sign-only` and `sign-message`.
+        #[clap(long = "signature", value_parser)]
+        signatures: Vec<OfflineSignature>,
+    },
+    /// Prints an account of the program.
+    #[clap(subcommand)]
+    Show(Show),
+    /// Prints the instructions of a transaction or an instruction with their
+    /// accounts named, the calls wrapped in a multisig wallet invoke
+    /// included.
+    Decode(DecodeArgs),
+}
+
+/// Aliased for clap to take it as one value rather than a list of bytes.
+pub type InstructionData = Vec<u8>;
+
+#[derive(Debug, Args)]
+#[clap(group(ArgGroup::new("input").required(true).args(&["transaction", "message", "data"])))]
+pub struct DecodeArgs {
+    /// A serialized transaction in base64.
+    #[clap(long)]
+    pub transaction: Option<String>,
+    /// A message in base64, as exported by `--sign-only`.
+    #[clap(long)]
+    pub message: Option<String>,
+    /// Instruction data in hex.
+    #[clap(long, value_parser = parse_hex)]
+    pub data: Option<InstructionData>,
+    /// Program of `--data`, the teleport program when unset.
+    #[clap(long, requires = "data")]
+    pub program: Option<Pubkey>,
+    /// Accounts of `--data` in order, `PUBKEY` or `PUBKEY:w` for a writable
+    /// one.
+    #[clap(long = "account", requires = "data", value_parser = parse_account_meta)]
+    pub accounts: Vec<AccountMeta>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Show {
+    Config {
+        #[clap(long)]
+        config: Pubkey,
+    },
+    Admin {
+        #[clap(long)]
+        admin: Pubkey,
+    },
+    /// The teleport out record and the pending transfer of a Flow tx hash.
+    TeleportOut {
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+    },
+    TeleportInReceipt {
+        #[clap(long)]
+        from_auth: Pubkey,
+        #[clap(long)]
+        nonce: u64,
+    },
+    LockupSchedule {
+        #[clap(long)]
+        id: u32,
+    },
+    Vesting {
+        #[clap(long, value_parser = parse_tx_hash)]
+        tx_hash: [u8; 32],
+    },
+}
@@ -0,0 +1,851 @@
+//! Runs the commands
+
+use {
+    crate::{
+        args::{AdminArgs, Command, DecodeArgs, DenylistArgs, Show},
+        decode, output,
+    },
+    blt_teleport::{
+        instruction::{self, AddressType, DenylistKey},
+        state::{Admin, AmountLimits, Config},
+    },
+    blt_teleport_client::{
+        offline::{self, OfflineSignature},
+        ClientError, TeleportClient, TeleportRpc,
+    },
+    serde_json::{json, Value},
+    solana_sdk::{
+        instruction::Instruction,
+        pubkey::Pubkey,
+        sanitize::Sanitize,
+        signature::{read_keypair_file, Keypair, Signer},
+    },
+    std::path::Path,
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("can't read keypair {0}: {1}")]
+    Keypair(String, String),
+    #[error("{0}")]
+    NotFound(String),
+}
+
+impl From<solana_sdk::program_error::ProgramError> for CliError {
+    fn from(e: solana_sdk::program_error::ProgramError) -> Self {
+        CliError::Client(e.into())
+    }
+}
+
+pub fn read_keypair(path: &Path) -> Result<Keypair, CliError> {
+    read_keypair_file(path)
+        .map_err(|e| CliError::Keypair(path.display().to_string(), e.to_string()))
+}
+
+impl From<&DenylistArgs> for DenylistKey {
+    fn from(args: &DenylistArgs) -> Self {
+        // clap requires exactly one of them
+        match (args.solana, args.flow, args.evm) {
+            (Some(pubkey), _, _) => DenylistKey::Solana(pubkey),
+            (_, Some(address), _) => DenylistKey::Flow(address),
+            (_, _, Some(address)) => DenylistKey::Evm(address),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Instructions of a command and the keypairs signing them besides the fee
+/// payer.
+struct Plan {
+    instructions: Vec<Instruction>,
+    signers: Vec<Keypair>,
+    /// Whether the owner signs too.
+    owner: bool,
+}
+
+impl Plan {
+    fn owner(instruction: Instruction) -> Self {
+        Self {
+            instructions: vec![instruction],
+            signers: vec![],
+            owner: true,
+        }
+    }
+
+    fn signed_by(instruction: Instruction, signers: Vec<Keypair>) -> Self {
+        Self {
+            instructions: vec![instruction],
+            signers,
+            owner: false,
+        }
+    }
+}
+
+/// How the transaction of a command goes out.
+pub struct Submit<'a> {
+    /// Simulate the transaction instead of sending it.
+    pub dry_run: bool,
+    /// Export the message and the signatures made so far instead of sending
+    /// it.
+    pub sign_only: bool,
+    /// Durable nonce account and its authority.
+    pub nonce: Option<(Pubkey, &'a dyn Signer)>,
+    /// Multisig wallet and its PDA acting as the owner.
+    pub owner_wallet: Option<(Pubkey, Pubkey)>,
+}
+
+/// Runs `command` and returns what to print.
+pub async fn process<R: TeleportRpc + Send>(
+    client: &mut TeleportClient<R>,
+    owner: &dyn Signer,
+    command: &Command,
+    submit: &Submit<'_>,
+) -> Result<Value, CliError> {
+    match command {
+        Command::Show(show) => return process_show(client, show).await,
+        Command::SignMessage { message, signer } => {
+            let message = offline::decode_message(message)?;
+            let signature = offline::sign_message(&message, &read_keypair(signer)?)?;
+            return Ok(json!({ "offline_signature": signature.to_string() }));
+        }
+        Command::SendMessage {
+            message,
+            signatures,
+        } => return send_message(client, message, signatures, submit.dry_run).await,
+        Command::Decode(args) => return process_decode(client.program_id(), args),
+        _ => {}
+    }
+
+    let (plan, created) = match submit.owner_wallet {
+        Some((wallet, wallet_pda)) => {
+            let (mut plan, created) = plan(client, &wallet_pda, command).await?;
+            plan.instructions = plan
+                .instructions
+                .iter()
+                .map(|instruction| through_wallet(instruction, &wallet, &wallet_pda, owner))
+                .collect::<Result<_, _>>()?;
+            (plan, created)
+        }
+        None => plan(client, &owner.pubkey(), command).await?,
+    };
+    let mut signers: Vec<&dyn Signer> = plan.signers.iter().map(|s| s as &dyn Signer).collect();
+    if plan.owner {
+        signers.push(owner);
+    }
+
+    let mut result = match submit.nonce {
+        Some((nonce, authority)) => {
+            signers.push(authority);
+            let transaction = client
+                .nonce_transaction(&plan.instructions, &nonce, &authority.pubkey(), &signers)
+                .await?;
+            if submit.sign_only {
+                output::sign_only(&transaction)
+            } else if submit.dry_run {
+                output::simulation(&client.rpc().simulate_transaction(&transaction).await?)
+            } else {
+                let signature = client.broadcast(&transaction).await?;
+                json!({ "signature": signature.to_string() })
+            }
+        }
+        None if submit.dry_run => {
+            output::simulation(&client.simulate(&plan.instructions, &signers).await?)
+        }
+        None => {
+            let signature = client.send(&plan.instructions, &signers).await?;
+            json!({ "signature": signature.to_string() })
+        }
+    };
+    if let Some(created) = created {
+        result["account"] = json!(created.to_string());
+    }
+    Ok(result)
+}
+
+/// Wraps an instruction the owner wallet PDA signs in the wallet invoke
+/// instruction, `signer` approving it for the wallet.
+fn through_wallet(
+    instruction: &Instruction,
+    wallet: &Pubkey,
+    wallet_pda: &Pubkey,
+    signer: &dyn Signer,
+) -> Result<Instruction, CliError> {
+    let signed_by_owner = instruction
+        .accounts
+        .iter()
+        .any(|meta| meta.is_signer && meta.pubkey == *wallet_pda);
+    if !signed_by_owner {
+        return Ok(instruction.clone());
+    }
+    Ok(instruction::wallet_invoke(
+        wallet,
+        wallet_pda,
+        &[signer.pubkey()],
+        instruction,
+    )?)
+}
+
+/// Sends an exported message with the offline signatures, adding the fee
+/// payer's when it's missing.
+async fn send_message<R: TeleportRpc + Send>(
+    client: &mut TeleportClient<R>,
+    message: &str,
+    signatures: &[OfflineSignature],
+    dry_run: bool,
+) -> Result<Value, CliError> {
+    let message = offline::decode_message(message)?;
+    let mut signatures = signatures.to_vec();
+    let payer = client.payer();
+    if offline::signer_keys(&message).contains(&payer.pubkey())
+        && signatures.iter().all(|s| s.pubkey != payer.pubkey())
+    {
+        signatures.push(offline::sign_message(&message, payer)?);
+    }
+
+    let transaction = offline::assemble_transaction(message, &signatures)?;
+    if dry_run {
+        return Ok(output::simulation(
+            &client.rpc().simulate_transaction(&transaction).await?,
+        ));
+    }
+    let signature = client.broadcast(&transaction).await?;
+    Ok(json!({ "signature": signature.to_string() }))
+}
+
+fn admin_plan(
+    admin: &AdminArgs,
+    build: impl FnOnce(&Pubkey) -> Result<Instruction, solana_sdk::program_error::ProgramError>,
+) -> Result<Plan, CliError> {
+    let auth = read_keypair(&admin.auth)?;
+    let instruction = build(&auth.pubkey())?;
+    Ok(Plan::signed_by(instruction, vec![auth]))
+}
+
+/// The plan of `command`, and the account it creates if any.
+async fn plan<R: TeleportRpc + Send>(
+    client: &mut TeleportClient<R>,
+    owner: &Pubkey,
+    command: &Command,
+) -> Result<(Plan, Option<Pubkey>), CliError> {
+    let program_id = *client.program_id();
+    let payer = client.payer().pubkey();
+    let plan = match command {
+        Command::GetOwner => Plan::signed_by(instruction::get_owner(&program_id)?, vec![]),
+        Command::InitConfig { config_keypair } => {
+            let config = read_keypair(config_keypair)?;
+            let address = config.pubkey();
+            let plan = Plan {
+                instructions: vec![
+                    client.create_program_account(&address, Config::LEN).await?,
+                    instruction::init_config(&program_id, owner, &address)?,
+                ],
+                signers: vec![config],
+                owner: true,
+            };
+            return Ok((plan, Some(address)));
+        }
+        Command::InitAdmin {
+            config,
+            admin_keypair,
+            auth,
+            allowance,
+        } => {
+            let admin = read_keypair(admin_keypair)?;
+            let address = admin.pubkey();
+            let plan = Plan {
+                instructions: vec![
+                    client.create_program_account(&address, Admin::LEN).await?,
+                    instruction::init_admin(
+                        &program_id,
+                        owner,
+                        config,
+                        &address,
+                        auth,
+                        *allowance,
+                    )?,
+                ],
+                signers: vec![admin],
+                owner: true,
+            };
+            return Ok((plan, Some(address)));
+        }
+        Command::InitTeleportOutRecord { record } => Plan::signed_by(
+            instruction::init_teleport_out_record(&program_id, record)?,
+            vec![],
+        ),
+        Command::AddAdmin { config, admin } => {
+            Plan::owner(instruction::add_admin(&program_id, owner, config, admin)?)
+        }
+        Command::RemoveAdmin { config, admin } => Plan::owner(instruction::remove_admin(
+            &program_id,
+            owner,
+            config,
+            admin,
+        )?),
+        Command::Freeze { config } => Plan::owner(instruction::freeze(&program_id, owner, config)?),
+        Command::Unfreeze { config } => {
+            Plan::owner(instruction::unfreeze(&program_id, owner, config)?)
+        }
+        Command::TeleportIn {
+            config,
+            route,
+            from,
+            from_auth,
+            nonce,
+            amount,
+            chain,
+            to,
+            payload,
+        } => {
+            let from_auth = read_keypair(from_auth)?;
+            let instruction = instruction::teleport_in(
+                &program_id,
+                config,
+                &route.wallet,
+                &route.mint_auth,
+                from,
+                &route.mint,
+                &route.token_program,
+                &from_auth.pubkey(),
+                &payer,
+                *nonce,
+                *amount,
+                *chain,
+                to,
+                payload.clone(),
+            )?;
+            Plan::signed_by(instruction, vec![from_auth])
+        }
+        Command::TeleportOut {
+            admin,
+            route,
+            to,
+            to_address_type,
+            tx_hash,
+            amount,
+            payload,
+            receiver,
+            receiver_account,
+        } => {
+            let to_owner = client.fetch_recipient_owner(to, *to_address_type).await?;
+            admin_plan(admin, |auth| match (payload, receiver) {
+                (Some(payload), Some(receiver)) => instruction::teleport_out_with_payload(
+                    &program_id,
+                    &admin.config,
+                    &admin.admin,
+                    auth,
+                    &route.wallet,
+                    &payer,
+                    &route.mint,
+                    &route.token_program,
+                    to,
+                    *to_address_type,
+                    &to_owner,
+                    &route.mint_auth,
+                    tx_hash,
+                    *amount,
+                    payload.clone(),
+                    receiver,
+                    receiver_account.clone(),
+                ),
+                _ => instruction::teleport_out(
+                    &program_id,
+                    &admin.config,
+                    &admin.admin,
+                    auth,
+                    &route.wallet,
+                    &payer,
+                    &route.mint,
+                    &route.token_program,
+                    to,
+                    *to_address_type,
+                    &to_owner,
+                    &route.mint_auth,
+                    tx_hash,
+                    *amount,
+                ),
+            })?
+        }
+        Command::DepositAllowance {
+            config,
+            admin,
+            allowance,
+        } => Plan::owner(instruction::deposit_allowance(
+            &program_id,
+            owner,
+            config,
+            admin,
+            *allowance,
+        )?),
+        Command::CloseTeleportOutRecord {
+            admin,
+            tx_hash,
+            target,
+        } => {
+            let record = blt_teleport::state::get_teleport_out_record_address(&program_id, tx_hash);
+            admin_plan(admin, |auth| {
+                instruction::close_teleport_out_record(
+                    &program_id,
+                    &admin.config,
+                    &admin.admin,
+                    auth,
+                    &record,
+                    &target.unwrap_or(payer),
+                )
+            })?
+        }
+        Command::TeleportOutBatch {
+            admin,
+            route,
+            entries,
+        } => {
+            let mut recipient_owners = Vec::with_capacity(entries.len());
+            for entry in entries {
+                recipient_owners.push(
+                    client
+                        .fetch_recipient_owner(&entry.recipient, AddressType::Spl)
+                        .await?,
+                );
+            }
+            admin_plan(admin, |auth| {
+                instruction::teleport_out_batch(
+                    &program_id,
+                    &admin.config,
+                    &admin.admin,
+                    auth,
+                    &route.wallet,
+                    &payer,
+                    &route.mint,
+                    &route.token_program,
+                    &route.mint_auth,
+                    entries,
+                    &recipient_owners,
+                )
+            })?
+        }
+        Command::SetGasDrop {
+            config,
+            per_transfer,
+            daily_cap,
+        } => Plan::owner(instruction::set_gas_drop(
+            &program_id,
+            owner,
+            config,
+            *per_transfer,
+            *daily_cap,
+        )?),
+        Command::RefillGasTank { lamports } => {
+            Plan::owner(instruction::refill_gas_tank(&program_id, owner, *lamports)?)
+        }
+        Command::WithdrawGasTank { lamports, target } => {
+            Plan::owner(instruction::withdraw_gas_tank(
+                &program_id,
+                owner,
+                &target.unwrap_or(payer),
+                *lamports,
+            )?)
+        }
+        Command::SetDelayedSettlement {
+            config,
+            threshold,
+            delay,
+        } => Plan::owner(instruction::set_delayed_settlement(
+            &program_id,
+            owner,
+            config,
+            *threshold,
+            *delay,
+        )?),
+        Command::SetPauser { config, pauser } => {
+            Plan::owner(instruction::set_pauser(&program_id, owner, config, pauser)?)
+        }
+        Command::FinalizeTeleportOut {
+            config,
+            route,
+            tx_hash,
+        } => {
+            let pending = client
+                .fetch_pending_teleport_out(tx_hash)
+                .await?
+                .ok_or_else(|| not_found("pending teleport out", tx_hash))?;
+            let to_owner = client
+                .fetch_recipient_owner(&pending.to, pending.to_address_type)
+                .await?;
+            Plan::signed_by(
+                instruction::finalize_teleport_out(
+                    &program_id,
+                    config,
+                    &pending.payer,
+                    &route.wallet,
+                    &payer,
+                    &route.mint,
+                    &route.token_program,
+                    &pending.to,
+                    pending.to_address_type,
+                    &to_owner,
+                    &route.mint_auth,
+                    tx_hash,
+                )?,
+                vec![],
+            )
+        }
+        Command::CancelTeleportOut {
+            config,
+            authority,
+            tx_hash,
+        } => {
+            let pending = client
+                .fetch_pending_teleport_out(tx_hash)
+                .await?
+                .ok_or_else(|| not_found("pending teleport out", tx_hash))?;
+            let authority = read_keypair(authority)?;
+            Plan::signed_by(
+                instruction::cancel_teleport_out(
+                    &program_id,
+                    &authority.pubkey(),
+                    config,
+                    &pending.admin,
+                    &pending.payer,
+                    tx_hash,
+                )?,
+                vec![authority],
+            )
+        }
+        Command::SetRecordRetention { config, retention } => Plan::owner(
+            instruction::set_record_retention(&program_id, owner, config, *retention)?,
+        ),
+        Command::CloseExpiredTeleportOutRecords { config, tx_hashes } => {
+            let mut records = Vec::with_capacity(tx_hashes.len());
+            for tx_hash in tx_hashes {
+                let record = client
+                    .fetch_teleport_out_record(tx_hash)
+                    .await?
+                    .ok_or_else(|| not_found("teleport out record", tx_hash))?;
+                records.push((
+                    blt_teleport::state::get_teleport_out_record_address(&program_id, tx_hash),
+                    record.payer,
+                ));
+            }
+            Plan::signed_by(
+                instruction::close_expired_teleport_out_records(&program_id, config, &records)?,
+                vec![],
+            )
+        }
+        Command::CheckInvariants { config, mint } => Plan::signed_by(
+            instruction::check_invariants(&program_id, config, mint)?,
+            vec![],
+        ),
+        Command::RotateAdminAuth {
+            admin,
+            new_auth,
+            auth,
+        } => match auth {
+            Some(auth) => {
+                let auth = read_keypair(auth)?;
+                Plan {
+                    instructions: vec![instruction::rotate_admin_auth_by_admin(
+                        &program_id,
+                        owner,
+                        admin,
+                        &auth.pubkey(),
+                        new_auth,
+                    )?],
+                    signers: vec![auth],
+                    owner: true,
+                }
+            }
+            None => Plan::owner(instruction::rotate_admin_auth(
+                &program_id,
+                owner,
+                admin,
+                new_auth,
+            )?),
+        },
+        Command::SetAllowance {
+            config,
+            admin,
+            allowance,
+        } => Plan::owner(instruction::set_allowance(
+            &program_id,
+            owner,
+            config,
+            admin,
+            *allowance,
+        )?),
+        Command::DecreaseAllowance {
+            config,
+            admin,
+            allowance,
+        } => Plan::owner(instruction::decrease_allowance(
+            &program_id,
+            owner,
+            config,
+            admin,
+            *allowance,
+        )?),
+        Command::CloseAdmin {
+            config,
+            admin,
+            target,
+        } => Plan::owner(instruction::close_admin(
+            &program_id,
+            owner,
+            config,
+            admin,
+            &target.unwrap_or(payer),
+        )?),
+        Command::MigrateConfig { config, mint } => Plan::owner(instruction::migrate_config(
+            &program_id,
+            owner,
+            config,
+            mint,
+        )?),
+        Command::MigrateAdmin { admin, config } => Plan::owner(instruction::migrate_admin(
+            &program_id,
+            owner,
+            admin,
+            config,
+        )?),
+        Command::AddToDenylist { key } => Plan::owner(instruction::add_to_denylist(
+            &program_id,
+            owner,
+            key.into(),
+        )?),
+        Command::RemoveFromDenylist { key } => Plan::owner(instruction::remove_from_denylist(
+            &program_id,
+            owner,
+            key.into(),
+        )?),
+        Command::SetAmountLimits {
+            config,
+            teleport_in_min,
+            teleport_in_max,
+            teleport_out_min,
+            teleport_out_max,
+        } => Plan::owner(instruction::set_amount_limits(
+            &program_id,
+            owner,
+            config,
+            AmountLimits {
+                min: *teleport_in_min,
+                max: *teleport_in_max,
+            },
+            AmountLimits {
+                min: *teleport_out_min,
+                max: *teleport_out_max,
+            },
+        )?),
+        Command::SetChainEnabled {
+            config,
+            chain,
+            enabled,
+        } => Plan::owner(instruction::set_chain_enabled(
+            &program_id,
+            owner,
+            config,
+            *chain,
+            *enabled,
+        )?),
+        Command::AddReceiver { receiver } => {
+            Plan::owner(instruction::add_receiver(&program_id, owner, receiver)?)
+        }
+        Command::RemoveReceiver { receiver } => {
+            Plan::owner(instruction::remove_receiver(&program_id, owner, receiver)?)
+        }
+        Command::SetLockupSchedule { id, steps } => Plan::owner(instruction::set_lockup_schedule(
+            &program_id,
+            owner,
+            *id,
+            steps.clone(),
+        )?),
+        Command::TeleportOutVesting {
+            admin,
+            route,
+            tx_hash,
+            amount,
+            beneficiary,
+            schedule_id,
+        } => admin_plan(admin, |auth| {
+            instruction::teleport_out_vesting(
+                &program_id,
+                &admin.config,
+                &admin.admin,
+                auth,
+                &route.wallet,
+                &payer,
+                &route.mint,
+                &route.mint_auth,
+                tx_hash,
+                *amount,
+                beneficiary,
+                *schedule_id,
+            )
+        })?,
+        Command::ClaimVesting {
+            beneficiary,
+            tx_hash,
+            to,
+        } => {
+            let vesting = client
+                .fetch_vesting(tx_hash)
+                .await?
+                .ok_or_else(|| not_found("vesting", tx_hash))?;
+            let beneficiary = read_keypair(beneficiary)?;
+            Plan::signed_by(
+                instruction::claim_vesting(
+                    &program_id,
+                    &beneficiary.pubkey(),
+                    tx_hash,
+                    &vesting.mint,
+                    to,
+                    &vesting.payer,
+                )?,
+                vec![beneficiary],
+            )
+        }
+        Command::MarkTeleportInUnlocked {
+            admin,
+            from_auth,
+            nonce,
+        } => admin_plan(admin, |auth| {
+            instruction::mark_teleport_in_unlocked(
+                &program_id,
+                &admin.config,
+                &admin.admin,
+                auth,
+                from_auth,
+                *nonce,
+            )
+        })?,
+        Command::RefundTeleportIn {
+            admin,
+            route,
+            from_auth,
+            nonce,
+        } => {
+            let receipt = client
+                .fetch_teleport_in_receipt(from_auth, *nonce)
+                .await?
+                .ok_or_else(|| {
+                    CliError::NotFound(format!("no teleport in receipt {} {}", from_auth, nonce))
+                })?;
+            let from_owner = client
+                .fetch_recipient_owner(&receipt.from, AddressType::Spl)
+                .await?;
+            admin_plan(admin, |auth| {
+                instruction::refund_teleport_in(
+                    &program_id,
+                    &admin.config,
+                    &admin.admin,
+                    auth,
+                    &route.wallet,
+                    &payer,
+                    &route.mint,
+                    &route.token_program,
+                    &route.mint_auth,
+                    from_auth,
+                    *nonce,
+                    &receipt.from,
+                    &from_owner,
+                )
+            })?
+        }
+        Command::SetTokenRoute {
+            config,
+            mint,
+            token_program,
+        } => Plan::owner(instruction::set_token_route(
+            &program_id,
+            owner,
+            config,
+            mint,
+            token_program,
+        )?),
+        Command::Show(_)
+        | Command::SignMessage { .. }
+        | Command::SendMessage { .. }
+        | Command::Decode(_) => unreachable!(),
+    };
+    Ok((plan, None))
+}
+
+fn not_found(what: &str, tx_hash: &[u8; 32]) -> CliError {
+    CliError::NotFound(format!("no {} for tx hash {}", what, hex::encode(tx_hash)))
+}
+
+async fn process_show<R: TeleportRpc + Send>(
+    client: &mut TeleportClient<R>,
+    show: &Show,
+) -> Result<Value, CliError> {
+    match show {
+        Show::Config { config } => {
+            let state = client.fetch_config(config).await?;
+            Ok(output::config(config, &state))
+        }
+        Show::Admin { admin } => {
+            let state = client.fetch_admin(admin).await?;
+            Ok(output::admin(admin, &state))
+        }
+        Show::TeleportOut { tx_hash } => {
+            let record = client.fetch_teleport_out_record(tx_hash).await?;
+            let pending = client.fetch_pending_teleport_out(tx_hash).await?;
+            Ok(output::teleport_out(
+                tx_hash,
+                record.as_ref(),
+                pending.as_ref(),
+            ))
+        }
+        Show::TeleportInReceipt { from_auth, nonce } => {
+            let receipt = client
+                .fetch_teleport_in_receipt(from_auth, *nonce)
+                .await?
+                .ok_or_else(|| {
+                    CliError::NotFound(format!("no teleport in receipt {} {}", from_auth, nonce))
+                })?;
+            Ok(output::teleport_in_receipt(&receipt))
+        }
+        Show::LockupSchedule { id } => {
+            let schedule = client
+                .fetch_lockup_schedule(*id)
+                .await?
+                .ok_or_else(|| CliError::NotFound(format!("no lockup schedule {}", id)))?;
+            Ok(output::lockup_schedule(&schedule))
+        }
+        Show::Vesting { tx_hash } => {
+            let vesting = client
+                .fetch_vesting(tx_hash)
+                .await?
+                .ok_or_else(|| not_found("vesting", tx_hash))?;
+            Ok(output::vesting(&vesting))
+        }
+    }
+}
+
+fn process_decode(program_id: &Pubkey, args: &DecodeArgs) -> Result<Value, CliError> {
+    // clap requires exactly one of them
+    match (&args.transaction, &args.message, &args.data) {
+        (Some(transaction), _, _) => Ok(decode::transaction(
+            &decode::decode_transaction(transaction)?,
+            program_id,
+        )),
+        (_, Some(encoded), _) => {
+            let message = offline::decode_message(encoded)?;
+            message
+                .sanitize()
+                .map_err(|_| ClientError::InvalidMessage(encoded.to_string()))?;
+            Ok(decode::message(&message, program_id))
+        }
+        (_, _, Some(data)) => {
+            let instruction = Instruction {
+                program_id: args.program.unwrap_or(*program_id),
+                accounts: args.accounts.clone(),
+                data: data.clone(),
+            };
+            Ok(decode::instruction(&instruction, program_id))
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,571 @@
+//! Labeled JSON views of transactions and instructions
+//!
+//! Teleport instructions get their variant, arguments and accounts named in
+//! the order the processor reads them. Multisig wallet invoke instructions
+//! get the call they wrap decoded too, be it a teleport or a token
+//! instruction.
+
+use {
+    crate::output,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    blt_teleport::{
+        instruction::{AddressType, Chain, DenylistKey, TeleportInstruction},
+        state::{self, MULTISIG_PROGRAM_KEY},
+    },
+    blt_teleport_client::ClientError,
+    borsh::BorshDeserialize,
+    serde_json::{json, Value},
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        program_utils::limited_deserialize,
+        pubkey::Pubkey,
+        sanitize::Sanitize,
+        transaction::Transaction,
+    },
+    spl_token::instruction::TokenInstruction,
+    std::{convert::TryInto, str::FromStr},
+};
+
+/// A serialized transaction in base64, signatures included. Its account
+/// indexes are checked, the views index them as they are.
+pub fn decode_transaction(encoded: &str) -> Result<Transaction, ClientError> {
+    let invalid = || ClientError::InvalidMessage(encoded.to_string());
+    let data = STANDARD.decode(encoded.trim()).map_err(|_| invalid())?;
+    let transaction: Transaction = limited_deserialize(&data).map_err(|_| invalid())?;
+    transaction.sanitize().map_err(|_| invalid())?;
+    Ok(transaction)
+}
+
+pub fn transaction(transaction: &Transaction, program_id: &Pubkey) -> Value {
+    let signatures: Vec<String> = transaction
+        .signatures
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let mut view = message(&transaction.message, program_id);
+    view["signatures"] = json!(signatures);
+    view
+}
+
+/// `message` should be sanitized.
+pub fn message(message: &Message, program_id: &Pubkey) -> Value {
+    let instructions: Vec<Value> = (0..message.instructions.len())
+        .map(|index| instruction(&compiled_instruction(message, index), program_id))
+        .collect();
+    json!({
+        "fee_payer": message.account_keys.first().map(ToString::to_string),
+        "recent_blockhash": message.recent_blockhash.to_string(),
+        "instructions": instructions,
+    })
+}
+
+fn compiled_instruction(message: &Message, index: usize) -> Instruction {
+    let compiled = &message.instructions[index];
+    Instruction {
+        program_id: message.account_keys[compiled.program_id_index as usize],
+        accounts: compiled
+            .accounts
+            .iter()
+            .map(|index| {
+                let index = *index as usize;
+                AccountMeta {
+                    pubkey: message.account_keys[index],
+                    is_signer: message.is_signer(index),
+                    is_writable: message.is_writable(index),
+                }
+            })
+            .collect(),
+        data: compiled.data.clone(),
+    }
+}
+
+/// An instruction of the teleport program at `program_id`, the multisig
+/// wallet or a token program, the others with their raw data.
+pub fn instruction(instruction: &Instruction, program_id: &Pubkey) -> Value {
+    let decoded = if instruction.program_id == *program_id {
+        teleport_instruction(instruction)
+    } else if instruction.program_id == Pubkey::from_str(MULTISIG_PROGRAM_KEY).unwrap() {
+        wallet_invoke(instruction, program_id)
+    } else if state::is_supported_token_program(&instruction.program_id) {
+        token_instruction(instruction, program_id)
+    } else {
+        None
+    };
+    decoded.unwrap_or_else(|| {
+        json!({
+            "program": program_label(&instruction.program_id, program_id),
+            "program_id": instruction.program_id.to_string(),
+            "instruction": Value::Null,
+            "data": hex::encode(&instruction.data),
+            "accounts": accounts(&instruction.accounts, &[]),
+        })
+    })
+}
+
+fn program_label(address: &Pubkey, program_id: &Pubkey) -> &'static str {
+    if address == program_id {
+        "teleport"
+    } else if address == &Pubkey::from_str(MULTISIG_PROGRAM_KEY).unwrap() {
+        "multisig"
+    } else if address == &spl_token::id() {
+        "spl-token"
+    } else if state::is_supported_token_program(address) {
+        "token-2022"
+    } else {
+        "unknown"
+    }
+}
+
+/// `names` label the accounts in order, the ones past them are numbered.
+fn accounts(metas: &[AccountMeta], names: &[String]) -> Vec<Value> {
+    metas
+        .iter()
+        .enumerate()
+        .map(|(index, meta)| {
+            json!({
+                "name": names.get(index).cloned().unwrap_or_else(|| format!("account_{}", index)),
+                "pubkey": meta.pubkey.to_string(),
+                "signer": meta.is_signer,
+                "writable": meta.is_writable,
+            })
+        })
+        .collect()
+}
+
+/// The variant name of a `Debug` enum value.
+fn variant_name<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+fn chain(chain: Chain) -> &'static str {
+    match chain {
+        Chain::Flow => "flow",
+        Chain::Ethereum => "ethereum",
+        Chain::Bsc => "bsc",
+    }
+}
+
+fn denylist_key(key: &DenylistKey) -> Value {
+    match key {
+        DenylistKey::Solana(pubkey) => json!({ "solana": pubkey.to_string() }),
+        DenylistKey::Flow(address) => json!({ "flow": hex::encode(address) }),
+        DenylistKey::Evm(address) => json!({ "evm": hex::encode(address) }),
+    }
+}
+
+fn teleport_instruction(instruction: &Instruction) -> Option<Value> {
+    let decoded = TeleportInstruction::try_from_slice(&instruction.data).ok()?;
+    let names = teleport_account_names(&decoded, instruction.accounts.len());
+    Some(json!({
+        "program": "teleport",
+        "program_id": instruction.program_id.to_string(),
+        "instruction": variant_name(&decoded),
+        "args": teleport_args(&decoded),
+        "accounts": accounts(&instruction.accounts, &names),
+    }))
+}
+
+fn teleport_args(instruction: &TeleportInstruction) -> Value {
+    use TeleportInstruction::*;
+    match instruction {
+        GetOwner
+        | InitConfig
+        | InitTeleportOutRecord
+        | Freeze
+        | Unfreeze
+        | CloseTeleportOutRecord
+        | FinalizeTeleportOut
+        | CancelTeleportOut
+        | CloseExpiredTeleportOutRecords
+        | CheckInvariants
+        | CloseAdmin
+        | MigrateConfig
+        | MigrateAdmin
+        | ClaimVesting
+        | MarkTeleportInUnlocked
+        | RefundTeleportIn => json!({}),
+        InitAdmin { auth, allowance } => {
+            json!({ "auth": auth.to_string(), "allowance": allowance })
+        }
+        AddAdmin { admin } | RemoveAdmin { admin } => json!({ "admin": admin.to_string() }),
+        TeleportIn {
+            amount,
+            to,
+            chain: to_chain,
+            payload,
+            nonce,
+        } => json!({
+            "amount": amount,
+            "to": hex::encode(to),
+            "chain": chain(*to_chain),
+            "payload": payload.as_ref().map(hex::encode),
+            "nonce": nonce,
+        }),
+        TeleportOut {
+            tx_hash,
+            amount,
+            to_address_type,
+            payload,
+        } => json!({
+            "tx_hash": hex::encode(tx_hash),
+            "amount": amount,
+            "to_address_type": output::address_type(*to_address_type),
+            "payload": payload.as_ref().map(hex::encode),
+        }),
+        DepositAllowance { allowance }
+        | SetAllowance { allowance }
+        | DecreaseAllowance { allowance } => json!({ "allowance": allowance }),
+        TeleportOutBatch { entries } => {
+            let entries: Vec<Value> = entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "tx_hash": hex::encode(entry.tx_hash),
+                        "amount": entry.amount,
+                        "recipient": entry.recipient.to_string(),
+                    })
+                })
+                .collect();
+            json!({ "entries": entries })
+        }
+        SetGasDrop {
+            per_transfer,
+            daily_cap,
+        } => json!({ "per_transfer": per_transfer, "daily_cap": daily_cap }),
+        RefillGasTank { lamports } | WithdrawGasTank { lamports } => {
+            json!({ "lamports": lamports })
+        }
+        SetDelayedSettlement { threshold, delay } => {
+            json!({ "threshold": threshold, "delay": delay })
+        }
+        SetPauser { pauser } => json!({ "pauser": pauser.to_string() }),
+        SetRecordRetention { retention } => json!({ "retention": retention }),
+        RotateAdminAuth { new_auth } => json!({ "new_auth": new_auth.to_string() }),
+        AddToDenylist { key } | RemoveFromDenylist { key } => {
+            json!({ "key": denylist_key(key) })
+        }
+        SetAmountLimits {
+            teleport_in,
+            teleport_out,
+        } => json!({
+            "teleport_in": output::limits(teleport_in),
+            "teleport_out": output::limits(teleport_out),
+        }),
+        SetChainEnabled {
+            chain: enabled_chain,
+            enabled,
+        } => json!({ "chain": chain(*enabled_chain), "enabled": enabled }),
+        AddReceiver { receiver } | RemoveReceiver { receiver } => {
+            json!({ "receiver": receiver.to_string() })
+        }
+        SetLockupSchedule { id, steps } => {
+            let steps: Vec<Value> = steps
+                .iter()
+                .map(|step| json!({ "timestamp": step.timestamp, "locked_ratio": step.locked_ratio }))
+                .collect();
+            json!({ "id": id, "steps": steps })
+        }
+        TeleportOutVesting {
+            tx_hash,
+            amount,
+            beneficiary,
+            schedule_id,
+        } => json!({
+            "tx_hash": hex::encode(tx_hash),
+            "amount": amount,
+            "beneficiary": beneficiary.to_string(),
+            "schedule_id": schedule_id,
+        }),
+        SetTokenRoute {
+            mint,
+            token_program,
+        } => json!({ "mint": mint.to_string(), "token_program": token_program.to_string() }),
+    }
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(ToString::to_string).collect()
+}
+
+/// Accounts of `SettleInfos`, the ones of a wallet recipient last.
+fn settle_names(to_address_type: AddressType) -> Vec<String> {
+    let mut settle = names(&[
+        "wallet",
+        "wallet_signer",
+        "fee_payer",
+        "wallet_program",
+        "mint",
+        "to",
+        "mint_auth",
+        "token_program",
+        "system_program",
+        "teleport_program",
+        "rent_sysvar",
+        "denylist",
+    ]);
+    if to_address_type == AddressType::Sol {
+        settle.extend(names(&["to_owner", "associated_token_program", "gas_tank"]));
+    }
+    settle
+}
+
+/// Accounts of a teleport out to a recipient, a token account recipient
+/// brings the denylist of its owner.
+fn recipient_names(to_address_type: AddressType) -> Vec<String> {
+    let mut all = settle_names(to_address_type);
+    if to_address_type == AddressType::Spl {
+        all.push("to_owner_denylist".to_string());
+    }
+    all
+}
+
+/// Account names of `instruction` in the order of its processor, `count`
+/// being the number of accounts passed for the ones taking any number.
+fn teleport_account_names(instruction: &TeleportInstruction, count: usize) -> Vec<String> {
+    use TeleportInstruction::*;
+    let owner_config = || names(&["owner", "config"]);
+    let admin_auth = || names(&["config", "admin", "admin_auth"]);
+    match instruction {
+        GetOwner => vec![],
+        InitConfig => names(&["owner", "config", "rent_sysvar"]),
+        InitAdmin { .. } => names(&["owner", "admin", "config"]),
+        InitTeleportOutRecord => names(&["record"]),
+        AddAdmin { .. }
+        | RemoveAdmin { .. }
+        | Freeze
+        | Unfreeze
+        | SetGasDrop { .. }
+        | SetDelayedSettlement { .. }
+        | SetPauser { .. }
+        | SetRecordRetention { .. }
+        | SetAmountLimits { .. }
+        | SetChainEnabled { .. } => owner_config(),
+        TeleportIn { .. } => names(&[
+            "config",
+            "wallet",
+            "wallet_pda",
+            "wallet_signer",
+            "wallet_program",
+            "from",
+            "mint",
+            "from_auth",
+            "token_program",
+            "from_auth_denylist",
+            "to_denylist",
+            "receipt",
+            "payer",
+            "system_program",
+        ]),
+        TeleportOut {
+            to_address_type,
+            payload,
+            ..
+        } => {
+            let mut all = admin_auth();
+            all.extend(names(&["record", "pending"]));
+            all.extend(recipient_names(*to_address_type));
+            if payload.is_some() {
+                all.extend(names(&["receiver_entry", "caller", "receiver_program"]));
+                let forwarded = count.saturating_sub(all.len());
+                all.extend((0..forwarded).map(|index| format!("forwarded_{}", index)));
+            }
+            all
+        }
+        DepositAllowance { .. } | SetAllowance { .. } | DecreaseAllowance { .. } => {
+            names(&["owner", "admin", "config"])
+        }
+        CloseTeleportOutRecord => {
+            let mut all = admin_auth();
+            all.extend(names(&["record", "target"]));
+            all
+        }
+        TeleportOutBatch { entries } => {
+            let mut all = admin_auth();
+            all.extend(names(&[
+                "wallet",
+                "wallet_signer",
+                "fee_payer",
+                "wallet_program",
+                "mint",
+                "mint_auth",
+                "token_program",
+                "system_program",
+                "teleport_program",
+                "rent_sysvar",
+            ]));
+            for index in 0..entries.len() {
+                for name in ["record", "to", "denylist", "to_owner_denylist"] {
+                    all.push(format!("{}_{}", name, index));
+                }
+            }
+            all
+        }
+        RefillGasTank { .. } => names(&["owner", "gas_tank", "system_program"]),
+        WithdrawGasTank { .. } => names(&["owner", "gas_tank", "target", "system_program"]),
+        FinalizeTeleportOut => {
+            // the address type is in the pending account, a wallet recipient
+            // brings 2 more accounts
+            let mut all = names(&["config", "pending", "payer"]);
+            let to_address_type = if count >= all.len() + recipient_names(AddressType::Sol).len() {
+                AddressType::Sol
+            } else {
+                AddressType::Spl
+            };
+            all.extend(recipient_names(to_address_type));
+            all
+        }
+        CancelTeleportOut => names(&["authority", "config", "admin", "pending", "payer"]),
+        CloseExpiredTeleportOutRecords => {
+            let mut all = names(&["config"]);
+            for index in 0..count.saturating_sub(1) / 2 {
+                all.push(format!("record_{}", index));
+                all.push(format!("payer_{}", index));
+            }
+            all
+        }
+        CheckInvariants => names(&["config", "mint"]),
+        RotateAdminAuth { .. } => names(&["owner", "admin", "auth"]),
+        CloseAdmin => names(&["owner", "config", "admin", "target"]),
+        MigrateConfig => names(&["owner", "config", "system_program", "mint"]),
+        MigrateAdmin => names(&["owner", "admin", "system_program", "config"]),
+        AddToDenylist { .. } => names(&["owner", "denylist", "system_program"]),
+        RemoveFromDenylist { .. } => names(&["owner", "denylist"]),
+        AddReceiver { .. } => names(&["owner", "receiver_entry", "system_program"]),
+        RemoveReceiver { .. } => names(&["owner", "receiver_entry"]),
+        SetLockupSchedule { .. } => names(&["owner", "schedule", "system_program"]),
+        TeleportOutVesting { .. } => {
+            let mut all = admin_auth();
+            all.extend(names(&[
+                "record",
+                "schedule",
+                "vesting",
+                "denylist",
+                "vault_authority",
+                "associated_token_program",
+            ]));
+            all.extend(settle_names(AddressType::Spl));
+            all
+        }
+        ClaimVesting => names(&[
+            "beneficiary",
+            "vesting",
+            "denylist",
+            "vault_authority",
+            "vault",
+            "to",
+            "payer",
+            "token_program",
+        ]),
+        MarkTeleportInUnlocked => {
+            let mut all = admin_auth();
+            all.push("receipt".to_string());
+            all
+        }
+        RefundTeleportIn => {
+            let mut all = admin_auth();
+            all.push("receipt".to_string());
+            all.extend(recipient_names(AddressType::Spl));
+            all
+        }
+        SetTokenRoute { .. } => names(&["owner", "config", "mint"]),
+    }
+}
+
+/// The multisig wallet invoke instruction, see `instruction::wallet_invoke`,
+/// with the call it wraps.
+fn wallet_invoke(instruction: &Instruction, program_id: &Pubkey) -> Option<Value> {
+    let data = &instruction.data;
+    if data.len() < 4 || data[0] != 3 {
+        return None;
+    }
+    let account = |index: u8| instruction.accounts.get(index as usize);
+    let invoked_program = account(data[1])?.pubkey;
+    let total = u16::from_le_bytes(data[2..4].try_into().ok()?) as usize;
+    let pairs = data.get(4..4 + total * 2)?;
+    let inner = Instruction {
+        program_id: invoked_program,
+        accounts: pairs
+            .chunks_exact(2)
+            .map(|pair| {
+                Some(AccountMeta {
+                    pubkey: account(pair[0])?.pubkey,
+                    is_signer: pair[1] & 2 != 0,
+                    is_writable: pair[1] & 1 != 0,
+                })
+            })
+            .collect::<Option<_>>()?,
+        data: data[4 + total * 2..].to_vec(),
+    };
+    let inner = self::instruction(&inner, program_id);
+
+    // accounts the call takes are named after its own names
+    let mut names = vec![String::new(); instruction.accounts.len()];
+    for (pair, inner_account) in pairs.chunks_exact(2).zip(inner["accounts"].as_array()?) {
+        names[pair[0] as usize] = inner_account["name"].as_str()?.to_string();
+    }
+    names[data[1] as usize] = "invoked_program".to_string();
+    for (index, name) in names.iter_mut().enumerate() {
+        if !name.is_empty() {
+            continue;
+        }
+        *name = match index {
+            0 => "wallet".to_string(),
+            1 => "wallet_pda".to_string(),
+            _ if instruction.accounts[index].is_signer => "wallet_signer".to_string(),
+            _ => format!("account_{}", index),
+        };
+    }
+    Some(json!({
+        "program": "multisig",
+        "program_id": instruction.program_id.to_string(),
+        "instruction": "Invoke",
+        "args": {},
+        "accounts": accounts(&instruction.accounts, &names),
+        "inner": inner,
+    }))
+}
+
+/// Token instructions of the base layout, shared by SPL Token and
+/// Token-2022.
+fn token_instruction(instruction: &Instruction, program_id: &Pubkey) -> Option<Value> {
+    let decoded = TokenInstruction::unpack(&instruction.data).ok()?;
+    let (names, args) = match &decoded {
+        TokenInstruction::MintTo { amount } => (
+            names(&["mint", "account", "owner"]),
+            json!({ "amount": amount }),
+        ),
+        TokenInstruction::MintToChecked { amount, decimals } => (
+            names(&["mint", "account", "owner"]),
+            json!({ "amount": amount, "decimals": decimals }),
+        ),
+        TokenInstruction::Burn { amount } => (
+            names(&["account", "mint", "owner"]),
+            json!({ "amount": amount }),
+        ),
+        TokenInstruction::BurnChecked { amount, decimals } => (
+            names(&["account", "mint", "owner"]),
+            json!({ "amount": amount, "decimals": decimals }),
+        ),
+        TokenInstruction::Transfer { amount } => (
+            names(&["source", "destination", "owner"]),
+            json!({ "amount": amount }),
+        ),
+        TokenInstruction::TransferChecked { amount, decimals } => (
+            names(&["source", "mint", "destination", "owner"]),
+            json!({ "amount": amount, "decimals": decimals }),
+        ),
+        TokenInstruction::CloseAccount => (names(&["account", "destination", "owner"]), json!({})),
+        _ => (vec![], json!({})),
+    };
+    Some(json!({
+        "program": program_label(&instruction.program_id, program_id),
+        "program_id": instruction.program_id.to_string(),
+        "instruction": variant_name(&decoded),
+        "args": args,
+        "accounts": accounts(&instruction.accounts, &names),
+    }))
+}
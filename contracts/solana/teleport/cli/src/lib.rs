@@ -0,0 +1,5 @@
+pub mod args;
+pub mod command;
+pub mod decode;
+pub mod output;
+pub mod parse;
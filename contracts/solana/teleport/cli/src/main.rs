@@ -0,0 +1,76 @@
+use {
+    blt_teleport_cli::{
+        args::{Cli, Command},
+        command::{process, read_keypair, CliError, Submit},
+    },
+    blt_teleport_client::TeleportClient,
+    clap::Parser,
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        pubkey::Pubkey,
+        signature::{Keypair, NullSigner, Signer},
+    },
+    std::{path::PathBuf, process::exit, str::FromStr},
+};
+
+/// Where `solana-keygen new` writes the default keypair.
+fn default_keypair_path() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    [&home, ".config", "solana", "id.json"].iter().collect()
+}
+
+/// The owner keypair file, or only its pubkey when signing offline.
+fn owner_signer(owner: &str, sign_only: bool) -> Result<Box<dyn Signer>, CliError> {
+    match Pubkey::from_str(owner) {
+        Ok(pubkey) if sign_only => Ok(Box::new(NullSigner::new(&pubkey))),
+        Ok(_) => Err(CliError::Keypair(
+            owner.to_string(),
+            "a pubkey owner only works with --sign-only".to_string(),
+        )),
+        Err(_) => Ok(Box::new(read_keypair(owner.as_ref())?)),
+    }
+}
+
+async fn run(cli: Cli) -> Result<serde_json::Value, CliError> {
+    // reading accounts and signing offline don't need a funded fee payer
+    let payer = match (&cli.command, &cli.keypair) {
+        (Command::Show(_), None)
+        | (Command::SignMessage { .. }, None)
+        | (Command::Decode(_), None) => Keypair::new(),
+        (_, keypair) => read_keypair(&keypair.clone().unwrap_or_else(default_keypair_path))?,
+    };
+    let owner: Box<dyn Signer> = match &cli.owner {
+        Some(owner) => owner_signer(owner, cli.sign_only)?,
+        None => Box::new(payer.insecure_clone()),
+    };
+    let nonce_authority: Box<dyn Signer> = match &cli.nonce_authority {
+        Some(path) => Box::new(read_keypair(path)?),
+        None => Box::new(payer.insecure_clone()),
+    };
+    let submit = Submit {
+        dry_run: cli.dry_run,
+        sign_only: cli.sign_only,
+        nonce: cli.nonce.map(|nonce| (nonce, nonce_authority.as_ref())),
+        owner_wallet: cli
+            .owner_wallet
+            .map(|wallet| (wallet, cli.owner_wallet_pda)),
+    };
+
+    let rpc = RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed());
+    let mut client = TeleportClient::new(rpc, cli.program_id, payer);
+    process(&mut client, owner.as_ref(), &cli.command, &submit).await
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(output) => println!("{}", serde_json::to_string_pretty(&output).unwrap()),
+        Err(e) => {
+            let output = serde_json::json!({ "error": e.to_string() });
+            eprintln!("{}", serde_json::to_string_pretty(&output).unwrap());
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! JSON output of program accounts
+
+use {
+    blt_teleport::{
+        instruction::AddressType,
+        lockup::{LockupSchedule, LockupStep, Vesting},
+        state::{
+            Admin, AmountLimits, Config, PendingTeleportOut, TeleportInReceipt, TeleportInStatus,
+            TeleportOutRecord,
+        },
+    },
+    blt_teleport_client::{
+        offline::{self, OfflineSignature},
+        Simulation,
+    },
+    serde_json::{json, Value},
+    solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::Transaction},
+};
+
+pub(crate) fn limits(limits: &AmountLimits) -> Value {
+    json!({ "min": limits.min, "max": limits.max })
+}
+
+pub(crate) fn address_type(address_type: AddressType) -> &'static str {
+    match address_type {
+        AddressType::Sol => "sol",
+        AddressType::Spl => "spl",
+    }
+}
+
+pub fn config(address: &Pubkey, config: &Config) -> Value {
+    let admins: Vec<String> = config
+        .admins
+        .iter()
+        .filter(|admin| **admin != Pubkey::default())
+        .map(Pubkey::to_string)
+        .collect();
+    json!({
+        "address": address.to_string(),
+        "version": config.version,
+        "is_init": config.is_init,
+        "is_frozen": config.is_frozen,
+        "admins": admins,
+        "gas_drop_per_transfer": config.gas_drop_per_transfer,
+        "gas_drop_daily_cap": config.gas_drop_daily_cap,
+        "gas_drop_day": config.gas_drop_day,
+        "gas_dropped_today": config.gas_dropped_today,
+        "delay_threshold": config.delay_threshold,
+        "challenge_delay": config.challenge_delay,
+        "pauser": config.pauser.to_string(),
+        "record_retention": config.record_retention,
+        "total_minted": config.total_minted,
+        "total_burned": config.total_burned,
+        "total_fees": config.total_fees,
+        "total_allowance_granted": config.total_allowance_granted,
+        "teleport_in_limits": limits(&config.teleport_in_limits),
+        "teleport_out_limits": limits(&config.teleport_out_limits),
+        "enabled_chains": config.enabled_chains,
+        "mint": config.mint().to_string(),
+        "token_program": config.token_program().to_string(),
+    })
+}
+
+pub fn admin(address: &Pubkey, admin: &Admin) -> Value {
+    json!({
+        "address": address.to_string(),
+        "version": admin.version,
+        "is_init": admin.is_init,
+        "auth": admin.auth.to_string(),
+        "allowance": admin.allowance,
+    })
+}
+
+pub fn teleport_out(
+    tx_hash: &[u8; 32],
+    record: Option<&TeleportOutRecord>,
+    pending: Option<&PendingTeleportOut>,
+) -> Value {
+    json!({
+        "tx_hash": hex::encode(tx_hash),
+        "record": record.map(|record| json!({
+            "version": record.version,
+            "is_init": record.is_init,
+            "payer": record.payer.to_string(),
+            "created_at": record.created_at,
+        })),
+        "pending": pending.map(|pending| json!({
+            "admin": pending.admin.to_string(),
+            "payer": pending.payer.to_string(),
+            "to": pending.to.to_string(),
+            "to_address_type": address_type(pending.to_address_type),
+            "amount": pending.amount,
+            "unlock_time": pending.unlock_time,
+        })),
+    })
+}
+
+pub fn teleport_in_receipt(receipt: &TeleportInReceipt) -> Value {
+    let status = match receipt.status {
+        TeleportInStatus::Pending => "pending",
+        TeleportInStatus::Unlocked => "unlocked",
+        TeleportInStatus::Refunded => "refunded",
+    };
+    json!({
+        "from_auth": receipt.from_auth.to_string(),
+        "nonce": receipt.nonce,
+        "from": receipt.from.to_string(),
+        "amount": receipt.amount,
+        "status": status,
+        "created_at": receipt.created_at,
+        "payer": receipt.payer.to_string(),
+    })
+}
+
+fn lockup_steps(steps: &[LockupStep]) -> Vec<Value> {
+    steps
+        .iter()
+        .map(|step| json!({ "timestamp": step.timestamp, "locked_ratio": step.locked_ratio }))
+        .collect()
+}
+
+pub fn lockup_schedule(schedule: &LockupSchedule) -> Value {
+    json!({ "id": schedule.id, "steps": lockup_steps(schedule.steps()) })
+}
+
+pub fn vesting(vesting: &Vesting) -> Value {
+    json!({
+        "tx_hash": hex::encode(vesting.tx_hash),
+        "beneficiary": vesting.beneficiary.to_string(),
+        "schedule_id": vesting.schedule_id,
+        "steps": lockup_steps(vesting.steps()),
+        "amount": vesting.amount,
+        "claimed": vesting.claimed,
+        "payer": vesting.payer.to_string(),
+        "mint": vesting.mint.to_string(),
+    })
+}
+
+pub fn simulation(simulation: &Simulation) -> Value {
+    json!({
+        "dry_run": true,
+        "ok": simulation.is_ok(),
+        "error": simulation.err.as_ref().map(|err| err.to_string()),
+        "teleport_error": simulation.teleport_error().map(|err| err.to_string()),
+        "units_consumed": simulation.units_consumed,
+        "logs": simulation.logs,
+    })
+}
+
+/// The message of a transaction to sign offline, with the signatures made so
+/// far.
+pub fn sign_only(transaction: &Transaction) -> Value {
+    let message = &transaction.message;
+    let signers: Vec<String> = offline::signer_keys(message)
+        .iter()
+        .map(Pubkey::to_string)
+        .collect();
+    let signatures: Vec<String> = offline::signer_keys(message)
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature != Signature::default())
+        .map(|(pubkey, signature)| {
+            OfflineSignature {
+                pubkey: *pubkey,
+                signature: *signature,
+            }
+            .to_string()
+        })
+        .collect();
+    json!({
+        "message": offline::encode_message(message),
+        "blockhash": message.recent_blockhash.to_string(),
+        "signers": signers,
+        "signatures": signatures,
+    })
+}
@@ -0,0 +1,94 @@
+//! Argument parsers
+
+use {
+    blt_teleport::{
+        instruction::{AddressType, Chain, TeleportOutEntry},
+        lockup::LockupStep,
+    },
+    solana_sdk::{instruction::AccountMeta, pubkey::Pubkey},
+    std::{convert::TryInto, str::FromStr},
+};
+
+/// Hex, with or without a `0x` prefix.
+pub fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|e| e.to_string())
+}
+
+fn parse_hex_array<const N: usize>(value: &str) -> Result<[u8; N], String> {
+    parse_hex(value)?
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected {} bytes, got {}", N, bytes.len()))
+}
+
+/// Flow tx hash in hex.
+pub fn parse_tx_hash(value: &str) -> Result<[u8; 32], String> {
+    parse_hex_array(value)
+}
+
+pub fn parse_flow_address(value: &str) -> Result<[u8; 8], String> {
+    parse_hex_array(value)
+}
+
+pub fn parse_evm_address(value: &str) -> Result<[u8; 20], String> {
+    parse_hex_array(value)
+}
+
+pub fn parse_chain(value: &str) -> Result<Chain, String> {
+    match value {
+        "flow" => Ok(Chain::Flow),
+        "ethereum" => Ok(Chain::Ethereum),
+        "bsc" => Ok(Chain::Bsc),
+        _ => Err(format!(
+            "unknown chain {}, expected flow, ethereum or bsc",
+            value
+        )),
+    }
+}
+
+pub fn parse_address_type(value: &str) -> Result<AddressType, String> {
+    match value {
+        "sol" => Ok(AddressType::Sol),
+        "spl" => Ok(AddressType::Spl),
+        _ => Err(format!(
+            "unknown address type {}, expected sol or spl",
+            value
+        )),
+    }
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value).map_err(|e| e.to_string())
+}
+
+/// `TIMESTAMP:LOCKED_RATIO`, the ratio in UFix64.
+pub fn parse_lockup_step(value: &str) -> Result<LockupStep, String> {
+    let (timestamp, locked_ratio) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected TIMESTAMP:LOCKED_RATIO, got {}", value))?;
+    Ok(LockupStep {
+        timestamp: timestamp.parse().map_err(|e| format!("{}", e))?,
+        locked_ratio: locked_ratio.parse().map_err(|e| format!("{}", e))?,
+    })
+}
+
+/// `TX_HASH:AMOUNT:RECIPIENT`, the amount in UFix64.
+pub fn parse_teleport_out_entry(value: &str) -> Result<TeleportOutEntry, String> {
+    let parts: Vec<&str> = value.split(':').collect();
+    if parts.len() != 3 {
+        return Err(format!("expected TX_HASH:AMOUNT:RECIPIENT, got {}", value));
+    }
+    Ok(TeleportOutEntry {
+        tx_hash: parse_tx_hash(parts[0])?,
+        amount: parts[1].parse().map_err(|e| format!("{}", e))?,
+        recipient: parse_pubkey(parts[2])?,
+    })
+}
+
+/// `PUBKEY`, or `PUBKEY:w` for a writable account.
+pub fn parse_account_meta(value: &str) -> Result<AccountMeta, String> {
+    match value.split_once(':') {
+        Some((pubkey, "w")) => Ok(AccountMeta::new(parse_pubkey(pubkey)?, false)),
+        Some(_) => Err(format!("expected PUBKEY or PUBKEY:w, got {}", value)),
+        None => Ok(AccountMeta::new_readonly(parse_pubkey(value)?, false)),
+    }
+}
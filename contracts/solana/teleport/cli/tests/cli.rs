@@ -0,0 +1,610 @@
+#![cfg(feature = "test-bpf")]
+
+use {
+    base64::{engine::general_purpose::STANDARD, Engine},
+    blt_teleport::instruction::{self, Chain},
+    blt_teleport_cli::{
+        args::{Cli, Command},
+        command::{process, Submit},
+    },
+    blt_teleport_client::{offline, TeleportClient, TeleportRpc},
+    clap::Parser,
+    serde_json::{json, Value},
+    solana_program_test::*,
+    solana_sdk::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        nonce::State,
+        program::invoke_signed,
+        pubkey::Pubkey,
+        signature::{write_keypair_file, Keypair, NullSigner, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+    std::{path::PathBuf, str::FromStr},
+};
+
+fn get_owner() -> Keypair {
+    Keypair::from_bytes(&[
+        216, 77, 18, 252, 166, 244, 245, 106, 13, 111, 107, 26, 108, 43, 230, 245, 113, 207, 205,
+        116, 14, 52, 64, 196, 192, 63, 41, 220, 146, 82, 66, 53, 161, 111, 219, 59, 147, 221, 7,
+        127, 88, 42, 141, 30, 211, 69, 198, 108, 142, 249, 183, 249, 92, 127, 241, 91, 118, 190,
+        46, 20, 186, 220, 132, 23,
+    ])
+    .unwrap()
+}
+
+// Writes a new keypair file under the temp dir.
+fn new_keypair_file() -> (Keypair, PathBuf) {
+    let keypair = Keypair::new();
+    let path = std::env::temp_dir().join(format!("blt-teleport-{}.json", keypair.pubkey()));
+    write_keypair_file(&keypair, &path).unwrap();
+    (keypair, path)
+}
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "blt_teleport",
+        blt_teleport::id(),
+        processor!(blt_teleport::processor::Processor::process_instruction),
+    );
+    program_test.add_program(
+        "multisig",
+        multisig_program_id(),
+        processor!(process_mock_multisig),
+    );
+    program_test
+}
+
+fn multisig_program_id() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::MULTISIG_PROGRAM_KEY).unwrap()
+}
+
+// Stand-in for the multisig wallet program, it runs the invoke instruction
+// signed by the PDA of the wallet without checking the wallet signers.
+fn process_mock_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    assert_eq!(input[0], 3);
+    let program_info = &accounts[input[1] as usize];
+    let total = u16::from_le_bytes([input[2], input[3]]) as usize;
+
+    let mut metas = Vec::with_capacity(total);
+    let mut infos = Vec::with_capacity(total + 1);
+    for pair in input[4..4 + total * 2].chunks_exact(2) {
+        let info = &accounts[pair[0] as usize];
+        metas.push(AccountMeta {
+            pubkey: *info.key,
+            is_signer: pair[1] & 2 != 0,
+            is_writable: pair[1] & 1 != 0,
+        });
+        infos.push(info.clone());
+    }
+    infos.push(program_info.clone());
+
+    let wallet_key = accounts[0].key;
+    let (_, bump) = Pubkey::find_program_address(&[wallet_key.as_ref()], program_id);
+    invoke_signed(
+        &Instruction {
+            program_id: *program_info.key,
+            accounts: metas,
+            data: input[4 + total * 2..].to_vec(),
+        },
+        &infos,
+        &[&[wallet_key.as_ref(), &[bump]]],
+    )
+}
+
+async fn start() -> TeleportClient<BanksClient> {
+    let (banks_client, payer, _) = program_test().start().await;
+    TeleportClient::new(banks_client, blt_teleport::id(), payer)
+}
+
+// Parses `args` as the command line and runs it with the test owner.
+async fn run(client: &mut TeleportClient<BanksClient>, args: &[&str]) -> Value {
+    run_as(client, &get_owner(), args).await
+}
+
+// Parses `args` as the command line and runs it with `owner`, the fee payer
+// being the nonce authority.
+async fn run_as(
+    client: &mut TeleportClient<BanksClient>,
+    owner: &dyn Signer,
+    args: &[&str],
+) -> Value {
+    let cli = Cli::try_parse_from(["blt-teleport"].iter().chain(args)).unwrap();
+    let payer = client.payer().insecure_clone();
+    let submit = Submit {
+        dry_run: cli.dry_run,
+        sign_only: cli.sign_only,
+        nonce: cli.nonce.map(|nonce| (nonce, &payer as &dyn Signer)),
+        owner_wallet: cli
+            .owner_wallet
+            .map(|wallet| (wallet, cli.owner_wallet_pda)),
+    };
+    process(client, owner, &cli.command, &submit).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_init_and_show() {
+    let mut client = start().await;
+
+    let (config, config_path) = new_keypair_file();
+    let output = run(
+        &mut client,
+        &[
+            "init-config",
+            "--config-keypair",
+            config_path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    assert_eq!(output["account"], config.pubkey().to_string());
+    assert!(output["signature"].is_string());
+    let config = config.pubkey().to_string();
+
+    let (admin, admin_path) = new_keypair_file();
+    let auth = Pubkey::new_unique().to_string();
+    run(
+        &mut client,
+        &[
+            "init-admin",
+            "--config",
+            &config,
+            "--admin-keypair",
+            admin_path.to_str().unwrap(),
+            "--auth",
+            &auth,
+            "--allowance",
+            "1000",
+        ],
+    )
+    .await;
+    let admin = admin.pubkey().to_string();
+    run(
+        &mut client,
+        &["add-admin", "--config", &config, "--admin", &admin],
+    )
+    .await;
+    run(
+        &mut client,
+        &[
+            "deposit-allowance",
+            "--config",
+            &config,
+            "--admin",
+            &admin,
+            "--allowance",
+            "500",
+        ],
+    )
+    .await;
+
+    let output = run(&mut client, &["show", "config", "--config", &config]).await;
+    assert_eq!(output["is_init"], true);
+    assert_eq!(output["is_frozen"], false);
+    assert_eq!(output["admins"], serde_json::json!([admin]));
+    assert_eq!(output["total_allowance_granted"], 1500);
+    assert_eq!(output["token_program"], spl_token::id().to_string());
+
+    let output = run(&mut client, &["show", "admin", "--admin", &admin]).await;
+    assert_eq!(output["auth"], auth);
+    assert_eq!(output["allowance"], 1500);
+
+    let output = run(
+        &mut client,
+        &["show", "teleport-out", "--tx-hash", &"01".repeat(32)],
+    )
+    .await;
+    assert_eq!(output["record"], Value::Null);
+    assert_eq!(output["pending"], Value::Null);
+}
+
+#[tokio::test]
+async fn test_dry_run() {
+    let mut client = start().await;
+
+    let (config, config_path) = new_keypair_file();
+    run(
+        &mut client,
+        &[
+            "init-config",
+            "--config-keypair",
+            config_path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    let config = config.pubkey().to_string();
+
+    let output = run(&mut client, &["--dry-run", "freeze", "--config", &config]).await;
+    assert_eq!(output["dry_run"], true);
+    assert_eq!(output["ok"], true);
+    assert!(output.get("signature").is_none());
+    let output = run(&mut client, &["show", "config", "--config", &config]).await;
+    assert_eq!(output["is_frozen"], false);
+
+    run(&mut client, &["freeze", "--config", &config]).await;
+    let output = run(&mut client, &["show", "config", "--config", &config]).await;
+    assert_eq!(output["is_frozen"], true);
+
+    // an admin auth that isn't on the config
+    let (_, auth_path) = new_keypair_file();
+    let output = run(
+        &mut client,
+        &[
+            "--dry-run",
+            "close-teleport-out-record",
+            "--config",
+            &config,
+            "--admin",
+            &Pubkey::new_unique().to_string(),
+            "--auth",
+            auth_path.to_str().unwrap(),
+            "--tx-hash",
+            &"02".repeat(32),
+        ],
+    )
+    .await;
+    assert_eq!(output["ok"], false);
+    assert!(output["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_offline_owner() {
+    let mut context = program_test().start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut client = TeleportClient::new(
+        context.banks_client.clone(),
+        blt_teleport::id(),
+        payer.insecure_clone(),
+    );
+
+    let (config, config_path) = new_keypair_file();
+    run(
+        &mut client,
+        &[
+            "init-config",
+            "--config-keypair",
+            config_path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    let config = config.pubkey().to_string();
+
+    let nonce = Keypair::new();
+    let lamports = client
+        .rpc()
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await
+        .unwrap();
+    let instructions = system_instruction::create_nonce_account(
+        &payer.pubkey(),
+        &nonce.pubkey(),
+        &payer.pubkey(),
+        lamports,
+    );
+    client.send(&instructions, &[&nonce]).await.unwrap();
+    context.warp_to_slot(2).unwrap();
+    let nonce = nonce.pubkey().to_string();
+
+    // the owner key isn't here, only its pubkey
+    let owner = get_owner();
+    let exported = run_as(
+        &mut client,
+        &NullSigner::new(&owner.pubkey()),
+        &[
+            "--nonce",
+            &nonce,
+            "--sign-only",
+            "freeze",
+            "--config",
+            &config,
+        ],
+    )
+    .await;
+    assert_eq!(
+        exported["signers"],
+        serde_json::json!([payer.pubkey().to_string(), owner.pubkey().to_string()])
+    );
+    // the fee payer signed already
+    let signatures = exported["signatures"].as_array().unwrap();
+    assert_eq!(signatures.len(), 1);
+    assert!(signatures[0]
+        .as_str()
+        .unwrap()
+        .starts_with(&payer.pubkey().to_string()));
+    let message = exported["message"].as_str().unwrap();
+
+    // on the offline machine
+    let owner_path = std::env::temp_dir().join(format!("blt-teleport-{}.json", owner.pubkey()));
+    write_keypair_file(&owner, &owner_path).unwrap();
+    let signed = run(
+        &mut client,
+        &[
+            "sign-message",
+            "--message",
+            message,
+            "--signer",
+            owner_path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    let owner_signature = signed["offline_signature"].as_str().unwrap();
+
+    context.warp_to_slot(400).unwrap();
+    let output = run(
+        &mut client,
+        &[
+            "send-message",
+            "--message",
+            message,
+            "--signature",
+            owner_signature,
+        ],
+    )
+    .await;
+    assert!(output["signature"].is_string());
+    let output = run(&mut client, &["show", "config", "--config", &config]).await;
+    assert_eq!(output["is_frozen"], true);
+}
+
+#[tokio::test]
+async fn test_owner_wallet() {
+    let mut client = start().await;
+
+    let (config, config_path) = new_keypair_file();
+    run(
+        &mut client,
+        &[
+            "init-config",
+            "--config-keypair",
+            config_path.to_str().unwrap(),
+        ],
+    )
+    .await;
+    let config = config.pubkey().to_string();
+
+    // the owner wallet of the test build, its PDA is the owner
+    let wallet = Pubkey::new_from_array([8; 32]).to_string();
+    let wallet_signer = Keypair::new();
+    let admin = Pubkey::new_unique().to_string();
+    run_as(
+        &mut client,
+        &wallet_signer,
+        &[
+            "--owner-wallet",
+            &wallet,
+            "add-admin",
+            "--config",
+            &config,
+            "--admin",
+            &admin,
+        ],
+    )
+    .await;
+
+    let output = run(&mut client, &["show", "config", "--config", &config]).await;
+    assert_eq!(output["admins"], serde_json::json!([admin]));
+}
+
+#[tokio::test]
+async fn test_decode() {
+    let mut client = start().await;
+    let program_id = blt_teleport::id();
+    let payer = client.payer().pubkey();
+
+    // an owner instruction through the owner wallet, then a teleport in
+    let wallet = Pubkey::new_unique();
+    let wallet_pda = Pubkey::new_unique();
+    let wallet_signer = Pubkey::new_unique();
+    let config = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let add_admin = instruction::add_admin(&program_id, &wallet_pda, &config, &admin).unwrap();
+    let invoke =
+        instruction::wallet_invoke(&wallet, &wallet_pda, &[wallet_signer], &add_admin).unwrap();
+    let from = Pubkey::new_unique();
+    let from_auth = Pubkey::new_unique();
+    let teleport_in = instruction::teleport_in(
+        &program_id,
+        &config,
+        &wallet,
+        &Pubkey::new_unique(),
+        &from,
+        &Pubkey::new_unique(),
+        &spl_token::id(),
+        &from_auth,
+        &payer,
+        7,
+        150_000_000,
+        Chain::Flow,
+        &[1, 2, 3, 4, 5, 6, 7, 8],
+        None,
+    )
+    .unwrap();
+    let transaction = Transaction::new_with_payer(&[invoke, teleport_in], Some(&payer));
+    let encoded = STANDARD.encode(bincode::serialize(&transaction).unwrap());
+
+    let output = run(&mut client, &["decode", "--transaction", &encoded]).await;
+    assert_eq!(output["fee_payer"], payer.to_string());
+    let instructions = output["instructions"].as_array().unwrap();
+    assert_eq!(instructions.len(), 2);
+
+    let invoke = &instructions[0];
+    assert_eq!(invoke["program"], "multisig");
+    assert_eq!(invoke["accounts"][0]["name"], "wallet");
+    assert_eq!(invoke["accounts"][2]["name"], "invoked_program");
+    assert_eq!(invoke["accounts"][3]["name"], "wallet_signer");
+    let inner = &invoke["inner"];
+    assert_eq!(inner["program"], "teleport");
+    assert_eq!(inner["instruction"], "AddAdmin");
+    assert_eq!(inner["args"], json!({ "admin": admin.to_string() }));
+    assert_eq!(
+        inner["accounts"],
+        json!([
+            { "name": "owner", "pubkey": wallet_pda.to_string(), "signer": true, "writable": true },
+            { "name": "config", "pubkey": config.to_string(), "signer": false, "writable": true },
+        ])
+    );
+
+    let teleport_in = &instructions[1];
+    assert_eq!(teleport_in["instruction"], "TeleportIn");
+    assert_eq!(
+        teleport_in["args"],
+        json!({
+            "amount": 150_000_000,
+            "to": "0102030405060708",
+            "chain": "flow",
+            "payload": null,
+            "nonce": 7,
+        })
+    );
+    let accounts = teleport_in["accounts"].as_array().unwrap();
+    assert_eq!(accounts.len(), 14);
+    assert_eq!(accounts[5]["name"], "from");
+    assert_eq!(accounts[5]["pubkey"], from.to_string());
+    assert_eq!(accounts[7]["name"], "from_auth");
+    assert_eq!(accounts[7]["signer"], true);
+
+    // the same message, as exported by `--sign-only`
+    let message = offline::encode_message(&transaction.message);
+    let output = run(&mut client, &["decode", "--message", &message]).await;
+    assert_eq!(output["instructions"], Value::Array(instructions.clone()));
+
+    // the burn `process_teleport_in` invokes through the wallet, as an
+    // explorer lists it
+    let mut data = vec![3, 2, 3, 0, 4, 1, 5, 1, 6, 2, 15];
+    data.extend(150_000_000u64.to_le_bytes().iter());
+    data.push(8);
+    let accounts = [
+        format!("{}:w", wallet),
+        wallet_pda.to_string(),
+        spl_token::id().to_string(),
+        wallet_signer.to_string(),
+        format!("{}:w", from),
+        format!("{}:w", Pubkey::new_unique()),
+        from_auth.to_string(),
+    ];
+    let mut args = vec![
+        "decode".to_string(),
+        "--data".to_string(),
+        hex::encode(&data),
+        "--program".to_string(),
+        multisig_program_id().to_string(),
+    ];
+    for account in &accounts {
+        args.push("--account".to_string());
+        args.push(account.clone());
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = run(&mut client, &args).await;
+    assert_eq!(output["accounts"][4]["name"], "account");
+    let inner = &output["inner"];
+    assert_eq!(inner["program"], "spl-token");
+    assert_eq!(inner["instruction"], "BurnChecked");
+    assert_eq!(
+        inner["args"],
+        json!({ "amount": 150_000_000, "decimals": 8 })
+    );
+    let names: Vec<&str> = inner["accounts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|account| account["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["account", "mint", "owner"]);
+
+    // data of another instruction or program stays raw
+    let output = run(&mut client, &["decode", "--data", "ff"]).await;
+    assert_eq!(output["instruction"], Value::Null);
+    assert_eq!(output["data"], "ff");
+}
+
+#[test]
+fn test_parse() {
+    let cli = Cli::try_parse_from([
+        "blt-teleport",
+        "add-to-denylist",
+        "--flow",
+        "0x01cf0e2f2f715450",
+    ])
+    .unwrap();
+    assert!(matches!(
+        cli.command,
+        Command::AddToDenylist { ref key } if key.flow == Some([0x01, 0xcf, 0x0e, 0x2f, 0x2f, 0x71, 0x54, 0x50])
+    ));
+
+    // exactly one denylist key
+    assert!(Cli::try_parse_from(["blt-teleport", "add-to-denylist"]).is_err());
+    assert!(Cli::try_parse_from([
+        "blt-teleport",
+        "add-to-denylist",
+        "--flow",
+        "0x01cf0e2f2f715450",
+        "--evm",
+        &"00".repeat(20),
+    ])
+    .is_err());
+
+    // a tx hash is 32 bytes
+    assert!(Cli::try_parse_from([
+        "blt-teleport",
+        "show",
+        "vesting",
+        "--tx-hash",
+        &"00".repeat(31),
+    ])
+    .is_err());
+
+    let cli = Cli::try_parse_from([
+        "blt-teleport",
+        "set-lockup-schedule",
+        "--id",
+        "1",
+        "--step",
+        "1700000000:100000000",
+        "--step",
+        "1800000000:50000000",
+    ])
+    .unwrap();
+    match cli.command {
+        Command::SetLockupSchedule { id, steps } => {
+            assert_eq!(id, 1);
+            assert_eq!(steps.len(), 2);
+            assert_eq!(steps[1].locked_ratio, 50_000_000);
+        }
+        _ => panic!("unexpected command"),
+    }
+}
+
+// Runs the binary against a local validator. The program should be built
+// with the test owner key, deployed, and the test owner funded:
+//
+//     solana-test-validator --bpf-program BLT2FgFauegpkQPXmT9dSKtHevX1dDNPgj4KtKFiDbPq blt_teleport.so
+//     solana airdrop -u localhost 10 BsBZoyMThoCCJAZR2nRyeCa3Tg2TyiDbAaNqeJxkqkHU
+//     cargo test --features test-bpf -- --ignored
+#[test]
+#[ignore]
+fn test_local_validator() {
+    let bin = env!("CARGO_BIN_EXE_blt-teleport");
+    let owner = concat!(env!("CARGO_MANIFEST_DIR"), "/../test-owner.json");
+    let (config, config_path) = new_keypair_file();
+
+    let output = std::process::Command::new(bin)
+        .args(["--keypair", owner, "--owner", owner, "init-config"])
+        .arg("--config-keypair")
+        .arg(&config_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = std::process::Command::new(bin)
+        .args(["show", "config", "--config", &config.pubkey().to_string()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let output: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(output["is_init"], true);
+}
@@ -0,0 +1,428 @@
+//! High level calls to the teleport program
+
+use {
+    crate::{
+        error::ClientError,
+        offline,
+        rpc::{Simulation, TeleportRpc},
+    },
+    blt_teleport::{
+        instruction::{self, AddressType},
+        lockup::{self, LockupSchedule, Vesting},
+        state::{
+            self, Admin, AdminV1, Config, ConfigV1, PendingTeleportOut, TeleportInReceipt,
+            TeleportOutRecord,
+        },
+    },
+    borsh::BorshDeserialize,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        system_instruction,
+        transaction::Transaction,
+    },
+};
+
+/// The multisig wallet minting and burning for a config, and its mint.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MintAuthority {
+    pub wallet: Pubkey,
+    /// Signer of the wallet that holds the mint authority.
+    pub mint_auth: Pubkey,
+    pub mint: Pubkey,
+    /// SPL Token or Token-2022, see `Config::token_program`.
+    pub token_program: Pubkey,
+}
+
+/// Builds, signs and sends teleport instructions through `R`, `payer` pays
+/// the fees and funds the accounts the calls create.
+pub struct TeleportClient<R> {
+    rpc: R,
+    program_id: Pubkey,
+    payer: Keypair,
+}
+
+impl<R: TeleportRpc + Send> TeleportClient<R> {
+    pub fn new(rpc: R, program_id: Pubkey, payer: Keypair) -> Self {
+        Self {
+            rpc,
+            program_id,
+            payer,
+        }
+    }
+
+    pub fn program_id(&self) -> &Pubkey {
+        &self.program_id
+    }
+
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    pub fn rpc(&mut self) -> &mut R {
+        &mut self.rpc
+    }
+
+    /// Account owned by the program, `None` if it doesn't exist.
+    pub async fn fetch_program_account(
+        &mut self,
+        address: &Pubkey,
+    ) -> Result<Option<Account>, ClientError> {
+        match self.rpc.get_account(address).await? {
+            Some(account) if account.owner != self.program_id => {
+                Err(ClientError::InvalidAccount(*address, "program account"))
+            }
+            account => Ok(account),
+        }
+    }
+
+    async fn fetch_existing_account(&mut self, address: &Pubkey) -> Result<Account, ClientError> {
+        self.fetch_program_account(address)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    /// Reads version 1 configs as well, as the program migrates them.
+    pub async fn fetch_config(&mut self, config: &Pubkey) -> Result<Config, ClientError> {
+        let account = self.fetch_existing_account(config).await?;
+        match account.data.len() {
+            Config::LEN => Ok(Config::try_from_slice(&account.data)?),
+            ConfigV1::LEN => Ok(ConfigV1::try_from_slice(&account.data)?.into()),
+            _ => Err(ClientError::InvalidAccount(*config, "config")),
+        }
+    }
+
+    /// Reads version 1 admins as well, as the program migrates them.
+    pub async fn fetch_admin(&mut self, admin: &Pubkey) -> Result<Admin, ClientError> {
+        let account = self.fetch_existing_account(admin).await?;
+        match account.data.len() {
+            Admin::LEN => Ok(Admin::try_from_slice(&account.data)?),
+            AdminV1::LEN => Ok(AdminV1::try_from_slice(&account.data)?.into()),
+            _ => Err(ClientError::InvalidAccount(*admin, "admin")),
+        }
+    }
+
+    pub async fn fetch_teleport_out_record(
+        &mut self,
+        tx_hash: &[u8; 32],
+    ) -> Result<Option<TeleportOutRecord>, ClientError> {
+        let address = state::get_teleport_out_record_address(&self.program_id, tx_hash);
+        match self.fetch_program_account(&address).await? {
+            Some(account) => Ok(Some(TeleportOutRecord::unpack(&account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether a teleport out of the Flow tx hash went through, pending ones
+    /// included. Closed records read as unprocessed, see
+    /// `Config::record_retention`.
+    pub async fn is_tx_hash_processed(&mut self, tx_hash: &[u8; 32]) -> Result<bool, ClientError> {
+        Ok(self
+            .fetch_teleport_out_record(tx_hash)
+            .await?
+            .is_some_and(|record| record.is_init))
+    }
+
+    pub async fn fetch_pending_teleport_out(
+        &mut self,
+        tx_hash: &[u8; 32],
+    ) -> Result<Option<PendingTeleportOut>, ClientError> {
+        let address = state::get_pending_teleport_out_address(&self.program_id, tx_hash);
+        match self.fetch_program_account(&address).await? {
+            Some(account) => Ok(Some(PendingTeleportOut::try_from_slice(&account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn fetch_teleport_in_receipt(
+        &mut self,
+        from_auth: &Pubkey,
+        nonce: u64,
+    ) -> Result<Option<TeleportInReceipt>, ClientError> {
+        let address = state::get_teleport_in_receipt_address(&self.program_id, from_auth, nonce);
+        match self.fetch_program_account(&address).await? {
+            Some(account) => Ok(Some(TeleportInReceipt::try_from_slice(&account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn fetch_lockup_schedule(
+        &mut self,
+        id: u32,
+    ) -> Result<Option<LockupSchedule>, ClientError> {
+        let address = lockup::get_lockup_schedule_address(&self.program_id, id);
+        match self.fetch_program_account(&address).await? {
+            Some(account) => Ok(Some(LockupSchedule::try_from_slice(&account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn fetch_vesting(
+        &mut self,
+        tx_hash: &[u8; 32],
+    ) -> Result<Option<Vesting>, ClientError> {
+        let address = lockup::get_vesting_address(&self.program_id, tx_hash);
+        match self.fetch_program_account(&address).await? {
+            Some(account) => Ok(Some(Vesting::try_from_slice(&account.data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Owner of the token account `to` for `AddressType::Spl`, `to` itself
+    /// for a wallet. A `to` that isn't a token account maps to itself too, so
+    /// the program rejects the transfer.
+    pub async fn fetch_recipient_owner(
+        &mut self,
+        to: &Pubkey,
+        to_address_type: AddressType,
+    ) -> Result<Pubkey, ClientError> {
+        if to_address_type == AddressType::Sol {
+            return Ok(*to);
+        }
+        Ok(match self.rpc.get_account(to).await? {
+            Some(account) => state::get_token_account_owner(&account.data).unwrap_or(*to),
+            None => *to,
+        })
+    }
+
+    /// `signers` with `payer` first, without duplicates.
+    fn with_payer<'a>(&'a self, signers: &[&'a dyn Signer]) -> Vec<&'a dyn Signer> {
+        let mut all_signers: Vec<&dyn Signer> = vec![&self.payer];
+        for signer in signers {
+            if all_signers.iter().all(|s| s.pubkey() != signer.pubkey()) {
+                all_signers.push(*signer);
+            }
+        }
+        all_signers
+    }
+
+    /// Transaction paid by `payer` on the latest blockhash, signed by `payer`
+    /// and `signers`.
+    pub async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&dyn Signer],
+    ) -> Result<Transaction, ClientError> {
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        transaction
+            .try_sign(&self.with_payer(signers), recent_blockhash)
+            .map_err(|e| ClientError::InvalidSignature(e.to_string()))?;
+        Ok(transaction)
+    }
+
+    /// Blockhash of a nonce account advanced by `authority`.
+    pub async fn fetch_nonce(
+        &mut self,
+        nonce: &Pubkey,
+        authority: &Pubkey,
+    ) -> Result<Hash, ClientError> {
+        let account = self
+            .rpc
+            .get_account(nonce)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*nonce))?;
+        offline::nonce_blockhash(nonce, &account, authority)
+    }
+
+    /// Transaction paid by `payer` on the durable nonce, it advances the nonce
+    /// first so it doesn't expire until then. Only `payer` and `signers` sign
+    /// it, the other signatures are collected offline, see
+    /// `offline::assemble_transaction`.
+    pub async fn nonce_transaction(
+        &mut self,
+        instructions: &[Instruction],
+        nonce: &Pubkey,
+        nonce_authority: &Pubkey,
+        signers: &[&dyn Signer],
+    ) -> Result<Transaction, ClientError> {
+        let blockhash = self.fetch_nonce(nonce, nonce_authority).await?;
+        let mut message = Message::new_with_nonce(
+            instructions.to_vec(),
+            Some(&self.payer.pubkey()),
+            nonce,
+            nonce_authority,
+        );
+        message.recent_blockhash = blockhash;
+
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction
+            .try_partial_sign(&self.with_payer(signers), blockhash)
+            .map_err(|e| ClientError::InvalidSignature(e.to_string()))?;
+        Ok(transaction)
+    }
+
+    /// Sends a transaction that's already signed, an assembled offline one
+    /// for instance.
+    pub async fn broadcast(&mut self, transaction: &Transaction) -> Result<Signature, ClientError> {
+        self.rpc.send_transaction(transaction).await
+    }
+
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&dyn Signer],
+    ) -> Result<Signature, ClientError> {
+        let transaction = self.transaction(instructions, signers).await?;
+        self.rpc.send_transaction(&transaction).await
+    }
+
+    /// Runs the instructions without committing anything.
+    pub async fn simulate(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&dyn Signer],
+    ) -> Result<Simulation, ClientError> {
+        let transaction = self.transaction(instructions, signers).await?;
+        self.rpc.simulate_transaction(&transaction).await
+    }
+
+    /// Instruction creating a program account of `len` bytes at `account`.
+    pub async fn create_program_account(
+        &mut self,
+        account: &Pubkey,
+        len: usize,
+    ) -> Result<Instruction, ClientError> {
+        let lamports = self.rpc.get_minimum_balance_for_rent_exemption(len).await?;
+        Ok(system_instruction::create_account(
+            &self.payer.pubkey(),
+            account,
+            lamports,
+            len as u64,
+            &self.program_id,
+        ))
+    }
+
+    /// Creates `config` and initializes it.
+    pub async fn init_config(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Keypair,
+    ) -> Result<Signature, ClientError> {
+        let instructions = [
+            self.create_program_account(&config.pubkey(), Config::LEN)
+                .await?,
+            instruction::init_config(&self.program_id, &owner.pubkey(), &config.pubkey())?,
+        ];
+        self.send(&instructions, &[owner, config]).await
+    }
+
+    /// Creates `admin` for `auth`, `add_admin` lets it act on the config.
+    pub async fn init_admin(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+        admin: &Keypair,
+        auth: &Pubkey,
+        allowance: u64,
+    ) -> Result<Signature, ClientError> {
+        let instructions = [
+            self.create_program_account(&admin.pubkey(), Admin::LEN)
+                .await?,
+            instruction::init_admin(
+                &self.program_id,
+                &owner.pubkey(),
+                config,
+                &admin.pubkey(),
+                auth,
+                allowance,
+            )?,
+        ];
+        self.send(&instructions, &[owner, admin]).await
+    }
+
+    pub async fn add_admin(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+        admin: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction = instruction::add_admin(&self.program_id, &owner.pubkey(), config, admin)?;
+        self.send(&[instruction], &[owner]).await
+    }
+
+    pub async fn remove_admin(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+        admin: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction =
+            instruction::remove_admin(&self.program_id, &owner.pubkey(), config, admin)?;
+        self.send(&[instruction], &[owner]).await
+    }
+
+    pub async fn freeze(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction = instruction::freeze(&self.program_id, &owner.pubkey(), config)?;
+        self.send(&[instruction], &[owner]).await
+    }
+
+    pub async fn unfreeze(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let instruction = instruction::unfreeze(&self.program_id, &owner.pubkey(), config)?;
+        self.send(&[instruction], &[owner]).await
+    }
+
+    /// Adds `allowance` in UFix64 to the admin.
+    pub async fn deposit_allowance(
+        &mut self,
+        owner: &dyn Signer,
+        config: &Pubkey,
+        admin: &Pubkey,
+        allowance: u64,
+    ) -> Result<Signature, ClientError> {
+        let instruction = instruction::deposit_allowance(
+            &self.program_id,
+            &owner.pubkey(),
+            config,
+            admin,
+            allowance,
+        )?;
+        self.send(&[instruction], &[owner]).await
+    }
+
+    /// Mints `amount` in UFix64 to `to` for the Flow tx hash, `payer` funds
+    /// the teleport out record.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn teleport_out(
+        &mut self,
+        admin_auth: &dyn Signer,
+        config: &Pubkey,
+        admin: &Pubkey,
+        mint_authority: &MintAuthority,
+        to: &Pubkey,
+        to_address_type: AddressType,
+        tx_hash: &[u8; 32],
+        amount: u64,
+    ) -> Result<Signature, ClientError> {
+        let to_owner = self.fetch_recipient_owner(to, to_address_type).await?;
+        let instruction = instruction::teleport_out(
+            &self.program_id,
+            config,
+            admin,
+            &admin_auth.pubkey(),
+            &mint_authority.wallet,
+            &self.payer.pubkey(),
+            &mint_authority.mint,
+            &mint_authority.token_program,
+            to,
+            to_address_type,
+            &to_owner,
+            &mint_authority.mint_auth,
+            tx_hash,
+            amount,
+        )?;
+        self.send(&[instruction], &[admin_auth]).await
+    }
+}
@@ -0,0 +1,67 @@
+//! Error types
+
+use {
+    blt_teleport::error::TeleportError,
+    num_traits::FromPrimitive,
+    solana_sdk::{
+        instruction::InstructionError, program_error::ProgramError, pubkey::Pubkey,
+        transaction::TransactionError,
+    },
+    thiserror::Error,
+};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    /// The RPC node couldn't be reached or answered with an error.
+    #[error("rpc error: {0}")]
+    Rpc(String),
+    /// The transaction was rejected by the program.
+    #[error("teleport error: {0}")]
+    Teleport(TeleportError),
+    /// The transaction failed for another reason.
+    #[error("transaction error: {0}")]
+    Transaction(TransactionError),
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("account {0} isn't a valid {1}")]
+    InvalidAccount(Pubkey, &'static str),
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+    #[error("invalid account data: {0}")]
+    InvalidData(#[from] std::io::Error),
+    #[error("program error: {0}")]
+    Program(#[from] ProgramError),
+}
+
+impl ClientError {
+    /// The program error the transaction was rejected with, if any.
+    pub fn teleport_error(&self) -> Option<&TeleportError> {
+        match self {
+            ClientError::Teleport(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransactionError> for ClientError {
+    fn from(e: TransactionError) -> Self {
+        match decode_teleport_error(&e) {
+            Some(teleport_error) => ClientError::Teleport(teleport_error),
+            None => ClientError::Transaction(e),
+        }
+    }
+}
+
+/// Custom instruction errors are read as `TeleportError` codes. Token program
+/// errors raised through a CPI share the same code space, so a failed mint or
+/// burn can show up as an unrelated variant.
+pub fn decode_teleport_error(e: &TransactionError) -> Option<TeleportError> {
+    match e {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+            TeleportError::from_u32(*code)
+        }
+        _ => None,
+    }
+}
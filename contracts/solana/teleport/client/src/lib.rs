@@ -0,0 +1,14 @@
+pub mod client;
+pub mod error;
+pub mod offline;
+pub mod rpc;
+
+pub use {
+    client::{MintAuthority, TeleportClient},
+    error::ClientError,
+    offline::OfflineSignature,
+    rpc::{Simulation, TeleportRpc},
+};
+
+// Export the program crate for downstream users building instructions by hand
+pub use blt_teleport;
@@ -0,0 +1,142 @@
+//! Offline signing on durable nonces
+//!
+//! The owner key is kept offline. A transaction built on a durable nonce
+//! doesn't expire with the recent blockhash, so its message can be exported,
+//! signed on the offline machine and broadcast whenever the signatures are
+//! back.
+
+use {
+    crate::error::ClientError,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    solana_sdk::{
+        account::Account,
+        account_utils::StateMut,
+        hash::Hash,
+        message::Message,
+        nonce::state::{State, Versions},
+        program_utils::limited_deserialize,
+        pubkey::Pubkey,
+        signature::{Signature, Signer},
+        transaction::Transaction,
+    },
+    std::{fmt, str::FromStr},
+};
+
+/// A signature made offline, `PUBKEY=SIGNATURE` as text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfflineSignature {
+    pub pubkey: Pubkey,
+    pub signature: Signature,
+}
+
+impl fmt::Display for OfflineSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.pubkey, self.signature)
+    }
+}
+
+impl FromStr for OfflineSignature {
+    type Err = ClientError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || ClientError::InvalidSignature(value.to_string());
+        let (pubkey, signature) = value.split_once('=').ok_or_else(invalid)?;
+        Ok(Self {
+            pubkey: Pubkey::from_str(pubkey).map_err(|_| invalid())?,
+            signature: Signature::from_str(signature).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// The blockhash stored in a nonce account, after checking it's initialized
+/// and advanced by `authority`.
+pub fn nonce_blockhash(
+    address: &Pubkey,
+    account: &Account,
+    authority: &Pubkey,
+) -> Result<Hash, ClientError> {
+    if account.owner != solana_sdk::system_program::id() {
+        return Err(ClientError::InvalidAccount(*address, "nonce account"));
+    }
+    let versions: Versions = account
+        .state()
+        .map_err(|_| ClientError::InvalidAccount(*address, "nonce account"))?;
+    match versions.state() {
+        State::Initialized(data) if &data.authority == authority => Ok(data.blockhash()),
+        State::Initialized(_) => Err(ClientError::InvalidAccount(
+            *address,
+            "nonce account of this authority",
+        )),
+        State::Uninitialized => Err(ClientError::InvalidAccount(*address, "nonce account")),
+    }
+}
+
+pub fn encode_message(message: &Message) -> String {
+    STANDARD.encode(message.serialize())
+}
+
+pub fn decode_message(encoded: &str) -> Result<Message, ClientError> {
+    let invalid = || ClientError::InvalidMessage(encoded.to_string());
+    let data = STANDARD.decode(encoded.trim()).map_err(|_| invalid())?;
+    let message: Message = limited_deserialize(&data).map_err(|_| invalid())?;
+    // the exported bytes should be all there is
+    if message.serialize() != data {
+        return Err(invalid());
+    }
+    Ok(message)
+}
+
+/// Signs an exported message, on the offline machine.
+pub fn sign_message(
+    message: &Message,
+    signer: &dyn Signer,
+) -> Result<OfflineSignature, ClientError> {
+    let pubkey = signer.pubkey();
+    if !signer_keys(message).contains(&pubkey) {
+        return Err(ClientError::InvalidSignature(format!(
+            "{} doesn't sign this message",
+            pubkey
+        )));
+    }
+    let signature = signer
+        .try_sign_message(&message.serialize())
+        .map_err(|e| ClientError::InvalidSignature(e.to_string()))?;
+    Ok(OfflineSignature { pubkey, signature })
+}
+
+/// Keys that have to sign the message, the fee payer first.
+pub fn signer_keys(message: &Message) -> &[Pubkey] {
+    &message.account_keys[..message.header.num_required_signatures as usize]
+}
+
+/// Transaction of `message` with every signature in place, verified.
+pub fn assemble_transaction(
+    message: Message,
+    signatures: &[OfflineSignature],
+) -> Result<Transaction, ClientError> {
+    let mut transaction = Transaction::new_unsigned(message);
+    for offline in signatures {
+        let position = signer_keys(&transaction.message)
+            .iter()
+            .position(|key| key == &offline.pubkey)
+            .ok_or_else(|| ClientError::InvalidSignature(offline.to_string()))?;
+        transaction.signatures[position] = offline.signature;
+    }
+
+    let unsigned: Vec<String> = signer_keys(&transaction.message)
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(key, _)| key.to_string())
+        .collect();
+    if !unsigned.is_empty() {
+        return Err(ClientError::InvalidSignature(format!(
+            "missing signatures of {}",
+            unsigned.join(", ")
+        )));
+    }
+    transaction
+        .verify()
+        .map_err(|e| ClientError::InvalidSignature(e.to_string()))?;
+    Ok(transaction)
+}
@@ -0,0 +1,168 @@
+//! RPC backends
+
+use {
+    crate::error::{decode_teleport_error, ClientError},
+    async_trait::async_trait,
+    blt_teleport::error::TeleportError,
+    solana_banks_client::{BanksClient, BanksClientError},
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_rpc_client_api::client_error::Error as RpcClientError,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        pubkey::Pubkey,
+        signature::Signature,
+        transaction::{Transaction, TransactionError},
+    },
+};
+
+/// Outcome of a simulated transaction.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Simulation {
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+}
+
+impl Simulation {
+    pub fn is_ok(&self) -> bool {
+        self.err.is_none()
+    }
+
+    pub fn teleport_error(&self) -> Option<TeleportError> {
+        self.err.as_ref().and_then(decode_teleport_error)
+    }
+}
+
+/// The calls `TeleportClient` needs from a node, implemented for the
+/// nonblocking `RpcClient` and for the `BanksClient` of `solana-program-test`.
+#[async_trait]
+pub trait TeleportRpc {
+    /// `None` if the account doesn't exist.
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError>;
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError>;
+
+    async fn get_minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError>;
+
+    /// Sends a signed transaction and waits until it's confirmed.
+    async fn send_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError>;
+
+    async fn simulate_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Simulation, ClientError>;
+}
+
+impl From<BanksClientError> for ClientError {
+    fn from(e: BanksClientError) -> Self {
+        match e {
+            BanksClientError::TransactionError(e) => e.into(),
+            BanksClientError::SimulationError { err, .. } => err.into(),
+            e => ClientError::Rpc(e.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl TeleportRpc for BanksClient {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        Ok(BanksClient::get_account(self, *address).await?)
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(BanksClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError> {
+        let rent = self.get_rent().await?;
+        Ok(rent.minimum_balance(data_len))
+    }
+
+    async fn send_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        // runs on the working bank, the banks server can't wait out a
+        // durable nonce as it looks the blockhash up to poll the status
+        self.process_transaction_with_metadata(transaction.clone())
+            .await?
+            .result?;
+        Ok(transaction.signatures[0])
+    }
+
+    async fn simulate_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Simulation, ClientError> {
+        let result = BanksClient::simulate_transaction(self, transaction.clone()).await?;
+        let details = result.simulation_details;
+        Ok(Simulation {
+            err: result.result.and_then(Result::err),
+            logs: details
+                .as_ref()
+                .map_or_else(Vec::new, |details| details.logs.clone()),
+            units_consumed: details.map(|details| details.units_consumed),
+        })
+    }
+}
+
+impl From<RpcClientError> for ClientError {
+    fn from(e: RpcClientError) -> Self {
+        match e.get_transaction_error() {
+            Some(e) => e.into(),
+            None => ClientError::Rpc(e.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl TeleportRpc for RpcClient {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        let response = self
+            .get_account_with_commitment(address, self.commitment())
+            .await?;
+        Ok(response.value)
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError> {
+        Ok(RpcClient::get_minimum_balance_for_rent_exemption(self, data_len).await?)
+    }
+
+    async fn send_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        Ok(self.send_and_confirm_transaction(transaction).await?)
+    }
+
+    async fn simulate_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Simulation, ClientError> {
+        let result = RpcClient::simulate_transaction(self, transaction)
+            .await?
+            .value;
+        Ok(Simulation {
+            err: result.err,
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+        })
+    }
+}
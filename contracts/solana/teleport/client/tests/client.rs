@@ -0,0 +1,274 @@
+#![cfg(feature = "test-bpf")]
+
+use {
+    blt_teleport::{error::TeleportError, instruction::AddressType},
+    blt_teleport_client::{
+        offline::{assemble_transaction, decode_message, encode_message, sign_message},
+        ClientError, MintAuthority, OfflineSignature, TeleportClient, TeleportRpc,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        nonce::State,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+        transaction::TransactionError,
+    },
+};
+
+fn get_owner() -> Keypair {
+    Keypair::from_bytes(&[
+        216, 77, 18, 252, 166, 244, 245, 106, 13, 111, 107, 26, 108, 43, 230, 245, 113, 207, 205,
+        116, 14, 52, 64, 196, 192, 63, 41, 220, 146, 82, 66, 53, 161, 111, 219, 59, 147, 221, 7,
+        127, 88, 42, 141, 30, 211, 69, 198, 108, 142, 249, 183, 249, 92, 127, 241, 91, 118, 190,
+        46, 20, 186, 220, 132, 23,
+    ])
+    .unwrap()
+}
+
+fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "blt_teleport",
+        blt_teleport::id(),
+        processor!(blt_teleport::processor::Processor::process_instruction),
+    )
+}
+
+async fn start() -> TeleportClient<BanksClient> {
+    let (banks_client, payer, _) = program_test().start().await;
+    TeleportClient::new(banks_client, blt_teleport::id(), payer)
+}
+
+#[tokio::test]
+async fn test_config_and_admins() {
+    let mut client = start().await;
+    let owner = get_owner();
+
+    let config = Keypair::new();
+    client.init_config(&owner, &config).await.unwrap();
+    let state = client.fetch_config(&config.pubkey()).await.unwrap();
+    assert!(state.is_init);
+    assert!(!state.is_frozen);
+
+    let admin = Keypair::new();
+    let auth = Pubkey::new_unique();
+    client
+        .init_admin(&owner, &config.pubkey(), &admin, &auth, 1000)
+        .await
+        .unwrap();
+    let admin_state = client.fetch_admin(&admin.pubkey()).await.unwrap();
+    assert_eq!(admin_state.auth, auth);
+    assert_eq!(admin_state.allowance, 1000);
+    client
+        .add_admin(&owner, &config.pubkey(), &admin.pubkey())
+        .await
+        .unwrap();
+    assert!(client
+        .fetch_config(&config.pubkey())
+        .await
+        .unwrap()
+        .contain_admin(&admin.pubkey()));
+
+    client
+        .remove_admin(&owner, &config.pubkey(), &admin.pubkey())
+        .await
+        .unwrap();
+    assert!(!client
+        .fetch_config(&config.pubkey())
+        .await
+        .unwrap()
+        .contain_admin(&admin.pubkey()));
+
+    client
+        .deposit_allowance(&owner, &config.pubkey(), &admin.pubkey(), 500)
+        .await
+        .unwrap();
+    let admin_state = client.fetch_admin(&admin.pubkey()).await.unwrap();
+    assert_eq!(admin_state.allowance, 1500);
+
+    let missing = Pubkey::new_unique();
+    assert!(matches!(
+        client.fetch_config(&missing).await,
+        Err(ClientError::AccountNotFound(address)) if address == missing
+    ));
+}
+
+#[tokio::test]
+async fn test_frozen_teleport_out() {
+    let mut client = start().await;
+    let owner = get_owner();
+
+    let config = Keypair::new();
+    client.init_config(&owner, &config).await.unwrap();
+    let admin = Keypair::new();
+    let auth = Keypair::new();
+    client
+        .init_admin(&owner, &config.pubkey(), &admin, &auth.pubkey(), 1000)
+        .await
+        .unwrap();
+    client
+        .add_admin(&owner, &config.pubkey(), &admin.pubkey())
+        .await
+        .unwrap();
+    client.freeze(&owner, &config.pubkey()).await.unwrap();
+    assert!(
+        client
+            .fetch_config(&config.pubkey())
+            .await
+            .unwrap()
+            .is_frozen
+    );
+
+    // rejected before the mint and the wallet are looked at
+    let mint_authority = MintAuthority {
+        wallet: Pubkey::new_unique(),
+        mint_auth: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        token_program: spl_token::id(),
+    };
+    let tx_hash = [1; 32];
+    let err = client
+        .teleport_out(
+            &auth,
+            &config.pubkey(),
+            &admin.pubkey(),
+            &mint_authority,
+            &Pubkey::new_unique(),
+            AddressType::Spl,
+            &tx_hash,
+            400,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.teleport_error(), Some(&TeleportError::Freeze));
+    assert!(!client.is_tx_hash_processed(&tx_hash).await.unwrap());
+    assert_eq!(
+        client.fetch_teleport_out_record(&tx_hash).await.unwrap(),
+        None
+    );
+
+    client.unfreeze(&owner, &config.pubkey()).await.unwrap();
+    let instruction =
+        blt_teleport::instruction::freeze(client.program_id(), &auth.pubkey(), &config.pubkey())
+            .unwrap();
+    let simulation = client.simulate(&[instruction], &[&auth]).await.unwrap();
+    assert_eq!(simulation.teleport_error(), Some(TeleportError::AuthFailed));
+    assert!(
+        !client
+            .fetch_config(&config.pubkey())
+            .await
+            .unwrap()
+            .is_frozen
+    );
+}
+
+// Blockhash lifetime of a transaction, in slots.
+const MAX_PROCESSING_AGE: u64 = 150;
+
+#[tokio::test]
+async fn test_offline_owner_signing() {
+    let mut context = program_test().start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let mut client = TeleportClient::new(
+        context.banks_client.clone(),
+        blt_teleport::id(),
+        payer.insecure_clone(),
+    );
+    let owner = get_owner();
+
+    let config = Keypair::new();
+    client.init_config(&owner, &config).await.unwrap();
+    let admin = Keypair::new();
+    let stale_admin = Keypair::new();
+    for admin in [&admin, &stale_admin].iter() {
+        client
+            .init_admin(&owner, &config.pubkey(), admin, &Pubkey::new_unique(), 0)
+            .await
+            .unwrap();
+    }
+
+    // nonce advanced by the fee payer, the hot key
+    let nonce = Keypair::new();
+    let lamports = client
+        .rpc()
+        .get_minimum_balance_for_rent_exemption(State::size())
+        .await
+        .unwrap();
+    let instructions = system_instruction::create_nonce_account(
+        &payer.pubkey(),
+        &nonce.pubkey(),
+        &payer.pubkey(),
+        lamports,
+    );
+    client.send(&instructions, &[&nonce]).await.unwrap();
+    let mut slot = 2;
+    context.warp_to_slot(slot).unwrap();
+
+    let add_admin = |admin: &Keypair| {
+        blt_teleport::instruction::add_admin(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &config.pubkey(),
+            &admin.pubkey(),
+        )
+        .unwrap()
+    };
+    let transaction = client
+        .nonce_transaction(&[add_admin(&admin)], &nonce.pubkey(), &payer.pubkey(), &[])
+        .await
+        .unwrap();
+    let exported = encode_message(&transaction.message);
+    let payer_signature = OfflineSignature {
+        pubkey: payer.pubkey(),
+        signature: transaction.signatures[0],
+    };
+    // the same instruction on a recent blockhash, signed right away
+    let stale = client
+        .transaction(&[add_admin(&stale_admin)], &[&owner])
+        .await
+        .unwrap();
+
+    // on the offline machine
+    let message = decode_message(&exported).unwrap();
+    assert!(sign_message(&message, &Keypair::new()).is_err());
+    let owner_signature: OfflineSignature = sign_message(&message, &owner)
+        .unwrap()
+        .to_string()
+        .parse()
+        .unwrap();
+
+    // the signatures come back after the recent blockhash expired
+    for _ in 0..=MAX_PROCESSING_AGE {
+        slot += 1;
+        context.warp_to_slot(slot).unwrap();
+    }
+    let err = client.broadcast(&stale).await.unwrap_err();
+    assert!(matches!(
+        err,
+        ClientError::Transaction(TransactionError::BlockhashNotFound)
+    ));
+
+    assert!(assemble_transaction(message.clone(), std::slice::from_ref(&payer_signature)).is_err());
+    let transaction = assemble_transaction(message, &[payer_signature, owner_signature]).unwrap();
+    let blockhash = client
+        .fetch_nonce(&nonce.pubkey(), &payer.pubkey())
+        .await
+        .unwrap();
+    client.broadcast(&transaction).await.unwrap();
+
+    let state = client.fetch_config(&config.pubkey()).await.unwrap();
+    assert!(state.contain_admin(&admin.pubkey()));
+    assert!(!state.contain_admin(&stale_admin.pubkey()));
+
+    // the nonce moved on, the transaction can't be replayed
+    assert_ne!(
+        client
+            .fetch_nonce(&nonce.pubkey(), &payer.pubkey())
+            .await
+            .unwrap(),
+        blockhash
+    );
+    slot += 1;
+    context.warp_to_slot(slot).unwrap();
+    assert!(client.broadcast(&transaction).await.is_err());
+}
@@ -28,6 +28,42 @@ pub enum TeleportError {
     Freeze,
     #[error("UnexpectedError")]
     UnexpectedError,
+    #[error("DeniedSender")]
+    DeniedSender,
+    #[error("DeniedFlowAddress")]
+    DeniedFlowAddress,
+    #[error("DeniedRecipient")]
+    DeniedRecipient,
+    #[error("AmountBelowMinimum")]
+    AmountBelowMinimum,
+    #[error("AmountAboveMaximum")]
+    AmountAboveMaximum,
+    #[error("DeniedEvmAddress")]
+    DeniedEvmAddress,
+    #[error("ChainDisabled")]
+    ChainDisabled,
+    #[error("InvalidAddress")]
+    InvalidAddress,
+    #[error("PayloadTooLarge")]
+    PayloadTooLarge,
+    #[error("ReceiverNotAllowed")]
+    ReceiverNotAllowed,
+    #[error("NothingToClaim")]
+    NothingToClaim,
+    #[error("TeleportInSettled")]
+    TeleportInSettled,
+    #[error("AmountNotRepresentable")]
+    AmountNotRepresentable,
+    #[error("InvalidBatchSize")]
+    InvalidBatchSize,
+    #[error("AmountDelayed")]
+    AmountDelayed,
+    #[error("ChallengeDelayNotOver")]
+    ChallengeDelayNotOver,
+    #[error("InvalidTokenRoute")]
+    InvalidTokenRoute,
+    #[error("DelayedPayload")]
+    DelayedPayload,
 }
 
 impl From<TeleportError> for ProgramError {
@@ -56,6 +92,24 @@ impl PrintProgramError for TeleportError {
             TeleportError::IncorrectProgramAccount => msg!("Incorrect Program Account"),
             TeleportError::Freeze => msg!("Freeze"),
             TeleportError::UnexpectedError => msg!("Unexpected Error"),
+            TeleportError::DeniedSender => msg!("Denied Sender"),
+            TeleportError::DeniedFlowAddress => msg!("Denied Flow Address"),
+            TeleportError::DeniedRecipient => msg!("Denied Recipient"),
+            TeleportError::AmountBelowMinimum => msg!("Amount Below Minimum"),
+            TeleportError::AmountAboveMaximum => msg!("Amount Above Maximum"),
+            TeleportError::DeniedEvmAddress => msg!("Denied Evm Address"),
+            TeleportError::ChainDisabled => msg!("Chain Disabled"),
+            TeleportError::InvalidAddress => msg!("Invalid Address"),
+            TeleportError::PayloadTooLarge => msg!("Payload Too Large"),
+            TeleportError::ReceiverNotAllowed => msg!("Receiver Not Allowed"),
+            TeleportError::NothingToClaim => msg!("Nothing To Claim"),
+            TeleportError::TeleportInSettled => msg!("Teleport In Settled"),
+            TeleportError::AmountNotRepresentable => msg!("Amount Not Representable"),
+            TeleportError::InvalidBatchSize => msg!("Invalid Batch Size"),
+            TeleportError::AmountDelayed => msg!("Amount Delayed"),
+            TeleportError::ChallengeDelayNotOver => msg!("Challenge Delay Not Over"),
+            TeleportError::InvalidTokenRoute => msg!("Invalid Token Route"),
+            TeleportError::DelayedPayload => msg!("Delayed Payload"),
         }
     }
 }
@@ -0,0 +1,39 @@
+//! Event types
+
+use {
+    crate::instruction::Chain,
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_program::{log::sol_log_data, pubkey::Pubkey},
+};
+
+/// Events are logged as `Program data:` entries, a Borsh encoded
+/// `TeleportEvent` each. Amounts are in Flow UFix64 units, 8 decimals. Only
+/// succeeding instructions emit, a denylist rejection surfaces as its error.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub enum TeleportEvent {
+    /// `amount` was burned for `to` on `chain`, relayers unlock it there
+    /// and hand `payload` to the destination. `from_auth` and `nonce` find
+    /// the receipt.
+    TeleportedIn {
+        from_auth: Pubkey,
+        nonce: u64,
+        amount: u64,
+        chain: Chain,
+        to: Vec<u8>,
+        payload: Option<Vec<u8>>,
+    },
+    /// The `TeleportIn` of `from_auth` with `nonce` was minted back to
+    /// `from`, relayers should drop its unlock.
+    TeleportInRefunded {
+        from_auth: Pubkey,
+        nonce: u64,
+        from: Pubkey,
+        amount: u64,
+    },
+}
+
+impl TeleportEvent {
+    pub fn emit(&self) {
+        sol_log_data(&[&self.try_to_vec().unwrap()]);
+    }
+}
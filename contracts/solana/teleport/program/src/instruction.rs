@@ -1,13 +1,16 @@
 //! Instruction types
 
 use {
+    crate::{lockup, state},
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         instruction::{AccountMeta, Instruction},
         program_error::ProgramError,
         pubkey::Pubkey,
-        sysvar,
+        system_program, sysvar,
     },
+    spl_associated_token_account::get_associated_token_address_with_program_id,
+    std::{convert::TryInto, str::FromStr},
 };
 
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
@@ -27,20 +30,180 @@ pub enum TeleportInstruction {
     },
     Freeze,
     Unfreeze,
+    /// `amount` is in Flow UFix64 units, 8 decimals. `to` is an address on
+    /// `chain`, EVM chains are reached through the Flow custody contracts.
+    /// `payload` goes out in the `TeleportedIn` event. `nonce` is picked by
+    /// the sender, unique among its teleport ins, and keys the receipt.
     TeleportIn {
         amount: u64,
-        decimals: u8,
         to: Vec<u8>,
+        chain: Chain,
+        payload: Option<Vec<u8>>,
+        nonce: u64,
     },
+    /// `amount` is in Flow UFix64 units, 8 decimals. `payload` is forwarded
+    /// to a whitelisted receiver program after minting.
     TeleportOut {
         tx_hash: [u8; 32],
         amount: u64,
-        decimals: u8,
+        to_address_type: AddressType,
+        payload: Option<Vec<u8>>,
     },
     DepositAllowance {
         allowance: u64,
     },
     CloseTeleportOutRecord,
+    TeleportOutBatch {
+        entries: Vec<TeleportOutEntry>,
+    },
+    SetGasDrop {
+        per_transfer: u64,
+        daily_cap: u64,
+    },
+    RefillGasTank {
+        lamports: u64,
+    },
+    WithdrawGasTank {
+        lamports: u64,
+    },
+    SetDelayedSettlement {
+        threshold: u64,
+        delay: i64,
+    },
+    SetPauser {
+        pauser: Pubkey,
+    },
+    FinalizeTeleportOut,
+    CancelTeleportOut,
+    SetRecordRetention {
+        retention: i64,
+    },
+    CloseExpiredTeleportOutRecords,
+    CheckInvariants,
+    RotateAdminAuth {
+        new_auth: Pubkey,
+    },
+    SetAllowance {
+        allowance: u64,
+    },
+    DecreaseAllowance {
+        allowance: u64,
+    },
+    CloseAdmin,
+    MigrateConfig,
+    MigrateAdmin,
+    AddToDenylist {
+        key: DenylistKey,
+    },
+    RemoveFromDenylist {
+        key: DenylistKey,
+    },
+    SetAmountLimits {
+        teleport_in: state::AmountLimits,
+        teleport_out: state::AmountLimits,
+    },
+    SetChainEnabled {
+        chain: Chain,
+        enabled: bool,
+    },
+    AddReceiver {
+        receiver: Pubkey,
+    },
+    RemoveReceiver {
+        receiver: Pubkey,
+    },
+    SetLockupSchedule {
+        id: u32,
+        steps: Vec<lockup::LockupStep>,
+    },
+    /// `TeleportOut` into a vesting escrow that `beneficiary` claims as the
+    /// lockup schedule `schedule_id` unlocks it.
+    TeleportOutVesting {
+        tx_hash: [u8; 32],
+        amount: u64,
+        beneficiary: Pubkey,
+        schedule_id: u32,
+    },
+    ClaimVesting,
+    /// Settles a teleport in receipt once the destination unlocked it.
+    MarkTeleportInUnlocked,
+    /// Mints a teleport in the destination rejected back to the sender.
+    RefundTeleportIn,
+    /// Picks the mint and its token program, SPL Token or Token-2022. The
+    /// minted and burned counters restart from the mint's supply.
+    SetTokenRoute {
+        mint: Pubkey,
+        token_program: Pubkey,
+    },
+}
+
+/// Kind of the `to` address of a Flow `Locked` event.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum AddressType {
+    /// A wallet, tokens go to its associated token account.
+    Sol,
+    /// A token account.
+    Spl,
+}
+
+/// An address on the denylist. Solana pubkeys are checked against `TeleportIn`
+/// token owners and teleport out recipients, Flow addresses against the `to`
+/// of `TeleportIn`.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum DenylistKey {
+    Solana(Pubkey),
+    Flow([u8; 8]),
+    /// Shared by every EVM chain.
+    Evm([u8; 20]),
+}
+
+impl DenylistKey {
+    pub fn seeds(&self) -> [&[u8]; 2] {
+        match self {
+            DenylistKey::Solana(pubkey) => [state::DENYLIST_SEED, pubkey.as_ref()],
+            DenylistKey::Flow(address) => [state::FLOW_DENYLIST_SEED, &address[..]],
+            DenylistKey::Evm(address) => [state::EVM_DENYLIST_SEED, &address[..]],
+        }
+    }
+}
+
+/// Destination chain of a `TeleportIn`, the variant index is the chain id.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Chain {
+    Flow,
+    Ethereum,
+    Bsc,
+}
+
+impl Chain {
+    /// Denylist key of `to`, none when `to` isn't an address of the chain.
+    pub fn denylist_key(&self, to: &[u8]) -> Option<DenylistKey> {
+        match self {
+            Chain::Flow => to.try_into().ok().map(DenylistKey::Flow),
+            Chain::Ethereum | Chain::Bsc => to.try_into().ok().map(DenylistKey::Evm),
+        }
+    }
+}
+
+/// Instruction data of the call to a receiver program. The accounts are the
+/// caller address as a signer, the token account of the recipient, then the
+/// accounts passed after the receiver program.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ReceiverMessage {
+    pub tx_hash: [u8; 32],
+    /// In Flow UFix64 units.
+    pub amount: u64,
+    pub to: Pubkey,
+    pub payload: Vec<u8>,
+}
+
+/// One transfer of a `TeleportOutBatch`, the recipient is a token account.
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct TeleportOutEntry {
+    pub tx_hash: [u8; 32],
+    /// In Flow UFix64 units, 8 decimals.
+    pub amount: u64,
+    pub recipient: Pubkey,
 }
 
 pub fn get_owner(program_id: &Pubkey) -> Result<Instruction, ProgramError> {
@@ -76,6 +239,7 @@ pub fn init_config(
 pub fn init_admin(
     program_id: &Pubkey,
     owner: &Pubkey,
+    config: &Pubkey,
     admin: &Pubkey,
     auth: &Pubkey,
     allowance: u64,
@@ -88,6 +252,7 @@ pub fn init_admin(
     let accounts = vec![
         AccountMeta::new(*owner, true),
         AccountMeta::new(*admin, false),
+        AccountMeta::new(*config, false),
     ];
     Ok(Instruction {
         program_id: *program_id,
@@ -148,6 +313,197 @@ pub fn remove_admin(
     })
 }
 
+/// A rotation approved by the owner alone, e.g. when the current auth is
+/// lost.
+pub fn rotate_admin_auth(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    admin: &Pubkey,
+    new_auth: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::RotateAdminAuth {
+        new_auth: *new_auth,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*admin, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// A rotation initiated by the admin, signed by its current `auth` and
+/// co-signed by the owner.
+pub fn rotate_admin_auth_by_admin(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    admin: &Pubkey,
+    auth: &Pubkey,
+    new_auth: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = rotate_admin_auth(program_id, owner, admin, new_auth)?;
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*auth, true));
+    Ok(instruction)
+}
+
+pub fn set_allowance(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    allowance: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetAllowance { allowance };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn decrease_allowance(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    allowance: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::DecreaseAllowance { allowance };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Removes `admin` from the config and sends its rent to `target`, which
+/// can't be `admin` itself.
+pub fn close_admin(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    target: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::CloseAdmin;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new(*target, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Rewrites a version 1 config in the current layout, `owner` pays the
+/// rent of the extra space. The supply of `mint`, the BLT mint, seeds the
+/// minted counter.
+pub fn migrate_config(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::MigrateConfig;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*mint, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Rewrites a version 1 admin in the current layout, `owner` pays the rent
+/// of the extra space. Its allowance counts as granted by `config`, which has
+/// to be migrated first.
+pub fn migrate_admin(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    admin: &Pubkey,
+    config: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::MigrateAdmin;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn add_to_denylist(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    key: DenylistKey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::AddToDenylist { key };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_denylist_address(program_id, &key), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Closes the denylist entry of `key`, `owner` gets the rent back.
+pub fn remove_from_denylist(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    key: DenylistKey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::RemoveFromDenylist { key };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_denylist_address(program_id, &key), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
 pub fn freeze(
     program_id: &Pubkey,
     owner: &Pubkey,
@@ -187,6 +543,7 @@ pub fn unfreeze(
 pub fn deposit_allowance(
     program_id: &Pubkey,
     owner: &Pubkey,
+    config: &Pubkey,
     admin: &Pubkey,
     allowance: u64,
 ) -> Result<Instruction, ProgramError> {
@@ -195,6 +552,7 @@ pub fn deposit_allowance(
     let accounts = vec![
         AccountMeta::new(*owner, true),
         AccountMeta::new(*admin, false),
+        AccountMeta::new(*config, false),
     ];
     Ok(Instruction {
         program_id: *program_id,
@@ -203,6 +561,8 @@ pub fn deposit_allowance(
     })
 }
 
+/// `target` should be the fee payer stored in the record, records without
+/// one go to any `target`.
 pub fn close_teleport_out_record(
     program_id: &Pubkey,
     config: &Pubkey,
@@ -227,6 +587,915 @@ pub fn close_teleport_out_record(
     })
 }
 
-// TODO
-// pub fn teleport_in()
-// pub fn teleport_out()
+pub fn set_record_retention(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    retention: i64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetRecordRetention { retention };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_amount_limits(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    teleport_in: state::AmountLimits,
+    teleport_out: state::AmountLimits,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetAmountLimits {
+        teleport_in,
+        teleport_out,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn add_receiver(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    receiver: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::AddReceiver {
+        receiver: *receiver,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_receiver_address(program_id, receiver), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Closes the whitelist entry of `receiver`, `owner` gets the rent back.
+pub fn remove_receiver(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    receiver: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::RemoveReceiver {
+        receiver: *receiver,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_receiver_address(program_id, receiver), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Marks the receipt of a teleport in as unlocked on the destination, it
+/// can't be refunded afterwards.
+pub fn mark_teleport_in_unlocked(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    from_auth: &Pubkey,
+    nonce: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::MarkTeleportInUnlocked;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new_readonly(*admin, false),
+        AccountMeta::new_readonly(*admin_auth, true),
+        AccountMeta::new(
+            state::get_teleport_in_receipt_address(program_id, from_auth, nonce),
+            false,
+        ),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Mints a pending teleport in back to its `from` token account, owned by
+/// `from_owner`, out of the admin allowance and marks the receipt refunded.
+#[allow(clippy::too_many_arguments)]
+pub fn refund_teleport_in(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    mint_auth: &Pubkey,
+    from_auth: &Pubkey,
+    nonce: u64,
+    from: &Pubkey,
+    from_owner: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::RefundTeleportIn;
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new_readonly(*admin_auth, true),
+        AccountMeta::new(
+            state::get_teleport_in_receipt_address(program_id, from_auth, nonce),
+            false,
+        ),
+    ];
+    accounts.extend(settle_accounts(
+        program_id,
+        wallet,
+        fee_payer,
+        mint,
+        token_program,
+        from,
+        AddressType::Spl,
+        mint_auth,
+    ));
+    accounts.extend(owner_denylist_account(
+        program_id,
+        AddressType::Spl,
+        from_owner,
+    ));
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `token_program` should own `mint`. The minted and burned counters restart
+/// from the mint's supply.
+pub fn set_token_route(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetTokenRoute {
+        mint: *mint,
+        token_program: *token_program,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(*mint, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates or replaces the lockup schedule `id`, existing escrows follow the
+/// new steps.
+pub fn set_lockup_schedule(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    id: u32,
+    steps: Vec<lockup::LockupStep>,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetLockupSchedule { id, steps };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(lockup::get_lockup_schedule_address(program_id, id), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Flow is always enabled, EVM chains are enabled by the owner.
+pub fn set_chain_enabled(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    chain: Chain,
+    enabled: bool,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetChainEnabled { chain, enabled };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Closes `(record, payer)` pairs older than the record retention, anyone
+/// can call it.
+pub fn close_expired_teleport_out_records(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    records: &[(Pubkey, Pubkey)],
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::CloseExpiredTeleportOutRecords;
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![AccountMeta::new_readonly(*config, false)];
+    for (record, payer) in records {
+        accounts.push(AccountMeta::new(*record, false));
+        accounts.push(AccountMeta::new(*payer, false));
+    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Compares the BLT supply with the config counters and freezes the bridge
+/// when the supply exceeds them, anyone can call it.
+pub fn check_invariants(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::CheckInvariants;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new_readonly(*mint, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `to` is a token account for `AddressType::Spl` and a wallet for
+/// `AddressType::Sol`, whose associated token account is created when missing
+/// and which gets a SOL drop-off from the gas tank when it holds no lamports.
+/// `to_owner` owns the token account, it's `to` itself for a wallet, both are
+/// checked against the denylist. Amounts above the delay threshold land in a
+/// pending transfer instead.
+#[allow(clippy::too_many_arguments)]
+pub fn teleport_out(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    to: &Pubkey,
+    to_address_type: AddressType,
+    to_owner: &Pubkey,
+    mint_auth: &Pubkey,
+    tx_hash: &[u8; 32],
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::TeleportOut {
+        tx_hash: *tx_hash,
+        amount,
+        to_address_type,
+        payload: None,
+    };
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new_readonly(*admin_auth, true),
+        AccountMeta::new(
+            state::get_teleport_out_record_address(program_id, tx_hash),
+            false,
+        ),
+        AccountMeta::new(
+            state::get_pending_teleport_out_address(program_id, tx_hash),
+            false,
+        ),
+    ];
+    accounts.extend(settle_accounts(
+        program_id,
+        wallet,
+        fee_payer,
+        mint,
+        token_program,
+        to,
+        to_address_type,
+        mint_auth,
+    ));
+    accounts.extend(owner_denylist_account(
+        program_id,
+        to_address_type,
+        to_owner,
+    ));
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `teleport_out` that calls `receiver` with `payload` after minting,
+/// `receiver_accounts` are passed on to it.
+#[allow(clippy::too_many_arguments)]
+pub fn teleport_out_with_payload(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    to: &Pubkey,
+    to_address_type: AddressType,
+    to_owner: &Pubkey,
+    mint_auth: &Pubkey,
+    tx_hash: &[u8; 32],
+    amount: u64,
+    payload: Vec<u8>,
+    receiver: &Pubkey,
+    receiver_accounts: Vec<AccountMeta>,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = teleport_out(
+        program_id,
+        config,
+        admin,
+        admin_auth,
+        wallet,
+        fee_payer,
+        mint,
+        token_program,
+        to,
+        to_address_type,
+        to_owner,
+        mint_auth,
+        tx_hash,
+        amount,
+    )?;
+    let init_data = TeleportInstruction::TeleportOut {
+        tx_hash: *tx_hash,
+        amount,
+        to_address_type,
+        payload: Some(payload),
+    };
+    instruction.data = init_data.try_to_vec()?;
+    instruction.accounts.extend([
+        AccountMeta::new_readonly(state::get_receiver_address(program_id, receiver), false),
+        AccountMeta::new_readonly(state::get_caller_address(program_id), false),
+        AccountMeta::new_readonly(*receiver, false),
+    ]);
+    instruction.accounts.extend(receiver_accounts);
+    Ok(instruction)
+}
+
+/// Mints into the vesting vault, amounts above the delay threshold aren't
+/// supported.
+#[allow(clippy::too_many_arguments)]
+pub fn teleport_out_vesting(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    mint_auth: &Pubkey,
+    tx_hash: &[u8; 32],
+    amount: u64,
+    beneficiary: &Pubkey,
+    schedule_id: u32,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::TeleportOutVesting {
+        tx_hash: *tx_hash,
+        amount,
+        beneficiary: *beneficiary,
+        schedule_id,
+    };
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new_readonly(*admin_auth, true),
+        AccountMeta::new(
+            state::get_teleport_out_record_address(program_id, tx_hash),
+            false,
+        ),
+        AccountMeta::new_readonly(
+            lockup::get_lockup_schedule_address(program_id, schedule_id),
+            false,
+        ),
+        AccountMeta::new(lockup::get_vesting_address(program_id, tx_hash), false),
+        AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*beneficiary)),
+            false,
+        ),
+        AccountMeta::new_readonly(lockup::get_vesting_vault_authority(program_id), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+    ];
+    accounts.extend(settle_accounts(
+        program_id,
+        wallet,
+        fee_payer,
+        mint,
+        &spl_token::id(),
+        &lockup::get_vesting_vault_address(program_id, mint),
+        AddressType::Spl,
+        mint_auth,
+    ));
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Sends the unlocked part of a vesting escrow to the `to` token account,
+/// `payer` gets the rent back once everything is claimed. The escrow keeps
+/// the schedule steps it was made with, a denylisted beneficiary can't claim.
+pub fn claim_vesting(
+    program_id: &Pubkey,
+    beneficiary: &Pubkey,
+    tx_hash: &[u8; 32],
+    mint: &Pubkey,
+    to: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::ClaimVesting;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new_readonly(*beneficiary, true),
+        AccountMeta::new(lockup::get_vesting_address(program_id, tx_hash), false),
+        AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*beneficiary)),
+            false,
+        ),
+        AccountMeta::new_readonly(lockup::get_vesting_vault_authority(program_id), false),
+        AccountMeta::new(lockup::get_vesting_vault_address(program_id, mint), false),
+        AccountMeta::new(*to, false),
+        AccountMeta::new(*payer, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Accounts that mint a teleport out to `to`, shared by `TeleportOut` and
+/// `FinalizeTeleportOut`.
+#[allow(clippy::too_many_arguments)]
+fn settle_accounts(
+    program_id: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    to: &Pubkey,
+    to_address_type: AddressType,
+    mint_auth: &Pubkey,
+) -> Vec<AccountMeta> {
+    let to_token_account = match to_address_type {
+        AddressType::Sol => get_associated_token_address_with_program_id(to, mint, token_program),
+        AddressType::Spl => *to,
+    };
+    let mut accounts = vec![
+        AccountMeta::new(*wallet, false),
+        AccountMeta::new_readonly(state::get_signer_address(program_id), false),
+        AccountMeta::new(*fee_payer, true),
+        AccountMeta::new_readonly(
+            Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap(),
+            false,
+        ),
+        AccountMeta::new(*mint, false),
+        AccountMeta::new(to_token_account, false),
+        AccountMeta::new_readonly(*mint_auth, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*to)),
+            false,
+        ),
+    ];
+    if to_address_type == AddressType::Sol {
+        accounts.push(AccountMeta::new(*to, false));
+        accounts.push(AccountMeta::new_readonly(
+            spl_associated_token_account::id(),
+            false,
+        ));
+        accounts.push(AccountMeta::new(
+            state::get_gas_tank_address(program_id),
+            false,
+        ));
+    }
+    accounts
+}
+
+/// Denylist of the token account owner, which follows the settle accounts of
+/// an `AddressType::Spl` recipient.
+fn owner_denylist_account(
+    program_id: &Pubkey,
+    to_address_type: AddressType,
+    to_owner: &Pubkey,
+) -> Option<AccountMeta> {
+    match to_address_type {
+        AddressType::Spl => Some(AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*to_owner)),
+            false,
+        )),
+        AddressType::Sol => None,
+    }
+}
+
+/// Mints a pending transfer once its challenge delay is over, anyone can
+/// call it. `payer` gets the rent of the pending transfer back, `to_owner` is
+/// as in `teleport_out`.
+#[allow(clippy::too_many_arguments)]
+pub fn finalize_teleport_out(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    to: &Pubkey,
+    to_address_type: AddressType,
+    to_owner: &Pubkey,
+    mint_auth: &Pubkey,
+    tx_hash: &[u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::FinalizeTeleportOut;
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(
+            state::get_pending_teleport_out_address(program_id, tx_hash),
+            false,
+        ),
+        AccountMeta::new(*payer, false),
+    ];
+    accounts.extend(settle_accounts(
+        program_id,
+        wallet,
+        fee_payer,
+        mint,
+        token_program,
+        to,
+        to_address_type,
+        mint_auth,
+    ));
+    accounts.extend(owner_denylist_account(
+        program_id,
+        to_address_type,
+        to_owner,
+    ));
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `authority` is the owner or the pauser. The amount goes back to the
+/// allowance of `admin`.
+pub fn cancel_teleport_out(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    payer: &Pubkey,
+    tx_hash: &[u8; 32],
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::CancelTeleportOut;
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new_readonly(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new(
+            state::get_pending_teleport_out_address(program_id, tx_hash),
+            false,
+        ),
+        AccountMeta::new(*payer, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_delayed_settlement(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    threshold: u64,
+    delay: i64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetDelayedSettlement { threshold, delay };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_pauser(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    pauser: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetPauser { pauser: *pauser };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// `recipient_owners` owns the token account of each entry, in order.
+#[allow(clippy::too_many_arguments)]
+pub fn teleport_out_batch(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    admin: &Pubkey,
+    admin_auth: &Pubkey,
+    wallet: &Pubkey,
+    fee_payer: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    mint_auth: &Pubkey,
+    entries: &[TeleportOutEntry],
+    recipient_owners: &[Pubkey],
+) -> Result<Instruction, ProgramError> {
+    if recipient_owners.len() != entries.len() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let init_data = TeleportInstruction::TeleportOutBatch {
+        entries: entries.to_vec(),
+    };
+    let data = init_data.try_to_vec()?;
+    let mut accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*admin, false),
+        AccountMeta::new_readonly(*admin_auth, true),
+        AccountMeta::new(*wallet, false),
+        AccountMeta::new_readonly(state::get_signer_address(program_id), false),
+        AccountMeta::new(*fee_payer, true),
+        AccountMeta::new_readonly(
+            Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap(),
+            false,
+        ),
+        AccountMeta::new(*mint, false),
+        AccountMeta::new_readonly(*mint_auth, false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(*program_id, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    for (entry, recipient_owner) in entries.iter().zip(recipient_owners) {
+        accounts.push(AccountMeta::new(
+            state::get_teleport_out_record_address(program_id, &entry.tx_hash),
+            false,
+        ));
+        accounts.push(AccountMeta::new(entry.recipient, false));
+        accounts.push(AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(entry.recipient)),
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*recipient_owner)),
+            false,
+        ));
+    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn set_gas_drop(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    config: &Pubkey,
+    per_transfer: u64,
+    daily_cap: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::SetGasDrop {
+        per_transfer,
+        daily_cap,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(*config, false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn refill_gas_tank(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    lamports: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::RefillGasTank { lamports };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_gas_tank_address(program_id), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+pub fn withdraw_gas_tank(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    target: &Pubkey,
+    lamports: u64,
+) -> Result<Instruction, ProgramError> {
+    let init_data = TeleportInstruction::WithdrawGasTank { lamports };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*owner, true),
+        AccountMeta::new(state::get_gas_tank_address(program_id), false),
+        AccountMeta::new(*target, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Burns `amount` from the `from` token account of `from_auth`, the wallet
+/// `mint_auth` is the mint authority of the multisig wallet. `to` should be
+/// an address of `chain` and `payload` at most `state::MAX_PAYLOAD_LEN` bytes.
+/// `payer` funds the receipt of `from_auth` and `nonce`.
+#[allow(clippy::too_many_arguments)]
+pub fn teleport_in(
+    program_id: &Pubkey,
+    config: &Pubkey,
+    wallet: &Pubkey,
+    mint_auth: &Pubkey,
+    from: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    from_auth: &Pubkey,
+    payer: &Pubkey,
+    nonce: u64,
+    amount: u64,
+    chain: Chain,
+    to: &[u8],
+    payload: Option<Vec<u8>>,
+) -> Result<Instruction, ProgramError> {
+    let to_key = chain
+        .denylist_key(to)
+        .ok_or(ProgramError::InvalidArgument)?;
+    let init_data = TeleportInstruction::TeleportIn {
+        amount,
+        to: to.to_vec(),
+        chain,
+        payload,
+        nonce,
+    };
+    let data = init_data.try_to_vec()?;
+    let accounts = vec![
+        AccountMeta::new(*config, false),
+        AccountMeta::new(*wallet, false),
+        AccountMeta::new_readonly(*mint_auth, false),
+        AccountMeta::new_readonly(state::get_signer_address(program_id), false),
+        AccountMeta::new_readonly(
+            Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap(),
+            false,
+        ),
+        AccountMeta::new(*from, false),
+        AccountMeta::new(*mint, false),
+        AccountMeta::new_readonly(*from_auth, true),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(
+            state::get_denylist_address(program_id, &DenylistKey::Solana(*from_auth)),
+            false,
+        ),
+        AccountMeta::new_readonly(state::get_denylist_address(program_id, &to_key), false),
+        AccountMeta::new(
+            state::get_teleport_in_receipt_address(program_id, from_auth, nonce),
+            false,
+        ),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Wraps `instruction` in the invoke instruction of the multisig wallet
+/// program, for `wallet_pda` to sign it once the wallet `signers` approve.
+/// The data is `3, program idx, total accounts` as a little endian u16, an
+/// account idx and flags pair per account of `instruction`, 1 writable and
+/// 2 signer, then the data of `instruction`.
+pub fn wallet_invoke(
+    wallet: &Pubkey,
+    wallet_pda: &Pubkey,
+    signers: &[Pubkey],
+    instruction: &Instruction,
+) -> Result<Instruction, ProgramError> {
+    let mut accounts = vec![
+        AccountMeta::new(*wallet, false),
+        AccountMeta::new_readonly(*wallet_pda, false),
+        AccountMeta::new_readonly(instruction.program_id, false),
+    ];
+    accounts.extend(
+        signers
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(*signer, true)),
+    );
+
+    let total: u16 = instruction
+        .accounts
+        .len()
+        .try_into()
+        .map_err(|_| ProgramError::InvalidArgument)?;
+    let mut data = vec![3, 2];
+    data.extend(total.to_le_bytes().iter().cloned());
+    for meta in &instruction.accounts {
+        let idx = match accounts.iter().position(|a| a.pubkey == meta.pubkey) {
+            Some(idx) => idx,
+            None => {
+                accounts.push(AccountMeta::new_readonly(meta.pubkey, false));
+                accounts.len() - 1
+            }
+        };
+        // the wallet program signs for its PDA, other signers sign the
+        // wallet instruction
+        accounts[idx].is_writable |= meta.is_writable;
+        accounts[idx].is_signer |= meta.is_signer && meta.pubkey != *wallet_pda;
+        data.push(idx.try_into().map_err(|_| ProgramError::InvalidArgument)?);
+        data.push(meta.is_writable as u8 | (meta.is_signer as u8) << 1);
+    }
+    data.extend(instruction.data.iter().cloned());
+
+    Ok(Instruction {
+        program_id: Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap(),
+        accounts,
+        data,
+    })
+}
@@ -1,5 +1,7 @@
 pub mod error;
+pub mod event;
 pub mod instruction;
+pub mod lockup;
 pub mod processor;
 pub mod state;
 
@@ -0,0 +1,131 @@
+//! Lockup schedules and vesting escrows
+
+use {
+    borsh::{BorshDeserialize, BorshSerialize},
+    solana_program::pubkey::Pubkey,
+    spl_associated_token_account::get_associated_token_address,
+};
+
+pub const LOCKUP_SCHEDULE_SEED: &[u8] = b"lockup_schedule";
+
+pub const VESTING_SEED: &[u8] = b"vesting";
+
+pub const VESTING_VAULT_SEED: &[u8] = b"vesting_vault";
+
+pub const MAX_LOCKUP_STEPS: usize = 16;
+
+/// A locked ratio of 1.0 in UFix64.
+pub const FULLY_LOCKED: u64 = 100_000_000;
+
+/// Address of the lockup schedule `id`.
+pub fn get_lockup_schedule_address(program_id: &Pubkey, id: u32) -> Pubkey {
+    Pubkey::find_program_address(&[LOCKUP_SCHEDULE_SEED, &id.to_le_bytes()], program_id).0
+}
+
+/// Address of the vesting escrow of a Flow tx hash.
+pub fn get_vesting_address(program_id: &Pubkey, tx_hash: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[VESTING_SEED, &tx_hash[..]], program_id).0
+}
+
+/// Address of the owner of the vault, the token account that holds the
+/// tokens of every vesting escrow.
+pub fn get_vesting_vault_authority(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[VESTING_VAULT_SEED], program_id).0
+}
+
+pub fn get_vesting_vault_address(program_id: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(&get_vesting_vault_authority(program_id), mint)
+}
+
+/// From `timestamp` on, `locked_ratio` of the amount stays locked.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct LockupStep {
+    pub timestamp: i64,
+    /// In UFix64, `FULLY_LOCKED` is 1.0.
+    pub locked_ratio: u64,
+}
+
+/// Mirrors the predefined lockup schedules of `BloctoPass` on Flow.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct LockupSchedule {
+    pub version: u8,
+    pub is_init: bool,
+    pub id: u32,
+    pub step_count: u8,
+    /// The first `step_count` are in use, in timestamp order.
+    pub steps: [LockupStep; MAX_LOCKUP_STEPS],
+    pub reserved: [u8; 16],
+}
+
+impl LockupSchedule {
+    pub const LEN: usize = 279;
+
+    pub fn steps(&self) -> &[LockupStep] {
+        &self.steps[..self.step_count as usize]
+    }
+
+    /// Steps should be in timestamp order with a ratio of at most 1.0.
+    pub fn is_valid_steps(steps: &[LockupStep]) -> bool {
+        !steps.is_empty()
+            && steps.len() <= MAX_LOCKUP_STEPS
+            && steps.iter().all(|step| step.locked_ratio <= FULLY_LOCKED)
+            && steps
+                .windows(2)
+                .all(|pair| pair[0].timestamp < pair[1].timestamp)
+    }
+}
+
+/// Tokens of a teleport out held in the vault for `beneficiary`.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct Vesting {
+    pub version: u8,
+    pub is_init: bool,
+    pub tx_hash: [u8; 32],
+    pub beneficiary: Pubkey,
+    pub schedule_id: u32,
+    /// The steps of the schedule when the escrow was made, a later
+    /// `SetLockupSchedule` doesn't move them.
+    pub step_count: u8,
+    pub steps: [LockupStep; MAX_LOCKUP_STEPS],
+    /// In base units of the mint.
+    pub amount: u64,
+    /// In base units of the mint.
+    pub claimed: u64,
+    /// Fee payer that funded the escrow, refunded once it's fully claimed.
+    pub payer: Pubkey,
+    /// Mint held in the vault, claims don't follow a later route change.
+    pub mint: Pubkey,
+    pub reserved: [u8; 16],
+}
+
+impl Vesting {
+    pub const LEN: usize = 423;
+
+    pub fn steps(&self) -> &[LockupStep] {
+        &self.steps[..self.step_count as usize]
+    }
+
+    /// Part of the amount locked at `now`, nothing is locked before the
+    /// first step.
+    pub fn locked_amount(&self, now: i64) -> u64 {
+        let locked_ratio = self
+            .steps()
+            .iter()
+            .rev()
+            .find(|step| step.timestamp <= now)
+            .map_or(0, |step| step.locked_ratio);
+
+        // at most `amount` as the ratio is at most 1.0
+        (self.amount as u128 * locked_ratio as u128 / FULLY_LOCKED as u128) as u64
+    }
+
+    /// Unlocked tokens that haven't been claimed yet.
+    pub fn claimable(&self, now: i64) -> u64 {
+        self.amount
+            .saturating_sub(self.locked_amount(now))
+            .saturating_sub(self.claimed)
+    }
+}
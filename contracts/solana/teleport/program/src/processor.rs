@@ -1,11 +1,19 @@
 //! Program state processor
 
 use {
-    crate::{error::TeleportError, instruction::TeleportInstruction, state},
+    crate::{
+        error::TeleportError,
+        event::TeleportEvent,
+        instruction::{
+            AddressType, Chain, DenylistKey, ReceiverMessage, TeleportInstruction, TeleportOutEntry,
+        },
+        lockup, state,
+    },
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{
         account_info::next_account_info,
         account_info::AccountInfo,
+        clock::Clock,
         entrypoint::ProgramResult,
         instruction::{AccountMeta, Instruction},
         msg,
@@ -16,7 +24,12 @@ use {
         system_instruction, system_program,
         sysvar::Sysvar,
     },
+    spl_associated_token_account::{
+        get_associated_token_address, get_associated_token_address_with_program_id,
+        instruction::create_associated_token_account,
+    },
     spl_token,
+    spl_token_2022::{extension::StateWithExtensions, state::Mint},
     std::str::FromStr,
 };
 
@@ -34,7 +47,9 @@ impl Processor {
             TeleportInstruction::GetOwner => {
                 msg!("Instruction: GetOwner");
                 let owner = Pubkey::from_str(state::OWNER_KEY).unwrap();
-                msg!(&format!("owner is {}", owner.to_string()));
+                msg!(&format!("owner is {}", owner));
+                let owner_wallet = Pubkey::from_str(state::OWNER_WALLET_KEY).unwrap();
+                msg!(&format!("owner wallet is {}", owner_wallet));
                 Ok(())
             }
             TeleportInstruction::InitConfig => {
@@ -67,28 +82,175 @@ impl Processor {
             }
             TeleportInstruction::TeleportIn {
                 amount,
-                decimals,
-                to: _,
+                to,
+                chain,
+                payload,
+                nonce,
             } => {
                 msg!("Instruction: TeleportIn");
-                Self::process_teleport_in(program_id, accounts, amount, decimals)
+                Self::process_teleport_in(program_id, accounts, amount, chain, &to, payload, nonce)
             }
             TeleportInstruction::TeleportOut {
                 tx_hash,
                 amount,
-                decimals,
+                to_address_type,
+                payload,
             } => {
                 msg!("Instruction: TeleportOut");
-                Self::process_teleport_out(program_id, accounts, &tx_hash, amount, decimals)
+                Self::process_teleport_out(
+                    program_id,
+                    accounts,
+                    &tx_hash,
+                    amount,
+                    to_address_type,
+                    payload.as_deref(),
+                )
             }
             TeleportInstruction::DepositAllowance { allowance } => {
                 msg!("Instruction: DepositAllowance");
                 Self::process_deposit_allowance(program_id, accounts, allowance)
             }
-            TeleportInstruction::CloseTeleportOutRecord {} => {
+            TeleportInstruction::CloseTeleportOutRecord => {
                 msg!("Instruction: TeleportInstruction");
                 Self::process_close_teleport_out_record(program_id, accounts)
             }
+            TeleportInstruction::TeleportOutBatch { entries } => {
+                msg!("Instruction: TeleportOutBatch");
+                Self::process_teleport_out_batch(program_id, accounts, &entries)
+            }
+            TeleportInstruction::SetGasDrop {
+                per_transfer,
+                daily_cap,
+            } => {
+                msg!("Instruction: SetGasDrop");
+                Self::process_set_gas_drop(program_id, accounts, per_transfer, daily_cap)
+            }
+            TeleportInstruction::RefillGasTank { lamports } => {
+                msg!("Instruction: RefillGasTank");
+                Self::process_refill_gas_tank(program_id, accounts, lamports)
+            }
+            TeleportInstruction::WithdrawGasTank { lamports } => {
+                msg!("Instruction: WithdrawGasTank");
+                Self::process_withdraw_gas_tank(program_id, accounts, lamports)
+            }
+            TeleportInstruction::SetDelayedSettlement { threshold, delay } => {
+                msg!("Instruction: SetDelayedSettlement");
+                Self::process_set_delayed_settlement(program_id, accounts, threshold, delay)
+            }
+            TeleportInstruction::SetPauser { pauser } => {
+                msg!("Instruction: SetPauser");
+                Self::process_set_pauser(program_id, accounts, &pauser)
+            }
+            TeleportInstruction::FinalizeTeleportOut => {
+                msg!("Instruction: FinalizeTeleportOut");
+                Self::process_finalize_teleport_out(program_id, accounts)
+            }
+            TeleportInstruction::CancelTeleportOut => {
+                msg!("Instruction: CancelTeleportOut");
+                Self::process_cancel_teleport_out(program_id, accounts)
+            }
+            TeleportInstruction::SetRecordRetention { retention } => {
+                msg!("Instruction: SetRecordRetention");
+                Self::process_set_record_retention(program_id, accounts, retention)
+            }
+            TeleportInstruction::CloseExpiredTeleportOutRecords => {
+                msg!("Instruction: CloseExpiredTeleportOutRecords");
+                Self::process_close_expired_teleport_out_records(program_id, accounts)
+            }
+            TeleportInstruction::CheckInvariants => {
+                msg!("Instruction: CheckInvariants");
+                Self::process_check_invariants(program_id, accounts)
+            }
+            TeleportInstruction::RotateAdminAuth { new_auth } => {
+                msg!("Instruction: RotateAdminAuth");
+                Self::process_rotate_admin_auth(program_id, accounts, &new_auth)
+            }
+            TeleportInstruction::SetAllowance { allowance } => {
+                msg!("Instruction: SetAllowance");
+                Self::process_set_allowance(program_id, accounts, allowance)
+            }
+            TeleportInstruction::DecreaseAllowance { allowance } => {
+                msg!("Instruction: DecreaseAllowance");
+                Self::process_decrease_allowance(program_id, accounts, allowance)
+            }
+            TeleportInstruction::CloseAdmin => {
+                msg!("Instruction: CloseAdmin");
+                Self::process_close_admin(program_id, accounts)
+            }
+            TeleportInstruction::MigrateConfig => {
+                msg!("Instruction: MigrateConfig");
+                Self::process_migrate_config(program_id, accounts)
+            }
+            TeleportInstruction::MigrateAdmin => {
+                msg!("Instruction: MigrateAdmin");
+                Self::process_migrate_admin(program_id, accounts)
+            }
+            TeleportInstruction::AddToDenylist { key } => {
+                msg!("Instruction: AddToDenylist");
+                Self::process_add_to_denylist(program_id, accounts, &key)
+            }
+            TeleportInstruction::RemoveFromDenylist { key } => {
+                msg!("Instruction: RemoveFromDenylist");
+                Self::process_remove_from_denylist(program_id, accounts, &key)
+            }
+            TeleportInstruction::SetAmountLimits {
+                teleport_in,
+                teleport_out,
+            } => {
+                msg!("Instruction: SetAmountLimits");
+                Self::process_set_amount_limits(program_id, accounts, teleport_in, teleport_out)
+            }
+            TeleportInstruction::SetChainEnabled { chain, enabled } => {
+                msg!("Instruction: SetChainEnabled");
+                Self::process_set_chain_enabled(program_id, accounts, chain, enabled)
+            }
+            TeleportInstruction::AddReceiver { receiver } => {
+                msg!("Instruction: AddReceiver");
+                Self::process_add_receiver(program_id, accounts, &receiver)
+            }
+            TeleportInstruction::RemoveReceiver { receiver } => {
+                msg!("Instruction: RemoveReceiver");
+                Self::process_remove_receiver(program_id, accounts, &receiver)
+            }
+            TeleportInstruction::SetLockupSchedule { id, steps } => {
+                msg!("Instruction: SetLockupSchedule");
+                Self::process_set_lockup_schedule(program_id, accounts, id, &steps)
+            }
+            TeleportInstruction::TeleportOutVesting {
+                tx_hash,
+                amount,
+                beneficiary,
+                schedule_id,
+            } => {
+                msg!("Instruction: TeleportOutVesting");
+                Self::process_teleport_out_vesting(
+                    program_id,
+                    accounts,
+                    &tx_hash,
+                    amount,
+                    &beneficiary,
+                    schedule_id,
+                )
+            }
+            TeleportInstruction::ClaimVesting => {
+                msg!("Instruction: ClaimVesting");
+                Self::process_claim_vesting(program_id, accounts)
+            }
+            TeleportInstruction::MarkTeleportInUnlocked => {
+                msg!("Instruction: MarkTeleportInUnlocked");
+                Self::process_mark_teleport_in_unlocked(program_id, accounts)
+            }
+            TeleportInstruction::RefundTeleportIn => {
+                msg!("Instruction: RefundTeleportIn");
+                Self::process_refund_teleport_in(program_id, accounts)
+            }
+            TeleportInstruction::SetTokenRoute {
+                mint,
+                token_program,
+            } => {
+                msg!("Instruction: SetTokenRoute");
+                Self::process_set_token_route(program_id, accounts, &mint, &token_program)
+            }
         }
     }
 
@@ -109,15 +271,16 @@ impl Processor {
             return Err(TeleportError::NotRentExempt.into());
         }
 
+        config.version = state::ACCOUNT_VERSION;
         config.is_init = true;
 
         config
-            .serialize(&mut *config_info.data.borrow_mut())
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
     pub fn process_init_admin(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         auth: &Pubkey,
         allowance: u64,
@@ -125,6 +288,7 @@ impl Processor {
         let account_info_iter = &mut accounts.iter();
         let owner_info = next_account_info(account_info_iter)?;
         let admin_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
         Self::only_owner(owner_info)?;
 
@@ -133,12 +297,15 @@ impl Processor {
             return Err(TeleportError::AlreadyInUse.into());
         }
 
+        admin.version = state::ACCOUNT_VERSION;
         admin.is_init = true;
         admin.auth = *auth;
         admin.allowance = allowance;
 
+        Self::grant_allowance(program_id, config_info, allowance)?;
+
         admin
-            .serialize(&mut *admin_info.data.borrow_mut())
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -154,9 +321,11 @@ impl Processor {
             return Err(TeleportError::AlreadyInUse.into());
         }
 
+        record.version = state::ACCOUNT_VERSION;
         record.is_init = true;
+        record.created_at = Clock::get()?.unix_timestamp;
         record
-            .serialize(&mut *record_info.data.borrow_mut())
+            .serialize(&mut &mut record_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -179,7 +348,7 @@ impl Processor {
         config.add_admin(admin)?;
 
         config
-            .serialize(&mut *config_info.data.borrow_mut())
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -202,7 +371,7 @@ impl Processor {
         config.remove_admin(admin)?;
 
         config
-            .serialize(&mut *config_info.data.borrow_mut())
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -221,7 +390,7 @@ impl Processor {
         config.is_frozen = true;
 
         config
-            .serialize(&mut *config_info.data.borrow_mut())
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -240,7 +409,7 @@ impl Processor {
         config.is_frozen = false;
 
         config
-            .serialize(&mut *config_info.data.borrow_mut())
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
@@ -248,7 +417,10 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
-        decimals: u8,
+        chain: Chain,
+        to: &[u8],
+        payload: Option<Vec<u8>>,
+        nonce: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let config_info = next_account_info(account_info_iter)?;
@@ -260,35 +432,96 @@ impl Processor {
         let mint_info = next_account_info(account_info_iter)?;
         let from_auth_info = next_account_info(account_info_iter)?;
         let spl_token_program_info = next_account_info(account_info_iter)?;
+        let from_auth_denylist_info = next_account_info(account_info_iter)?;
+        let to_denylist_info = next_account_info(account_info_iter)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
 
-        let config = Self::get_config(program_id, config_info)?;
+        let mut config = Self::get_config(program_id, config_info)?;
         if config.is_frozen {
             return Err(TeleportError::Freeze.into());
         }
 
+        Self::check_payload(payload.as_deref())?;
+
+        // check destination
+        if !config.is_chain_enabled(chain) {
+            msg!("{:?} isn't enabled", chain);
+            return Err(TeleportError::ChainDisabled.into());
+        }
+        let to_key = match chain.denylist_key(to) {
+            Some(to_key) if to.iter().any(|byte| *byte != 0) => to_key,
+            _ => {
+                msg!("to isn't a {:?} address", chain);
+                return Err(TeleportError::InvalidAddress.into());
+            }
+        };
+
+        // check denylist
+        if Self::is_denylisted(
+            program_id,
+            from_auth_denylist_info,
+            &DenylistKey::Solana(*from_auth_info.key),
+        )? {
+            msg!("token owner is denylisted");
+            return Err(TeleportError::DeniedSender.into());
+        }
+        if Self::is_denylisted(program_id, to_denylist_info, &to_key)? {
+            msg!("to is denylisted");
+            return Err(match to_key {
+                DenylistKey::Flow(_) => TeleportError::DeniedFlowAddress,
+                DenylistKey::Evm(_) => TeleportError::DeniedEvmAddress,
+                DenylistKey::Solana(_) => TeleportError::UnexpectedError,
+            }
+            .into());
+        }
+
+        if let Err(e) = config.teleport_in_limits.check(amount) {
+            msg!("amount is out of the teleport in limits");
+            return Err(e.into());
+        }
+
         // check wallet program
         let expected_multisig_program = Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap();
-        if wallet_program_info.key != &expected_multisig_program{
+        if wallet_program_info.key != &expected_multisig_program {
             msg!("unexpected multisig program");
             return Err(TeleportError::UnexpectedError.into());
         }
 
-        // check token program
-        if spl_token_program_info.key != &spl_token::id() {
-            msg!("unexpected spl-token-program");
-            return Err(TeleportError::UnexpectedError.into());
-        }
+        Self::check_token_route(&config, mint_info, spl_token_program_info)?;
+
+        Self::create_teleport_in_receipt(
+            program_id,
+            receipt_info,
+            payer_info,
+            system_program_info,
+            &state::TeleportInReceipt {
+                version: state::ACCOUNT_VERSION,
+                is_init: true,
+                from_auth: *from_auth_info.key,
+                nonce,
+                from: *from_info.key,
+                amount,
+                status: state::TeleportInStatus::Pending,
+                created_at: Clock::get()?.unix_timestamp,
+                payer: *payer_info.key,
+                reserved: [0; 16],
+            },
+        )?;
 
-        // check mint
-        let expected_blt = Pubkey::from_str(state::BLT_MINT_KEY).unwrap();
-        if mint_info.key != &expected_blt {
-            msg!("unexpected mint");
-            return Err(TeleportError::UnexpectedError.into());
-        }
+        // the event keeps the UFix64 amount
+        let decimals = Self::get_mint_decimals(mint_info)?;
+        let burned = state::to_base_units(amount, decimals)?;
+
+        config.total_burned = config
+            .total_burned
+            .checked_add(burned)
+            .ok_or(TeleportError::UnexpectedError)?;
 
         let seeds: &[&[_]] = &[
             state::SIGNER_SEED,
-            &[Pubkey::find_program_address(&[state::SIGNER_SEED], &program_id).1],
+            &[Pubkey::find_program_address(&[state::SIGNER_SEED], program_id).1],
         ];
 
         let mut data = vec![
@@ -300,7 +533,7 @@ impl Processor {
             6, 2,  // readonly singer
             15, // u8, mint instruction in token program
         ];
-        data.extend(amount.to_le_bytes().iter().cloned());
+        data.extend(burned.to_le_bytes().iter().cloned());
         data.push(decimals);
 
         invoke_signed(
@@ -327,46 +560,207 @@ impl Processor {
                 mint_info.clone(),
                 from_auth_info.clone(),
             ],
-            &[&seeds],
+            &[seeds],
         )?;
 
-        Ok(())
+        TeleportEvent::TeleportedIn {
+            from_auth: *from_auth_info.key,
+            nonce,
+            amount,
+            chain,
+            to: to.to_vec(),
+            payload,
+        }
+        .emit();
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
     }
 
-    pub fn process_teleport_out(
+    fn create_teleport_in_receipt<'a>(
+        program_id: &Pubkey,
+        receipt_info: &AccountInfo<'a>,
+        payer_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        receipt: &state::TeleportInReceipt,
+    ) -> ProgramResult {
+        let nonce = receipt.nonce.to_le_bytes();
+        let (pda, bump) = Pubkey::find_program_address(
+            &[
+                state::TELEPORT_IN_RECEIPT_SEED,
+                receipt.from_auth.as_ref(),
+                &nonce,
+            ],
+            program_id,
+        );
+        if receipt_info.key != &pda {
+            msg!("receipt account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if receipt_info.lamports() > 0 {
+            msg!("nonce is already used");
+            return Err(TeleportError::AlreadyInUse.into());
+        }
+
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                receipt_info.key,
+                Rent::get()?.minimum_balance(state::TeleportInReceipt::LEN),
+                state::TeleportInReceipt::LEN as u64,
+                program_id,
+            ),
+            &[
+                payer_info.clone(),
+                receipt_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[
+                state::TELEPORT_IN_RECEIPT_SEED,
+                receipt.from_auth.as_ref(),
+                &nonce,
+                &[bump],
+            ]],
+        )?;
+
+        receipt
+            .serialize(&mut &mut receipt_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_mark_teleport_in_unlocked(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        txhash: &[u8; 32],
-        amount: u64,
-        decimals: u8,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let config_info = next_account_info(account_info_iter)?;
         let admin_info = next_account_info(account_info_iter)?;
         let admin_auth_info = next_account_info(account_info_iter)?;
+        let receipt_info = next_account_info(account_info_iter)?;
 
-        // check config
-        let config = Self::get_config(program_id, config_info)?;
-        if config.is_frozen {
-            return Err(TeleportError::Freeze.into());
+        Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        let mut receipt = Self::get_pending_teleport_in_receipt(program_id, receipt_info)?;
+        receipt.status = state::TeleportInStatus::Unlocked;
+        receipt
+            .serialize(&mut &mut receipt_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_refund_teleport_in(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let admin_auth_info = next_account_info(account_info_iter)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+        let settle_infos = SettleInfos::new(account_info_iter, AddressType::Spl)?;
+        let owner_denylist_info = next_account_info(account_info_iter)?;
+
+        let (mut config, mut admin) =
+            Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        let mut receipt = Self::get_pending_teleport_in_receipt(program_id, receipt_info)?;
+        if settle_infos.to_info.key != &receipt.from {
+            msg!("a refund goes back to the burned token account");
+            return Err(TeleportError::UnexpectedError.into());
         }
 
-        // check admin & auth
-        if !config.contain_admin(admin_info.key) {
-            msg!("config doesn't contain admin key");
+        // a token owner denylisted since the burn can't get the tokens back
+        if !state::is_supported_token_program(settle_infos.to_info.owner) {
+            msg!("from should be a token account");
             return Err(TeleportError::UnexpectedError.into());
         }
-        let mut admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
-        if !admin.is_init {
-            return Err(TeleportError::UninitializedAccount.into());
+        let owner = state::get_token_account_owner(&settle_infos.to_info.data.borrow())?;
+        if Self::is_denylisted(program_id, owner_denylist_info, &DenylistKey::Solana(owner))? {
+            msg!("token owner is denylisted");
+            return Err(TeleportError::DeniedSender.into());
         }
 
-        if admin_auth_info.key != &admin.auth {
-            msg!("admin auth mismatch");
+        // a refund mints like a teleport out
+        if admin.allowance < receipt.amount {
+            msg!("admin allowance isn't enough");
             return Err(TeleportError::UnexpectedError.into());
         }
-        if !admin_auth_info.is_signer {
-            return Err(TeleportError::MissingRequiredSignature.into());
+        admin.allowance -= receipt.amount;
+        admin.serialize(&mut &mut admin_info.data.borrow_mut()[..])?;
+
+        Self::check_teleport_out_programs(
+            program_id,
+            settle_infos.wallet_program_info,
+            settle_infos.system_program_info,
+            settle_infos.teleport_program_info,
+        )?;
+
+        Self::check_token_route(
+            &config,
+            settle_infos.mint_info,
+            settle_infos.spl_token_program_info,
+        )?;
+
+        // the refunded tokens are no longer burned
+        let decimals = Self::get_mint_decimals(settle_infos.mint_info)?;
+        let amount = state::to_base_units(receipt.amount, decimals)?;
+        config.total_burned = config
+            .total_burned
+            .checked_sub(amount)
+            .ok_or(TeleportError::UnexpectedError)?;
+
+        let signer_bump = Pubkey::find_program_address(&[state::SIGNER_SEED], program_id).1;
+        Self::mint_blt(
+            settle_infos.wallet_info,
+            settle_infos.wallet_signer_info,
+            settle_infos.fee_payer_info,
+            settle_infos.wallet_program_info,
+            settle_infos.mint_info,
+            settle_infos.to_info,
+            settle_infos.mint_auth_info,
+            settle_infos.spl_token_program_info,
+            signer_bump,
+            amount,
+            decimals,
+        )?;
+
+        receipt.status = state::TeleportInStatus::Refunded;
+        receipt.serialize(&mut &mut receipt_info.data.borrow_mut()[..])?;
+
+        TeleportEvent::TeleportInRefunded {
+            from_auth: receipt.from_auth,
+            nonce: receipt.nonce,
+            from: receipt.from,
+            amount: receipt.amount,
+        }
+        .emit();
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_teleport_out(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        txhash: &[u8; 32],
+        amount: u64,
+        to_address_type: AddressType,
+        payload: Option<&[u8]>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let admin_auth_info = next_account_info(account_info_iter)?;
+
+        Self::check_payload(payload)?;
+
+        let (mut config, mut admin) =
+            Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        if let Err(e) = config.teleport_out_limits.check(amount) {
+            msg!("amount is out of the teleport out limits");
+            return Err(e.into());
         }
 
         if admin.allowance < amount {
@@ -374,105 +768,663 @@ impl Processor {
             return Err(TeleportError::UnexpectedError.into());
         }
         admin.allowance -= amount;
-        admin.serialize(&mut *admin_info.data.borrow_mut())?;
+        admin.serialize(&mut &mut admin_info.data.borrow_mut()[..])?;
+
+        Self::teleport_out(
+            program_id,
+            &mut config,
+            admin_info.key,
+            account_info_iter,
+            txhash,
+            amount,
+            to_address_type,
+            payload,
+        )?;
 
-        Self::teleport_out(program_id, account_info_iter, txhash, amount, decimals)
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn teleport_out(
         program_id: &Pubkey,
+        config: &mut state::Config,
+        admin_key: &Pubkey,
         account_info_iter: &mut std::slice::Iter<solana_program::account_info::AccountInfo>,
         txhash: &[u8; 32],
         amount: u64,
-        decimals: u8,
+        to_address_type: AddressType,
+        payload: Option<&[u8]>,
     ) -> ProgramResult {
         let record_info = next_account_info(account_info_iter)?;
-        let wallet_info = next_account_info(account_info_iter)?;
-        let wallet_signer_info = next_account_info(account_info_iter)?;
-        let fee_payer_info = next_account_info(account_info_iter)?;
-        let wallet_program_info = next_account_info(account_info_iter)?;
-        let mint_info = next_account_info(account_info_iter)?;
-        let to_info = next_account_info(account_info_iter)?;
-        let mint_auth_info = next_account_info(account_info_iter)?;
-        let spl_token_program_info = next_account_info(account_info_iter)?;
-        let system_program_info = next_account_info(account_info_iter)?;
-        let teleport_program_info = next_account_info(account_info_iter)?;
-        let rent_sysvar_info = next_account_info(account_info_iter)?;
-        let rent = &Rent::from_account_info(rent_sysvar_info)?;
-        // check wallet program
-        let expected_multisig_program = Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap();
-        if wallet_program_info.key != &expected_multisig_program {
-            msg!("unexpected multisig program");
-            return Err(TeleportError::UnexpectedError.into());
+        let pending_info = next_account_info(account_info_iter)?;
+        let settle_infos = SettleInfos::new(account_info_iter, to_address_type)?;
+        let owner_denylist_info =
+            Self::next_owner_denylist_info(account_info_iter, to_address_type)?;
+        let rent = &Rent::from_account_info(settle_infos.rent_sysvar_info)?;
+
+        Self::check_teleport_out_programs(
+            program_id,
+            settle_infos.wallet_program_info,
+            settle_infos.system_program_info,
+            settle_infos.teleport_program_info,
+        )?;
+
+        Self::check_token_route(
+            config,
+            settle_infos.mint_info,
+            settle_infos.spl_token_program_info,
+        )?;
+
+        Self::check_recipient(
+            program_id,
+            settle_infos.denylist_info,
+            settle_infos.recipient().key,
+        )?;
+        if let Some(owner_denylist_info) = owner_denylist_info {
+            Self::check_token_owner(program_id, owner_denylist_info, settle_infos.to_info)?;
         }
 
-        // check token program
-        if spl_token_program_info.key != &spl_token::id() {
-            msg!("unexpected token program");
-            return Err(TeleportError::UnexpectedError.into());
+        // reject dust before anything is recorded, delayed transfers included
+        state::to_base_units(amount, Self::get_mint_decimals(settle_infos.mint_info)?)?;
+
+        if payload.is_some() && config.is_delayed(amount) {
+            msg!("a payload can't wait out the challenge delay");
+            return Err(TeleportError::DelayedPayload.into());
         }
 
-        // check system program
-        if system_program_info.key != &system_program::id() {
-            msg!("unexpected system program id");
-            return Err(TeleportError::UnexpectedError.into());
+        Self::create_teleport_out_record(
+            program_id,
+            record_info,
+            settle_infos.fee_payer_info,
+            rent,
+            txhash,
+        )?;
+
+        if config.is_delayed(amount) {
+            let pending = state::PendingTeleportOut {
+                version: state::ACCOUNT_VERSION,
+                is_init: true,
+                tx_hash: *txhash,
+                admin: *admin_key,
+                payer: *settle_infos.fee_payer_info.key,
+                to: *settle_infos.recipient().key,
+                to_address_type,
+                amount,
+                unlock_time: Clock::get()?
+                    .unix_timestamp
+                    .checked_add(config.challenge_delay)
+                    .ok_or(TeleportError::UnexpectedError)?,
+                reserved: [0; 32],
+            };
+            msg!(
+                "amount is above the delay threshold, pending until {}",
+                pending.unlock_time
+            );
+            return Self::create_pending_teleport_out(
+                program_id,
+                pending_info,
+                settle_infos.fee_payer_info,
+                settle_infos.system_program_info,
+                rent,
+                &pending,
+            );
         }
 
-        // check teleport program
-        if teleport_program_info.key != program_id {
-            msg!("unexpected teleport program");
-            return Err(TeleportError::UnexpectedError.into());
+        Self::settle_teleport_out(program_id, config, &settle_infos, rent, amount)?;
+
+        match payload {
+            Some(payload) => Self::call_receiver(
+                program_id,
+                account_info_iter,
+                settle_infos.to_info,
+                txhash,
+                amount,
+                payload,
+            ),
+            None => Ok(()),
         }
+    }
 
-        let (pda, bump) = Pubkey::find_program_address(&[&txhash[..]], &program_id);
-        if record_info.key != &pda {
-            msg!("record account mismatch");
-            return Err(TeleportError::UnexpectedError.into());
+    fn check_payload(payload: Option<&[u8]>) -> ProgramResult {
+        if payload.map_or(0, |payload| payload.len()) > state::MAX_PAYLOAD_LEN {
+            msg!("payload is over {} bytes", state::MAX_PAYLOAD_LEN);
+            return Err(TeleportError::PayloadTooLarge.into());
         }
-        // TODO assign owner for account hold lamports
-        if record_info.try_lamports().unwrap() != 0 {
-            msg!("record lamports is not zero");
+        Ok(())
+    }
+
+    /// Calls a whitelisted receiver program with the payload of a teleport
+    /// out, signed by the caller address.
+    fn call_receiver<'a>(
+        program_id: &Pubkey,
+        account_info_iter: &mut std::slice::Iter<AccountInfo<'a>>,
+        to_info: &AccountInfo<'a>,
+        txhash: &[u8; 32],
+        amount: u64,
+        payload: &[u8],
+    ) -> ProgramResult {
+        let receiver_entry_info = next_account_info(account_info_iter)?;
+        let caller_info = next_account_info(account_info_iter)?;
+        let receiver_program_info = next_account_info(account_info_iter)?;
+
+        if !Self::is_receiver(program_id, receiver_entry_info, receiver_program_info.key)? {
+            msg!("receiver isn't whitelisted");
+            return Err(TeleportError::ReceiverNotAllowed.into());
+        }
+
+        let (caller, bump) = Pubkey::find_program_address(&[state::CALLER_SEED], program_id);
+        if caller_info.key != &caller {
+            msg!("caller account mismatch");
             return Err(TeleportError::UnexpectedError.into());
         }
-        if !record_info.data_is_empty() {
-            msg!("record data is not empty");
+
+        // the rest of the accounts go to the receiver, without signing
+        let forwarded_infos = account_info_iter.as_slice();
+        let mut account_metas = vec![
+            AccountMeta::new_readonly(caller, true),
+            AccountMeta::new_readonly(*to_info.key, false),
+        ];
+        let mut account_infos = vec![caller_info.clone(), to_info.clone()];
+        for info in forwarded_infos {
+            account_metas.push(AccountMeta {
+                pubkey: *info.key,
+                is_signer: false,
+                is_writable: info.is_writable,
+            });
+            account_infos.push(info.clone());
+        }
+        account_infos.push(receiver_program_info.clone());
+
+        let message = ReceiverMessage {
+            tx_hash: *txhash,
+            amount,
+            to: *to_info.key,
+            payload: payload.to_vec(),
+        };
+        invoke_signed(
+            &Instruction {
+                program_id: *receiver_program_info.key,
+                accounts: account_metas,
+                data: message.try_to_vec()?,
+            },
+            &account_infos,
+            &[&[state::CALLER_SEED, &[bump]]],
+        )
+    }
+
+    /// Mints a UFix64 `amount` in base units of the mint.
+    fn settle_teleport_out(
+        program_id: &Pubkey,
+        config: &mut state::Config,
+        infos: &SettleInfos,
+        rent: &Rent,
+        amount: u64,
+    ) -> ProgramResult {
+        let decimals = Self::get_mint_decimals(infos.mint_info)?;
+        let amount = state::to_base_units(amount, decimals)?;
+        config.total_minted = config
+            .total_minted
+            .checked_add(amount)
+            .ok_or(TeleportError::UnexpectedError)?;
+
+        // a wallet recipient gets the tokens in its associated token account
+        if let Some(wallet_recipient) = &infos.wallet_recipient {
+            Self::drop_gas(
+                program_id,
+                config,
+                wallet_recipient.gas_tank_info,
+                wallet_recipient.to_owner_info,
+                infos.system_program_info,
+                rent,
+            )?;
+            Self::create_associated_token_account(
+                infos.fee_payer_info,
+                wallet_recipient.to_owner_info,
+                infos.to_info,
+                infos.mint_info,
+                infos.system_program_info,
+                infos.spl_token_program_info,
+                wallet_recipient.associated_token_program_info,
+            )?;
+        }
+
+        let signer_bump = Pubkey::find_program_address(&[state::SIGNER_SEED], program_id).1;
+        Self::mint_blt(
+            infos.wallet_info,
+            infos.wallet_signer_info,
+            infos.fee_payer_info,
+            infos.wallet_program_info,
+            infos.mint_info,
+            infos.to_info,
+            infos.mint_auth_info,
+            infos.spl_token_program_info,
+            signer_bump,
+            amount,
+            decimals,
+        )
+    }
+
+    fn create_pending_teleport_out<'a>(
+        program_id: &Pubkey,
+        pending_info: &AccountInfo<'a>,
+        fee_payer_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        rent: &Rent,
+        pending: &state::PendingTeleportOut,
+    ) -> ProgramResult {
+        let (pda, bump) = Pubkey::find_program_address(
+            &[state::PENDING_TELEPORT_OUT_SEED, &pending.tx_hash[..]],
+            program_id,
+        );
+        if pending_info.key != &pda {
+            msg!("pending account mismatch");
             return Err(TeleportError::UnexpectedError.into());
         }
 
-        // create teleport out account
-        let seeds: &[&[_]] = &[&txhash[..], &[bump]];
+        let seeds: &[&[_]] = &[
+            state::PENDING_TELEPORT_OUT_SEED,
+            &pending.tx_hash[..],
+            &[bump],
+        ];
         invoke_signed(
             &system_instruction::create_account(
-                &fee_payer_info.key,
-                &record_info.key,
-                rent.minimum_balance(state::TeleportOutRecord::LEN),
-                state::TeleportOutRecord::LEN as u64,
-                &program_id,
+                fee_payer_info.key,
+                pending_info.key,
+                rent.minimum_balance(state::PendingTeleportOut::LEN),
+                state::PendingTeleportOut::LEN as u64,
+                program_id,
             ),
-            &[fee_payer_info.clone(), record_info.clone()],
-            &[&seeds],
+            &[
+                fee_payer_info.clone(),
+                pending_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[seeds],
         )?;
 
-        // init teleport out account
-        invoke(
-            &crate::instruction::init_teleport_out_record(&program_id, &record_info.key).unwrap(),
-            &[record_info.clone()],
-        )?;
+        pending
+            .serialize(&mut &mut pending_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
 
-        // mint blt token
-        let seeds: &[&[_]] = &[
-            state::SIGNER_SEED,
-            &[Pubkey::find_program_address(&[state::SIGNER_SEED], &program_id).1],
-        ];
+    pub fn process_teleport_out_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        entries: &[TeleportOutEntry],
+    ) -> ProgramResult {
+        if entries.is_empty() || entries.len() > state::MAX_TELEPORT_OUT_BATCH {
+            msg!(
+                "batch size should be between 1 and {}",
+                state::MAX_TELEPORT_OUT_BATCH
+            );
+            return Err(TeleportError::InvalidBatchSize.into());
+        }
 
-        let mut data = vec![
-            3, // u8, wallet program invoke instruction
-            3, // u8, invoke program idx
-            3, 0, // u16, total account, little endian
-            5, 1, // u8, u8, account idx, not signer writable
-            6, 1, // ..
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let admin_auth_info = next_account_info(account_info_iter)?;
+        let wallet_info = next_account_info(account_info_iter)?;
+        let wallet_signer_info = next_account_info(account_info_iter)?;
+        let fee_payer_info = next_account_info(account_info_iter)?;
+        let wallet_program_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let mint_auth_info = next_account_info(account_info_iter)?;
+        let spl_token_program_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let teleport_program_info = next_account_info(account_info_iter)?;
+        let rent_sysvar_info = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_sysvar_info)?;
+
+        // remaining accounts are (record, to, denylist, owner denylist)
+        // quadruples, one per entry
+        let remaining = account_info_iter.as_slice();
+        if remaining.len() != entries.len() * 4 {
+            msg!("remaining accounts should be a quadruple for each entry");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let (mut config, mut admin) =
+            Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        if entries.iter().any(|entry| config.is_delayed(entry.amount)) {
+            msg!("amount above the delay threshold should use TeleportOut");
+            return Err(TeleportError::AmountDelayed.into());
+        }
+        for entry in entries {
+            if let Err(e) = config.teleport_out_limits.check(entry.amount) {
+                msg!("amount is out of the teleport out limits");
+                return Err(e.into());
+            }
+        }
+
+        // debit the whole batch from the allowance once
+        let total = entries
+            .iter()
+            .try_fold(0u64, |total, entry| total.checked_add(entry.amount))
+            .ok_or(TeleportError::UnexpectedError)?;
+        if admin.allowance < total {
+            msg!("admin allowance isn't enough");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        admin.allowance -= total;
+        admin.serialize(&mut &mut admin_info.data.borrow_mut()[..])?;
+
+        Self::check_teleport_out_programs(
+            program_id,
+            wallet_program_info,
+            system_program_info,
+            teleport_program_info,
+        )?;
+
+        Self::check_token_route(&config, mint_info, spl_token_program_info)?;
+
+        let decimals = Self::get_mint_decimals(mint_info)?;
+        let signer_bump = Pubkey::find_program_address(&[state::SIGNER_SEED], program_id).1;
+        for (entry, quadruple) in entries.iter().zip(remaining.chunks_exact(4)) {
+            let record_info = &quadruple[0];
+            let to_info = &quadruple[1];
+            let denylist_info = &quadruple[2];
+            let owner_denylist_info = &quadruple[3];
+
+            if to_info.key != &entry.recipient {
+                msg!("recipient mismatch");
+                return Err(TeleportError::UnexpectedError.into());
+            }
+            Self::check_recipient(program_id, denylist_info, to_info.key)?;
+            Self::check_token_owner(program_id, owner_denylist_info, to_info)?;
+
+            let amount = state::to_base_units(entry.amount, decimals)?;
+            config.total_minted = config
+                .total_minted
+                .checked_add(amount)
+                .ok_or(TeleportError::UnexpectedError)?;
+
+            // the record creation fails for a processed tx hash, including
+            // a tx hash repeated inside the same batch
+            Self::create_teleport_out_record(
+                program_id,
+                record_info,
+                fee_payer_info,
+                rent,
+                &entry.tx_hash,
+            )?;
+
+            Self::mint_blt(
+                wallet_info,
+                wallet_signer_info,
+                fee_payer_info,
+                wallet_program_info,
+                mint_info,
+                to_info,
+                mint_auth_info,
+                spl_token_program_info,
+                signer_bump,
+                amount,
+                decimals,
+            )?;
+        }
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    fn check_recipient(
+        program_id: &Pubkey,
+        denylist_info: &AccountInfo,
+        to: &Pubkey,
+    ) -> ProgramResult {
+        if Self::is_denylisted(program_id, denylist_info, &DenylistKey::Solana(*to))? {
+            msg!("recipient is denylisted");
+            return Err(TeleportError::DeniedRecipient.into());
+        }
+        Ok(())
+    }
+
+    /// The denylist of the token account owner follows the settle accounts
+    /// of an `AddressType::Spl` recipient.
+    fn next_owner_denylist_info<'a, 'b>(
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+        to_address_type: AddressType,
+    ) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+        match to_address_type {
+            AddressType::Spl => Ok(Some(next_account_info(account_info_iter)?)),
+            AddressType::Sol => Ok(None),
+        }
+    }
+
+    // a denylisted wallet can't receive through its token accounts either
+    fn check_token_owner(
+        program_id: &Pubkey,
+        owner_denylist_info: &AccountInfo,
+        to_info: &AccountInfo,
+    ) -> ProgramResult {
+        if !state::is_supported_token_program(to_info.owner) {
+            msg!("recipient should be a token account");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        let owner = state::get_token_account_owner(&to_info.data.borrow())?;
+        Self::check_recipient(program_id, owner_denylist_info, &owner)
+    }
+
+    fn check_teleport_out_programs(
+        program_id: &Pubkey,
+        wallet_program_info: &AccountInfo,
+        system_program_info: &AccountInfo,
+        teleport_program_info: &AccountInfo,
+    ) -> ProgramResult {
+        // check wallet program
+        let expected_multisig_program = Pubkey::from_str(state::MULTISIG_PROGRAM_KEY).unwrap();
+        if wallet_program_info.key != &expected_multisig_program {
+            msg!("unexpected multisig program");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // check system program
+        if system_program_info.key != &system_program::id() {
+            msg!("unexpected system program id");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // check teleport program
+        if teleport_program_info.key != program_id {
+            msg!("unexpected teleport program");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        Ok(())
+    }
+
+    /// The mint and its token program should be the route of the config.
+    fn check_token_route(
+        config: &state::Config,
+        mint_info: &AccountInfo,
+        token_program_info: &AccountInfo,
+    ) -> ProgramResult {
+        if token_program_info.key != &config.token_program() {
+            msg!("unexpected token program");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+
+        if mint_info.key != &config.mint() || mint_info.owner != token_program_info.key {
+            msg!("unexpected mint");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+
+        Ok(())
+    }
+
+    fn create_teleport_out_record<'a>(
+        program_id: &Pubkey,
+        record_info: &AccountInfo<'a>,
+        fee_payer_info: &AccountInfo<'a>,
+        rent: &Rent,
+        txhash: &[u8; 32],
+    ) -> ProgramResult {
+        let (pda, bump) = Pubkey::find_program_address(&[&txhash[..]], program_id);
+        if record_info.key != &pda {
+            msg!("record account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        // TODO assign owner for account hold lamports
+        if record_info.try_lamports().unwrap() != 0 {
+            msg!("record lamports is not zero");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if !record_info.data_is_empty() {
+            msg!("record data is not empty");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // create teleport out account
+        let seeds: &[&[_]] = &[&txhash[..], &[bump]];
+        invoke_signed(
+            &system_instruction::create_account(
+                fee_payer_info.key,
+                record_info.key,
+                rent.minimum_balance(state::TeleportOutRecord::LEN),
+                state::TeleportOutRecord::LEN as u64,
+                program_id,
+            ),
+            &[fee_payer_info.clone(), record_info.clone()],
+            &[seeds],
+        )?;
+
+        // init teleport out account
+        let record = state::TeleportOutRecord {
+            version: state::ACCOUNT_VERSION,
+            is_init: true,
+            payer: *fee_payer_info.key,
+            created_at: Clock::get()?.unix_timestamp,
+            reserved: [0; 16],
+        };
+        record
+            .serialize(&mut &mut record_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    fn drop_gas<'a>(
+        program_id: &Pubkey,
+        config: &mut state::Config,
+        gas_tank_info: &AccountInfo<'a>,
+        to_owner_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        rent: &Rent,
+    ) -> ProgramResult {
+        let (gas_tank, bump) = Pubkey::find_program_address(&[state::GAS_TANK_SEED], program_id);
+        if gas_tank_info.key != &gas_tank {
+            msg!("gas tank mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // only a fresh wallet gets a drop-off
+        if to_owner_info.lamports() != 0 {
+            return Ok(());
+        }
+
+        let lamports = config.gas_drop(Clock::get()?.unix_timestamp);
+        if lamports == 0 {
+            return Ok(());
+        }
+
+        // an empty tank skips the drop-off instead of failing the transfer
+        let reserve = rent.minimum_balance(0);
+        if gas_tank_info.lamports() < lamports.saturating_add(reserve) {
+            msg!("gas tank is short, skip the drop-off");
+            return Ok(());
+        }
+
+        let seeds: &[&[_]] = &[state::GAS_TANK_SEED, &[bump]];
+        invoke_signed(
+            &system_instruction::transfer(gas_tank_info.key, to_owner_info.key, lamports),
+            &[
+                gas_tank_info.clone(),
+                to_owner_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[seeds],
+        )?;
+
+        config.gas_dropped_today += lamports;
+        Ok(())
+    }
+
+    fn create_associated_token_account<'a>(
+        fee_payer_info: &AccountInfo<'a>,
+        to_owner_info: &AccountInfo<'a>,
+        to_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        spl_token_program_info: &AccountInfo<'a>,
+        associated_token_program_info: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        // check associated token program
+        if associated_token_program_info.key != &spl_associated_token_account::id() {
+            msg!("unexpected associated token program");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let expected_to = get_associated_token_address_with_program_id(
+            to_owner_info.key,
+            mint_info.key,
+            spl_token_program_info.key,
+        );
+        if to_info.key != &expected_to {
+            msg!("associated token account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        if !to_info.data_is_empty() {
+            return Ok(());
+        }
+
+        // the fee payer covers the rent of the new token account
+        invoke(
+            &create_associated_token_account(
+                fee_payer_info.key,
+                to_owner_info.key,
+                mint_info.key,
+                spl_token_program_info.key,
+            ),
+            &[
+                fee_payer_info.clone(),
+                to_info.clone(),
+                to_owner_info.clone(),
+                mint_info.clone(),
+                system_program_info.clone(),
+                spl_token_program_info.clone(),
+                associated_token_program_info.clone(),
+            ],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn mint_blt<'a>(
+        wallet_info: &AccountInfo<'a>,
+        wallet_signer_info: &AccountInfo<'a>,
+        fee_payer_info: &AccountInfo<'a>,
+        wallet_program_info: &AccountInfo<'a>,
+        mint_info: &AccountInfo<'a>,
+        to_info: &AccountInfo<'a>,
+        mint_auth_info: &AccountInfo<'a>,
+        spl_token_program_info: &AccountInfo<'a>,
+        signer_bump: u8,
+        amount: u64,
+        decimals: u8,
+    ) -> ProgramResult {
+        let seeds: &[&[_]] = &[state::SIGNER_SEED, &[signer_bump]];
+
+        let mut data = vec![
+            3, // u8, wallet program invoke instruction
+            3, // u8, invoke program idx
+            3, 0, // u16, total account, little endian
+            5, 1, // u8, u8, account idx, not signer writable
+            6, 1, // ..
             7, 2,  // readonly singer
-            14, // u8, burn instruction in token program
+            14, // u8, mint instruction in token program
         ];
         data.extend(amount.to_le_bytes().iter().cloned());
         data.push(decimals);
@@ -502,85 +1454,1224 @@ impl Processor {
                 mint_info.clone(),
                 to_info.clone(),
             ],
-            &[&seeds],
-        )?;
+            &[seeds],
+        )
+    }
+
+    pub fn process_deposit_allowance(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        allowance: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
+        if !admin.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+
+        admin.allowance = admin.allowance.checked_add(allowance).unwrap();
+
+        Self::grant_allowance(program_id, config_info, allowance)?;
+
+        admin
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    fn grant_allowance(
+        program_id: &Pubkey,
+        config_info: &AccountInfo,
+        allowance: u64,
+    ) -> ProgramResult {
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        config.total_allowance_granted = config
+            .total_allowance_granted
+            .checked_add(allowance)
+            .ok_or(TeleportError::UnexpectedError)?;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    // clawed back allowance no longer counts as granted
+    fn revoke_allowance(config: &mut state::Config, allowance: u64) -> ProgramResult {
+        config.total_allowance_granted = config
+            .total_allowance_granted
+            .checked_sub(allowance)
+            .ok_or(TeleportError::UnexpectedError)?;
+        Ok(())
+    }
+
+    pub fn process_rotate_admin_auth(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_auth: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut admin = Self::get_admin(program_id, admin_info)?;
+
+        // an admin initiating the rotation signs with its current auth
+        if let Some(auth_info) = account_info_iter.next() {
+            if auth_info.key != &admin.auth || !auth_info.is_signer {
+                msg!("auth mismatch");
+                return Err(TeleportError::AuthFailed.into());
+            }
+        }
+
+        admin.auth = *new_auth;
+
+        admin
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_set_allowance(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        allowance: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut admin = Self::get_admin(program_id, admin_info)?;
+
+        if allowance >= admin.allowance {
+            Self::grant_allowance(program_id, config_info, allowance - admin.allowance)?;
+        } else {
+            let mut config = Self::get_config(program_id, config_info)?;
+            Self::revoke_allowance(&mut config, admin.allowance - allowance)?;
+            config.serialize(&mut &mut config_info.data.borrow_mut()[..])?;
+        }
+
+        admin.allowance = allowance;
+
+        admin
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_decrease_allowance(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        allowance: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut admin = Self::get_admin(program_id, admin_info)?;
+        if admin.allowance < allowance {
+            msg!("admin allowance isn't enough");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        admin.allowance -= allowance;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+        Self::revoke_allowance(&mut config, allowance)?;
+        config.serialize(&mut &mut config_info.data.borrow_mut()[..])?;
+
+        admin
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_close_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let target_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        // the rent would be zeroed along with the account
+        if target_info.key == admin_info.key {
+            msg!("target should not be the admin");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let admin = Self::get_admin(program_id, admin_info)?;
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        // a retired admin may already be out of the list
+        if config.contain_admin(admin_info.key) {
+            config.remove_admin(admin_info.key)?;
+        }
+        Self::revoke_allowance(&mut config, admin.allowance)?;
+        config.serialize(&mut &mut config_info.data.borrow_mut()[..])?;
+
+        let dest_starting_lamports = target_info.lamports();
+        **target_info.lamports.borrow_mut() = dest_starting_lamports
+            .checked_add(admin_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **admin_info.lamports.borrow_mut() = 0;
+        admin_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    pub fn process_migrate_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if config_info.owner != program_id || config_info.data_len() != state::ConfigV1::LEN {
+            msg!("config isn't a version 1 account");
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+        let mut config: state::Config =
+            state::ConfigV1::try_from_slice(&config_info.data.borrow())?.into();
+
+        // the live supply was minted before the counters existed, so
+        // `CheckInvariants` starts from it
+        if mint_info.key != &config.mint() || mint_info.owner != &config.token_program() {
+            msg!("unexpected mint");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+        config.total_minted = Self::get_mint(mint_info)?.supply;
+
+        Self::realloc(
+            owner_info,
+            config_info,
+            system_program_info,
+            state::Config::LEN,
+        )?;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_migrate_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if admin_info.owner != program_id || admin_info.data_len() != state::AdminV1::LEN {
+            msg!("admin isn't a version 1 account");
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+        let admin: state::Admin = state::AdminV1::try_from_slice(&admin_info.data.borrow())?.into();
+
+        // the allowance predates the counter, count it as granted so it can
+        // be revoked, the config has to be migrated first
+        Self::grant_allowance(program_id, config_info, admin.allowance)?;
+
+        Self::realloc(
+            owner_info,
+            admin_info,
+            system_program_info,
+            state::Admin::LEN,
+        )?;
+
+        admin
+            .serialize(&mut &mut admin_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    // the payer tops up the rent of the grown account
+    fn realloc<'a>(
+        payer_info: &AccountInfo<'a>,
+        account_info: &AccountInfo<'a>,
+        system_program_info: &AccountInfo<'a>,
+        new_len: usize,
+    ) -> ProgramResult {
+        let minimum_balance = Rent::get()?.minimum_balance(new_len);
+        let lamports = minimum_balance.saturating_sub(account_info.lamports());
+        if lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer_info.key, account_info.key, lamports),
+                &[
+                    payer_info.clone(),
+                    account_info.clone(),
+                    system_program_info.clone(),
+                ],
+            )?;
+        }
+
+        account_info.realloc(new_len, true)
+    }
+
+    pub fn process_add_to_denylist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        key: &DenylistKey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let denylist_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let seeds = key.seeds();
+        let (pda, bump) = Pubkey::find_program_address(&seeds, program_id);
+        if denylist_info.key != &pda {
+            msg!("denylist account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if denylist_info.lamports() > 0 {
+            msg!("already denylisted");
+            return Err(TeleportError::AlreadyInUse.into());
+        }
+
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_info.key,
+                denylist_info.key,
+                Rent::get()?.minimum_balance(state::DenylistEntry::LEN),
+                state::DenylistEntry::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_info.clone(),
+                denylist_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[seeds[0], seeds[1], &[bump]]],
+        )?;
+
+        state::DenylistEntry {
+            version: state::ACCOUNT_VERSION,
+            is_init: true,
+            reserved: [0; 16],
+        }
+        .serialize(&mut &mut denylist_info.data.borrow_mut()[..])
+        .map_err(|e| e.into())
+    }
+
+    pub fn process_remove_from_denylist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        key: &DenylistKey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let denylist_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if !Self::is_denylisted(program_id, denylist_info, key)? {
+            msg!("not denylisted");
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+
+        let dest_starting_lamports = owner_info.lamports();
+        **owner_info.lamports.borrow_mut() = dest_starting_lamports
+            .checked_add(denylist_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **denylist_info.lamports.borrow_mut() = 0;
+        denylist_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    pub fn process_add_receiver(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        receiver: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let receiver_entry_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if receiver == program_id {
+            msg!("the teleport program can't be a receiver");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let (pda, bump) =
+            Pubkey::find_program_address(&[state::RECEIVER_SEED, receiver.as_ref()], program_id);
+        if receiver_entry_info.key != &pda {
+            msg!("receiver account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if receiver_entry_info.lamports() > 0 {
+            msg!("already whitelisted");
+            return Err(TeleportError::AlreadyInUse.into());
+        }
+
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_info.key,
+                receiver_entry_info.key,
+                Rent::get()?.minimum_balance(state::ReceiverEntry::LEN),
+                state::ReceiverEntry::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_info.clone(),
+                receiver_entry_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&[state::RECEIVER_SEED, receiver.as_ref(), &[bump]]],
+        )?;
+
+        state::ReceiverEntry {
+            version: state::ACCOUNT_VERSION,
+            is_init: true,
+            reserved: [0; 16],
+        }
+        .serialize(&mut &mut receiver_entry_info.data.borrow_mut()[..])
+        .map_err(|e| e.into())
+    }
+
+    pub fn process_remove_receiver(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        receiver: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let receiver_entry_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if !Self::is_receiver(program_id, receiver_entry_info, receiver)? {
+            msg!("not whitelisted");
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+
+        let dest_starting_lamports = owner_info.lamports();
+        **owner_info.lamports.borrow_mut() = dest_starting_lamports
+            .checked_add(receiver_entry_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **receiver_entry_info.lamports.borrow_mut() = 0;
+        receiver_entry_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    pub fn process_set_lockup_schedule(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        id: u32,
+        steps: &[lockup::LockupStep],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let schedule_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if !lockup::LockupSchedule::is_valid_steps(steps) {
+            msg!(
+                "steps should be 1 to {} in timestamp order, each locking at most 1.0",
+                lockup::MAX_LOCKUP_STEPS
+            );
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let (pda, bump) = Pubkey::find_program_address(
+            &[lockup::LOCKUP_SCHEDULE_SEED, &id.to_le_bytes()],
+            program_id,
+        );
+        if schedule_info.key != &pda {
+            msg!("lockup schedule account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // a schedule is created once and replaced in place afterwards
+        if schedule_info.lamports() == 0 {
+            invoke_signed(
+                &system_instruction::create_account(
+                    owner_info.key,
+                    schedule_info.key,
+                    Rent::get()?.minimum_balance(lockup::LockupSchedule::LEN),
+                    lockup::LockupSchedule::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    owner_info.clone(),
+                    schedule_info.clone(),
+                    system_program_info.clone(),
+                ],
+                &[&[lockup::LOCKUP_SCHEDULE_SEED, &id.to_le_bytes(), &[bump]]],
+            )?;
+        } else if schedule_info.owner != program_id
+            || schedule_info.data_len() != lockup::LockupSchedule::LEN
+        {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        let mut schedule = lockup::LockupSchedule {
+            version: state::ACCOUNT_VERSION,
+            is_init: true,
+            id,
+            step_count: steps.len() as u8,
+            steps: [lockup::LockupStep::default(); lockup::MAX_LOCKUP_STEPS],
+            reserved: [0; 16],
+        };
+        schedule.steps[..steps.len()].copy_from_slice(steps);
+        schedule
+            .serialize(&mut &mut schedule_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_teleport_out_vesting(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        txhash: &[u8; 32],
+        amount: u64,
+        beneficiary: &Pubkey,
+        schedule_id: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let admin_auth_info = next_account_info(account_info_iter)?;
+        let record_info = next_account_info(account_info_iter)?;
+        let schedule_info = next_account_info(account_info_iter)?;
+        let vesting_info = next_account_info(account_info_iter)?;
+        let denylist_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let associated_token_program_info = next_account_info(account_info_iter)?;
+        let settle_infos = SettleInfos::new(account_info_iter, AddressType::Spl)?;
+        let rent = &Rent::from_account_info(settle_infos.rent_sysvar_info)?;
+
+        let (mut config, mut admin) =
+            Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        if let Err(e) = config.teleport_out_limits.check(amount) {
+            msg!("amount is out of the teleport out limits");
+            return Err(e.into());
+        }
+
+        if config.is_delayed(amount) {
+            msg!("amount above the delay threshold should use TeleportOut");
+            return Err(TeleportError::AmountDelayed.into());
+        }
+
+        // claims transfer out of the vault, which Token-2022 transfer fees
+        // and hooks would get in the way of
+        if config.token_program() != spl_token::id() {
+            msg!("vesting escrows only hold SPL Token mints");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        if admin.allowance < amount {
+            msg!("admin allowance isn't enough");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        admin.allowance -= amount;
+
+        Self::check_teleport_out_programs(
+            program_id,
+            settle_infos.wallet_program_info,
+            settle_infos.system_program_info,
+            settle_infos.teleport_program_info,
+        )?;
+
+        Self::check_token_route(
+            &config,
+            settle_infos.mint_info,
+            settle_infos.spl_token_program_info,
+        )?;
+
+        let schedule = Self::get_lockup_schedule(program_id, schedule_info, schedule_id)?;
+
+        Self::check_recipient(program_id, denylist_info, beneficiary)?;
+
+        if vault_authority_info.key != &lockup::get_vesting_vault_authority(program_id) {
+            msg!("vault authority mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // the vault is the associated token account of the vault authority
+        Self::create_associated_token_account(
+            settle_infos.fee_payer_info,
+            vault_authority_info,
+            settle_infos.to_info,
+            settle_infos.mint_info,
+            settle_infos.system_program_info,
+            settle_infos.spl_token_program_info,
+            associated_token_program_info,
+        )?;
+
+        Self::create_teleport_out_record(
+            program_id,
+            record_info,
+            settle_infos.fee_payer_info,
+            rent,
+            txhash,
+        )?;
+
+        let (pda, bump) =
+            Pubkey::find_program_address(&[lockup::VESTING_SEED, &txhash[..]], program_id);
+        if vesting_info.key != &pda {
+            msg!("vesting account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        invoke_signed(
+            &system_instruction::create_account(
+                settle_infos.fee_payer_info.key,
+                vesting_info.key,
+                rent.minimum_balance(lockup::Vesting::LEN),
+                lockup::Vesting::LEN as u64,
+                program_id,
+            ),
+            &[
+                settle_infos.fee_payer_info.clone(),
+                vesting_info.clone(),
+                settle_infos.system_program_info.clone(),
+            ],
+            &[&[lockup::VESTING_SEED, &txhash[..], &[bump]]],
+        )?;
+
+        let decimals = Self::get_mint_decimals(settle_infos.mint_info)?;
+        lockup::Vesting {
+            version: state::ACCOUNT_VERSION,
+            is_init: true,
+            tx_hash: *txhash,
+            beneficiary: *beneficiary,
+            schedule_id,
+            step_count: schedule.step_count,
+            steps: schedule.steps,
+            amount: state::to_base_units(amount, decimals)?,
+            claimed: 0,
+            payer: *settle_infos.fee_payer_info.key,
+            mint: *settle_infos.mint_info.key,
+            reserved: [0; 16],
+        }
+        .serialize(&mut &mut vesting_info.data.borrow_mut()[..])?;
+
+        Self::settle_teleport_out(program_id, &mut config, &settle_infos, rent, amount)?;
+
+        admin.serialize(&mut &mut admin_info.data.borrow_mut()[..])?;
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_claim_vesting(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let beneficiary_info = next_account_info(account_info_iter)?;
+        let vesting_info = next_account_info(account_info_iter)?;
+        let denylist_info = next_account_info(account_info_iter)?;
+        let vault_authority_info = next_account_info(account_info_iter)?;
+        let vault_info = next_account_info(account_info_iter)?;
+        let to_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let spl_token_program_info = next_account_info(account_info_iter)?;
+
+        if !beneficiary_info.is_signer {
+            msg!("beneficiary should be a signer");
+            return Err(TeleportError::AuthFailed.into());
+        }
+
+        let mut vesting = Self::get_vesting(program_id, vesting_info)?;
+        if beneficiary_info.key != &vesting.beneficiary {
+            msg!("beneficiary mismatch");
+            return Err(TeleportError::AuthFailed.into());
+        }
+        if payer_info.key != &vesting.payer {
+            msg!("payer mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // a beneficiary denylisted since the teleport out can't claim
+        Self::check_recipient(program_id, denylist_info, beneficiary_info.key)?;
+
+        let (vault_authority, bump) =
+            Pubkey::find_program_address(&[lockup::VESTING_VAULT_SEED], program_id);
+        if vault_authority_info.key != &vault_authority {
+            msg!("vault authority mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if vault_info.key != &get_associated_token_address(&vault_authority, &vesting.mint) {
+            msg!("vault mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if spl_token_program_info.key != &spl_token::id() {
+            msg!("unexpected token program");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+
+        let amount = vesting.claimable(Clock::get()?.unix_timestamp);
+        if amount == 0 {
+            msg!("nothing is unlocked yet");
+            return Err(TeleportError::NothingToClaim.into());
+        }
+
+        invoke_signed(
+            &spl_token::instruction::transfer(
+                spl_token_program_info.key,
+                vault_info.key,
+                to_info.key,
+                vault_authority_info.key,
+                &[],
+                amount,
+            )?,
+            &[
+                vault_info.clone(),
+                to_info.clone(),
+                vault_authority_info.clone(),
+                spl_token_program_info.clone(),
+            ],
+            &[&[lockup::VESTING_VAULT_SEED, &[bump]]],
+        )?;
+
+        vesting.claimed += amount;
+        if vesting.claimed < vesting.amount {
+            return vesting
+                .serialize(&mut &mut vesting_info.data.borrow_mut()[..])
+                .map_err(|e| e.into());
+        }
+
+        // fully claimed, the payer gets the rent back
+        let dest_starting_lamports = payer_info.lamports();
+        **payer_info.lamports.borrow_mut() = dest_starting_lamports
+            .checked_add(vesting_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **vesting_info.lamports.borrow_mut() = 0;
+        vesting_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    pub fn process_check_invariants(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        // check mint
+        if mint_info.key != &config.mint() || mint_info.owner != &config.token_program() {
+            msg!("unexpected mint");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+        let supply = Self::get_mint(mint_info)?.supply;
+
+        // holders can burn outside the bridge, so only a supply above what
+        // the bridge minted is a mismatch
+        if matches!(config.expected_supply(), Some(expected) if supply <= expected) {
+            return Ok(());
+        }
+
+        // freeze instead of failing, so the freeze sticks
+        msg!(
+            "supply {} exceeds minted {} minus burned {}, freeze",
+            supply,
+            config.total_minted,
+            config.total_burned
+        );
+        config.is_frozen = true;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_set_gas_drop(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        per_transfer: u64,
+        daily_cap: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        // a fresh wallet can't hold less than the rent-exempt minimum, the
+        // drop-off would fail the transfer
+        if per_transfer != 0 && per_transfer < Rent::get()?.minimum_balance(0) {
+            msg!("gas drop per transfer should not be below the rent-exempt minimum");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        config.gas_drop_per_transfer = per_transfer;
+        config.gas_drop_daily_cap = daily_cap;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_refill_gas_tank(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let gas_tank_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if gas_tank_info.key != &state::get_gas_tank_address(program_id) {
+            msg!("gas tank mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        invoke(
+            &system_instruction::transfer(owner_info.key, gas_tank_info.key, lamports),
+            &[
+                owner_info.clone(),
+                gas_tank_info.clone(),
+                system_program_info.clone(),
+            ],
+        )
+    }
+
+    pub fn process_withdraw_gas_tank(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        lamports: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let gas_tank_info = next_account_info(account_info_iter)?;
+        let target_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let (gas_tank, bump) = Pubkey::find_program_address(&[state::GAS_TANK_SEED], program_id);
+        if gas_tank_info.key != &gas_tank {
+            msg!("gas tank mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        // the tank keeps its rent exempt reserve, as the drop-offs do
+        let available = gas_tank_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0));
+        if lamports > available {
+            msg!("withdrawal capped at the lamports above the rent reserve");
+        }
+        let lamports = lamports.min(available);
+
+        let seeds: &[&[_]] = &[state::GAS_TANK_SEED, &[bump]];
+        invoke_signed(
+            &system_instruction::transfer(gas_tank_info.key, target_info.key, lamports),
+            &[
+                gas_tank_info.clone(),
+                target_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[seeds],
+        )
+    }
+
+    pub fn process_set_delayed_settlement(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        threshold: u64,
+        delay: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if delay < 0 {
+            msg!("challenge delay should not be negative");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        config.delay_threshold = threshold;
+        config.challenge_delay = delay;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_set_pauser(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pauser: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        config.pauser = *pauser;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_finalize_teleport_out(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let pending_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+        if config.is_frozen {
+            return Err(TeleportError::Freeze.into());
+        }
+
+        let pending = Self::get_pending_teleport_out(program_id, pending_info)?;
+        if Clock::get()?.unix_timestamp < pending.unlock_time {
+            msg!("challenge delay isn't over until {}", pending.unlock_time);
+            return Err(TeleportError::ChallengeDelayNotOver.into());
+        }
+
+        let settle_infos = SettleInfos::new(account_info_iter, pending.to_address_type)?;
+        let owner_denylist_info =
+            Self::next_owner_denylist_info(account_info_iter, pending.to_address_type)?;
+        if settle_infos.recipient().key != &pending.to {
+            msg!("recipient mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        let rent = &Rent::from_account_info(settle_infos.rent_sysvar_info)?;
+
+        Self::check_teleport_out_programs(
+            program_id,
+            settle_infos.wallet_program_info,
+            settle_infos.system_program_info,
+            settle_infos.teleport_program_info,
+        )?;
+
+        Self::check_token_route(
+            &config,
+            settle_infos.mint_info,
+            settle_infos.spl_token_program_info,
+        )?;
+
+        // the recipient may have been denylisted during the challenge delay
+        Self::check_recipient(
+            program_id,
+            settle_infos.denylist_info,
+            settle_infos.recipient().key,
+        )?;
+        if let Some(owner_denylist_info) = owner_denylist_info {
+            Self::check_token_owner(program_id, owner_denylist_info, settle_infos.to_info)?;
+        }
+
+        Self::settle_teleport_out(program_id, &mut config, &settle_infos, rent, pending.amount)?;
+
+        Self::close_pending_teleport_out(&pending, pending_info, payer_info)?;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_cancel_teleport_out(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let authority_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let pending_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let config = Self::get_config(program_id, config_info)?;
+
+        // the owner or the pauser
+        if authority_info.key != &config.pauser {
+            Self::only_owner(authority_info)?;
+        } else if !authority_info.is_signer {
+            msg!("pauser should be a singer");
+            return Err(TeleportError::AuthFailed.into());
+        }
+
+        let pending = Self::get_pending_teleport_out(program_id, pending_info)?;
+
+        // refund the allowance to the admin that relayed the transfer
+        if admin_info.key != &pending.admin {
+            msg!("admin mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        // a closed admin has no allowance left to refund
+        if admin_info.lamports() == 0 && admin_info.data_is_empty() {
+            msg!("admin is closed, allowance isn't refunded");
+        } else {
+            if admin_info.owner != program_id {
+                return Err(TeleportError::IncorrectProgramAccount.into());
+            }
+            let mut admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
+            if !admin.is_init {
+                return Err(TeleportError::UninitializedAccount.into());
+            }
+            admin.allowance = admin
+                .allowance
+                .checked_add(pending.amount)
+                .ok_or(TeleportError::UnexpectedError)?;
+            admin.serialize(&mut &mut admin_info.data.borrow_mut()[..])?;
+        }
+
+        Self::close_pending_teleport_out(&pending, pending_info, payer_info)
+    }
+
+    fn close_pending_teleport_out(
+        pending: &state::PendingTeleportOut,
+        pending_info: &AccountInfo,
+        payer_info: &AccountInfo,
+    ) -> ProgramResult {
+        if payer_info.key != &pending.payer {
+            msg!("payer mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        let dest_starting_lamports = payer_info.lamports();
+        **payer_info.lamports.borrow_mut() = dest_starting_lamports
+            .checked_add(pending_info.lamports())
+            .ok_or(ProgramError::InvalidAccountData)?;
+        **pending_info.lamports.borrow_mut() = 0;
+        pending_info.data.borrow_mut().fill(0);
 
         Ok(())
     }
 
-    pub fn process_deposit_allowance(
-        _program_id: &Pubkey,
+    pub fn process_close_teleport_out_record(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
-        allowance: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
         let admin_info = next_account_info(account_info_iter)?;
+        let admin_auth_info = next_account_info(account_info_iter)?;
+        let teleport_out_record_info = next_account_info(account_info_iter)?;
+        let target_info = next_account_info(account_info_iter)?;
+
+        Self::check_admin(program_id, config_info, admin_info, admin_auth_info)?;
+
+        // check teleport_out_record_info
+        let record = Self::get_teleport_out_record(program_id, teleport_out_record_info)?;
+
+        // refund the fee payer that funded the record
+        if record.payer != Pubkey::default() && target_info.key != &record.payer {
+            msg!("target should be the record payer");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        Self::close_teleport_out_record(teleport_out_record_info, target_info)
+    }
+
+    pub fn process_set_record_retention(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        retention: i64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
         Self::only_owner(owner_info)?;
 
-        let mut admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
-        if !admin.is_init {
-            return Err(TeleportError::UninitializedAccount.into());
+        if retention < 0 {
+            msg!("record retention should not be negative");
+            return Err(TeleportError::UnexpectedError.into());
         }
 
-        admin.allowance = admin.allowance.checked_add(allowance).unwrap();
+        let mut config = Self::get_config(program_id, config_info)?;
 
-        admin
-            .serialize(&mut *admin_info.data.borrow_mut())
+        config.record_retention = retention;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
             .map_err(|e| e.into())
     }
 
-    pub fn process_close_teleport_out_record(
+    pub fn process_set_amount_limits(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
+        teleport_in: state::AmountLimits,
+        teleport_out: state::AmountLimits,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
         let config_info = next_account_info(account_info_iter)?;
-        let admin_info = next_account_info(account_info_iter)?;
-        let admin_auth_info = next_account_info(account_info_iter)?;
-        let teleport_out_record_info = next_account_info(account_info_iter)?;
-        let target_info = next_account_info(account_info_iter)?;
 
-        // check config
-        let config = Self::get_config(program_id, config_info)?;
-        if config.is_frozen {
-            return Err(TeleportError::Freeze.into());
+        Self::only_owner(owner_info)?;
+
+        if !teleport_in.is_valid() || !teleport_out.is_valid() {
+            msg!("min amount should not exceed max amount");
+            return Err(TeleportError::UnexpectedError.into());
         }
 
-        // check admin & auth
-        if !config.contain_admin(admin_info.key) {
-            msg!("config doesn't contain admin key");
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        config.teleport_in_limits = teleport_in;
+        config.teleport_out_limits = teleport_out;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_set_token_route(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        if !state::is_supported_token_program(token_program) {
+            msg!("token program should be SPL Token or Token-2022");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+        if mint_info.key != mint || mint_info.owner != token_program {
+            msg!("mint should be owned by the token program");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+        // the supply counters only make sense for a single mint, they restart
+        // from its live supply so `CheckInvariants` doesn't freeze the config
+        let supply = Self::get_mint(mint_info)?.supply;
+
+        config.mint = *mint;
+        config.token_program = *token_program;
+        config.total_minted = supply;
+        config.total_burned = 0;
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_set_chain_enabled(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain: Chain,
+        enabled: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let owner_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        Self::only_owner(owner_info)?;
+
+        if chain == Chain::Flow {
+            msg!("flow is always enabled");
             return Err(TeleportError::UnexpectedError.into());
         }
-        let admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
-        if !admin.is_init {
-            return Err(TeleportError::UninitializedAccount.into());
+
+        let mut config = Self::get_config(program_id, config_info)?;
+
+        config.set_chain_enabled(chain, enabled);
+
+        config
+            .serialize(&mut &mut config_info.data.borrow_mut()[..])
+            .map_err(|e| e.into())
+    }
+
+    pub fn process_close_expired_teleport_out_records(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+
+        let config = Self::get_config(program_id, config_info)?;
+        if config.record_retention == 0 {
+            msg!("record retention isn't set");
+            return Err(TeleportError::UnexpectedError.into());
         }
 
-        if admin_auth_info.key != &admin.auth {
-            msg!("admin auth mismatch");
+        // remaining accounts are (record, payer) pairs
+        let pairs = account_info_iter.as_slice().chunks_exact(2);
+        if pairs.len() == 0 || !pairs.remainder().is_empty() {
+            msg!("remaining accounts should be (record, payer) pairs");
             return Err(TeleportError::UnexpectedError.into());
         }
-        if !admin_auth_info.is_signer {
-            return Err(TeleportError::MissingRequiredSignature.into());
+
+        let now = Clock::get()?.unix_timestamp;
+        for pair in pairs {
+            let record_info = &pair[0];
+            let payer_info = &pair[1];
+
+            let record = Self::get_teleport_out_record(program_id, record_info)?;
+            if record.payer == Pubkey::default() || payer_info.key != &record.payer {
+                msg!("payer mismatch");
+                return Err(TeleportError::UnexpectedError.into());
+            }
+            if now < record.created_at.saturating_add(config.record_retention) {
+                msg!("record is within the retention period");
+                return Err(TeleportError::UnexpectedError.into());
+            }
+
+            Self::close_teleport_out_record(record_info, payer_info)?;
         }
 
-        // check teleport_out_record_info
-        Self::get_teleport_out_record(program_id, teleport_out_record_info)?;
+        Ok(())
+    }
 
+    fn close_teleport_out_record(
+        record_info: &AccountInfo,
+        target_info: &AccountInfo,
+    ) -> ProgramResult {
         let dest_starting_lamports = target_info.lamports();
         **target_info.lamports.borrow_mut() = dest_starting_lamports
-            .checked_add(teleport_out_record_info.lamports())
+            .checked_add(record_info.lamports())
             .ok_or(ProgramError::InvalidAccountData)?;
-        **teleport_out_record_info.lamports.borrow_mut() = 0;
+        **record_info.lamports.borrow_mut() = 0;
+        record_info.data.borrow_mut().fill(0);
 
         Ok(())
     }
 
+    /// The owner key signs the transaction, the owner wallet PDA signs
+    /// through the multisig wallet program.
     fn only_owner(account_info: &AccountInfo) -> ProgramResult {
         let owner = Pubkey::from_str(state::OWNER_KEY).unwrap();
-        if account_info.key != &owner {
+        let owner_wallet = Pubkey::from_str(state::OWNER_WALLET_KEY).unwrap();
+        if account_info.key != &owner && account_info.key != &owner_wallet {
             msg!("owner mismatch");
             return Err(TeleportError::AuthFailed.into());
         }
@@ -609,8 +2700,218 @@ impl Processor {
         if !config.is_init {
             return Err(TeleportError::UninitializedAccount.into());
         }
+        Self::check_version(config.version)?;
+
+        Ok(config)
+    }
+
+    fn check_admin(
+        program_id: &Pubkey,
+        config_info: &AccountInfo,
+        admin_info: &AccountInfo,
+        admin_auth_info: &AccountInfo,
+    ) -> Result<(state::Config, state::Admin), ProgramError> {
+        // check config
+        let config = Self::get_config(program_id, config_info)?;
+        if config.is_frozen {
+            return Err(TeleportError::Freeze.into());
+        }
+
+        // check admin & auth
+        if !config.contain_admin(admin_info.key) {
+            msg!("config doesn't contain admin key");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        let admin = Self::get_admin(program_id, admin_info)?;
+
+        if admin_auth_info.key != &admin.auth {
+            msg!("admin auth mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+        if !admin_auth_info.is_signer {
+            return Err(TeleportError::MissingRequiredSignature.into());
+        }
+
+        Ok((config, admin))
+    }
+
+    fn check_version(version: u8) -> ProgramResult {
+        if version != state::ACCOUNT_VERSION {
+            msg!("account version {} should be migrated", version);
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+        Ok(())
+    }
+
+    fn get_admin(
+        program_id: &Pubkey,
+        admin_info: &AccountInfo,
+    ) -> Result<state::Admin, ProgramError> {
+        if admin_info.owner != program_id {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        if admin_info.data_len() != state::Admin::LEN {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        let admin = state::Admin::try_from_slice(&admin_info.data.borrow())?;
+        if !admin.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+        Self::check_version(admin.version)?;
+
+        Ok(admin)
+    }
+
+    /// Whether `denylist_info`, the denylist address of `key`, holds an entry.
+    fn is_denylisted(
+        program_id: &Pubkey,
+        denylist_info: &AccountInfo,
+        key: &DenylistKey,
+    ) -> Result<bool, ProgramError> {
+        if denylist_info.key != &state::get_denylist_address(program_id, key) {
+            msg!("denylist account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        if denylist_info.owner != program_id
+            || denylist_info.data_len() != state::DenylistEntry::LEN
+        {
+            return Ok(false);
+        }
+
+        Ok(state::DenylistEntry::try_from_slice(&denylist_info.data.borrow())?.is_init)
+    }
+
+    /// Whether `receiver_entry_info`, the whitelist address of `receiver`,
+    /// holds an entry.
+    fn is_receiver(
+        program_id: &Pubkey,
+        receiver_entry_info: &AccountInfo,
+        receiver: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        if receiver_entry_info.key != &state::get_receiver_address(program_id, receiver) {
+            msg!("receiver account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        if receiver_entry_info.owner != program_id
+            || receiver_entry_info.data_len() != state::ReceiverEntry::LEN
+        {
+            return Ok(false);
+        }
+
+        Ok(state::ReceiverEntry::try_from_slice(&receiver_entry_info.data.borrow())?.is_init)
+    }
+
+    /// A receipt that is neither unlocked nor refunded yet.
+    fn get_pending_teleport_in_receipt(
+        program_id: &Pubkey,
+        receipt_info: &AccountInfo,
+    ) -> Result<state::TeleportInReceipt, ProgramError> {
+        if receipt_info.owner != program_id {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        if receipt_info.data_len() != state::TeleportInReceipt::LEN {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        let receipt = state::TeleportInReceipt::try_from_slice(&receipt_info.data.borrow())?;
+        if !receipt.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+        Self::check_version(receipt.version)?;
+
+        if receipt.status != state::TeleportInStatus::Pending {
+            msg!("teleport in is already {:?}", receipt.status);
+            return Err(TeleportError::TeleportInSettled.into());
+        }
+
+        Ok(receipt)
+    }
+
+    fn get_lockup_schedule(
+        program_id: &Pubkey,
+        schedule_info: &AccountInfo,
+        id: u32,
+    ) -> Result<lockup::LockupSchedule, ProgramError> {
+        if schedule_info.key != &lockup::get_lockup_schedule_address(program_id, id) {
+            msg!("lockup schedule account mismatch");
+            return Err(TeleportError::UnexpectedError.into());
+        }
+
+        if schedule_info.owner != program_id
+            || schedule_info.data_len() != lockup::LockupSchedule::LEN
+        {
+            msg!("unknown lockup schedule");
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+
+        let schedule = lockup::LockupSchedule::try_from_slice(&schedule_info.data.borrow())?;
+        if !schedule.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+        Self::check_version(schedule.version)?;
+
+        Ok(schedule)
+    }
+
+    fn get_vesting(
+        program_id: &Pubkey,
+        vesting_info: &AccountInfo,
+    ) -> Result<lockup::Vesting, ProgramError> {
+        if vesting_info.owner != program_id {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        if vesting_info.data_len() != lockup::Vesting::LEN {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        let vesting = lockup::Vesting::try_from_slice(&vesting_info.data.borrow())?;
+        if !vesting.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+        Self::check_version(vesting.version)?;
+
+        Ok(vesting)
+    }
+
+    fn get_mint_decimals(mint_info: &AccountInfo) -> Result<u8, ProgramError> {
+        Ok(Self::get_mint(mint_info)?.decimals)
+    }
+
+    /// An SPL Token or a Token-2022 mint, extensions are skipped.
+    fn get_mint(mint_info: &AccountInfo) -> Result<Mint, ProgramError> {
+        if !state::is_supported_token_program(mint_info.owner) {
+            msg!("mint should be owned by the token program");
+            return Err(TeleportError::InvalidTokenRoute.into());
+        }
+
+        Ok(StateWithExtensions::<Mint>::unpack(&mint_info.data.borrow())?.base)
+    }
+
+    fn get_pending_teleport_out(
+        program_id: &Pubkey,
+        pending_info: &AccountInfo,
+    ) -> Result<state::PendingTeleportOut, ProgramError> {
+        if pending_info.owner != program_id {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        if pending_info.data_len() != state::PendingTeleportOut::LEN {
+            return Err(TeleportError::IncorrectProgramAccount.into());
+        }
+
+        let pending = state::PendingTeleportOut::try_from_slice(&pending_info.data.borrow())?;
+        if !pending.is_init {
+            return Err(TeleportError::UninitializedAccount.into());
+        }
+        Self::check_version(pending.version)?;
 
-        return Ok(config);
+        Ok(pending)
     }
 
     fn get_teleport_out_record(
@@ -621,16 +2922,82 @@ impl Processor {
             return Err(TeleportError::IncorrectProgramAccount.into());
         }
 
-        if teleport_out_record_info.data_len() != state::TeleportOutRecord::LEN {
+        let data_len = teleport_out_record_info.data_len();
+        if data_len != state::TeleportOutRecord::LEN
+            && data_len != state::TeleportOutRecord::LEGACY_LEN
+        {
             return Err(TeleportError::IncorrectProgramAccount.into());
         }
 
         let teleport_out_record =
-            state::TeleportOutRecord::try_from_slice(&teleport_out_record_info.data.borrow())?;
+            state::TeleportOutRecord::unpack(&teleport_out_record_info.data.borrow())?;
         if !teleport_out_record.is_init {
             return Err(TeleportError::UninitializedAccount.into());
         }
 
-        return Ok(teleport_out_record);
+        Ok(teleport_out_record)
+    }
+}
+
+/// Accounts that mint a teleport out, in the order of
+/// `instruction::settle_accounts`.
+struct SettleInfos<'a, 'b> {
+    wallet_info: &'a AccountInfo<'b>,
+    wallet_signer_info: &'a AccountInfo<'b>,
+    fee_payer_info: &'a AccountInfo<'b>,
+    wallet_program_info: &'a AccountInfo<'b>,
+    mint_info: &'a AccountInfo<'b>,
+    to_info: &'a AccountInfo<'b>,
+    mint_auth_info: &'a AccountInfo<'b>,
+    spl_token_program_info: &'a AccountInfo<'b>,
+    system_program_info: &'a AccountInfo<'b>,
+    teleport_program_info: &'a AccountInfo<'b>,
+    rent_sysvar_info: &'a AccountInfo<'b>,
+    denylist_info: &'a AccountInfo<'b>,
+    wallet_recipient: Option<WalletRecipientInfos<'a, 'b>>,
+}
+
+/// Extra accounts of an `AddressType::Sol` recipient.
+struct WalletRecipientInfos<'a, 'b> {
+    to_owner_info: &'a AccountInfo<'b>,
+    associated_token_program_info: &'a AccountInfo<'b>,
+    gas_tank_info: &'a AccountInfo<'b>,
+}
+
+impl<'a, 'b> SettleInfos<'a, 'b> {
+    fn new(
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+        to_address_type: AddressType,
+    ) -> Result<Self, ProgramError> {
+        Ok(Self {
+            wallet_info: next_account_info(account_info_iter)?,
+            wallet_signer_info: next_account_info(account_info_iter)?,
+            fee_payer_info: next_account_info(account_info_iter)?,
+            wallet_program_info: next_account_info(account_info_iter)?,
+            mint_info: next_account_info(account_info_iter)?,
+            to_info: next_account_info(account_info_iter)?,
+            mint_auth_info: next_account_info(account_info_iter)?,
+            spl_token_program_info: next_account_info(account_info_iter)?,
+            system_program_info: next_account_info(account_info_iter)?,
+            teleport_program_info: next_account_info(account_info_iter)?,
+            rent_sysvar_info: next_account_info(account_info_iter)?,
+            denylist_info: next_account_info(account_info_iter)?,
+            wallet_recipient: match to_address_type {
+                AddressType::Sol => Some(WalletRecipientInfos {
+                    to_owner_info: next_account_info(account_info_iter)?,
+                    associated_token_program_info: next_account_info(account_info_iter)?,
+                    gas_tank_info: next_account_info(account_info_iter)?,
+                }),
+                AddressType::Spl => None,
+            },
+        })
+    }
+
+    /// The wallet for `AddressType::Sol`, the token account otherwise.
+    fn recipient(&self) -> &'a AccountInfo<'b> {
+        match &self.wallet_recipient {
+            Some(wallet_recipient) => wallet_recipient.to_owner_info,
+            None => self.to_info,
+        }
     }
 }
@@ -1,14 +1,35 @@
 //! State transition types
 
 use {
-    crate::error::TeleportError,
+    crate::{
+        error::TeleportError,
+        instruction::{AddressType, Chain, DenylistKey},
+    },
     borsh::{BorshDeserialize, BorshSerialize},
     solana_program::{msg, program_error::ProgramError, pubkey::Pubkey},
+    spl_token_2022::{extension::StateWithExtensions, state::Account as TokenAccount},
+    std::str::FromStr,
 };
 
 // TODO use env!
 pub const OWNER_KEY: &str = "Ad8pZMQCznufvH85mVhyUyuchhEvBRMMMSawJTPCBVZq";
 
+/// Owner wallet of a build whose owner has no multisig wallet yet: the system
+/// program id. No transaction signature or program invoke can sign for it,
+/// so only `OWNER_KEY` passes the owner check.
+pub const UNSET_OWNER_WALLET_KEY: &str = "11111111111111111111111111111111";
+
+// TODO use env!
+/// Multisig wallet PDA that acts as the owner too, signing through the wallet
+/// invoke instruction. A release for a wallet owner sets it to the wallet's
+/// PDA under `MULTISIG_PROGRAM_KEY`, seeded with the wallet key; until then
+/// it's `UNSET_OWNER_WALLET_KEY`.
+#[cfg(not(feature = "test-bpf"))]
+pub const OWNER_WALLET_KEY: &str = UNSET_OWNER_WALLET_KEY;
+/// The PDA of the `[8; 32]` wallet the tests invoke through.
+#[cfg(feature = "test-bpf")]
+pub const OWNER_WALLET_KEY: &str = "B3VkRRDdXqTLYtXwnJ5rdbuCMTJG1w3h2EvYaNBFisL9";
+
 // TODO use env!
 pub const MULTISIG_PROGRAM_KEY: &str = "D6vhDDD47LqHfHGps5YKkzJNBhXasrTf5LbNmkF3XHww";
 
@@ -17,19 +38,238 @@ pub const BLT_MINT_KEY: &str = "BLT1noyNr3GttckEVrtcfC6oyK6yV1DpPgSyXbncMwef";
 
 pub const SIGNER_SEED: &[u8] = b"BLT";
 
+pub const GAS_TANK_SEED: &[u8] = b"gas_tank";
+
+pub const PENDING_TELEPORT_OUT_SEED: &[u8] = b"pending";
+
+pub const DENYLIST_SEED: &[u8] = b"denylist";
+
+pub const FLOW_DENYLIST_SEED: &[u8] = b"denylist_flow";
+
+pub const EVM_DENYLIST_SEED: &[u8] = b"denylist_evm";
+
+pub const RECEIVER_SEED: &[u8] = b"receiver";
+
+pub const CALLER_SEED: &[u8] = b"caller";
+
+pub const TELEPORT_IN_RECEIPT_SEED: &[u8] = b"teleport_in";
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
 pub const MAX_ADMIN: usize = 5;
 
+/// Decimals of a Flow UFix64, the unit of teleport amounts.
+pub const FLOW_DECIMALS: u8 = 8;
+
+/// Max bytes of a teleport payload.
+pub const MAX_PAYLOAD_LEN: usize = 256;
+
+/// Max entries in one `TeleportOutBatch`. Every entry adds 4 accounts and 72
+/// bytes of data, a full batch with 2 signers has to fit the 1232 bytes of a
+/// legacy transaction. The relayer and the CLI only send legacy
+/// transactions, and a lookup table wouldn't help much anyway: the record
+/// and the recipient of each entry are new for every transfer.
+pub const MAX_TELEPORT_OUT_BATCH: usize = 3;
+
+/// Address of the program signer, the multisig wallet owner.
+pub fn get_signer_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SIGNER_SEED], program_id).0
+}
+
+/// Address of the record that marks a Flow tx hash as teleported out.
+pub fn get_teleport_out_record_address(program_id: &Pubkey, tx_hash: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[&tx_hash[..]], program_id).0
+}
+
+/// Address of the gas tank, the system account that funds SOL drop-offs.
+pub fn get_gas_tank_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[GAS_TANK_SEED], program_id).0
+}
+
+/// Address of the pending transfer of a delayed `TeleportOut`.
+pub fn get_pending_teleport_out_address(program_id: &Pubkey, tx_hash: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[PENDING_TELEPORT_OUT_SEED, &tx_hash[..]], program_id).0
+}
+
+/// Address of the denylist entry of `key`, it exists while `key` is denied.
+pub fn get_denylist_address(program_id: &Pubkey, key: &DenylistKey) -> Pubkey {
+    Pubkey::find_program_address(&key.seeds(), program_id).0
+}
+
+/// Address of the whitelist entry of `receiver`, a program that can be called
+/// with the payload of a teleport out.
+pub fn get_receiver_address(program_id: &Pubkey, receiver: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[RECEIVER_SEED, receiver.as_ref()], program_id).0
+}
+
+/// Address of the receipt of the `TeleportIn` of `from_auth` with `nonce`.
+pub fn get_teleport_in_receipt_address(
+    program_id: &Pubkey,
+    from_auth: &Pubkey,
+    nonce: u64,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TELEPORT_IN_RECEIPT_SEED,
+            from_auth.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+/// Address that signs the calls to receiver programs. It holds no other
+/// authority, receivers check it to trust the call.
+pub fn get_caller_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CALLER_SEED], program_id).0
+}
+
+/// Token programs a config can bridge through. Token-2022 mints may carry
+/// transfer fees or transfer hooks, neither applies to the mint and burn the
+/// bridge does, so amounts stay exact.
+pub fn is_supported_token_program(token_program: &Pubkey) -> bool {
+    token_program == &spl_token::id() || token_program == &spl_token_2022::id()
+}
+
+/// Owner of an SPL Token or Token-2022 account from its data.
+pub fn get_token_account_owner(data: &[u8]) -> Result<Pubkey, ProgramError> {
+    Ok(StateWithExtensions::<TokenAccount>::unpack(data)?
+        .base
+        .owner)
+}
+
+/// Converts a UFix64 amount into base units of a mint with `decimals`.
+/// Dust below the mint precision can't be represented and is rejected.
+pub fn to_base_units(amount: u64, decimals: u8) -> Result<u64, ProgramError> {
+    if decimals >= FLOW_DECIMALS {
+        return 10u64
+            .checked_pow((decimals - FLOW_DECIMALS) as u32)
+            .and_then(|scale| amount.checked_mul(scale))
+            .ok_or_else(|| {
+                msg!("amount overflows the mint precision");
+                TeleportError::AmountNotRepresentable.into()
+            });
+    }
+
+    let scale = 10u64.pow((FLOW_DECIMALS - decimals) as u32);
+    let base_units = amount / scale;
+    if base_units * scale != amount {
+        msg!("amount has dust below the mint precision");
+        return Err(TeleportError::AmountNotRepresentable.into());
+    }
+    Ok(base_units)
+}
+
+/// Layout version of the program accounts. Version 1 predates the version
+/// byte, its accounts are told apart by their length.
+pub const ACCOUNT_VERSION: u8 = 2;
+
 /// Program states.
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct Config {
+    pub version: u8,
     pub is_init: bool,
     pub is_frozen: bool,
     pub admins: [Pubkey; MAX_ADMIN],
+    /// Lamports dropped off to a fresh wallet recipient, zero disables it.
+    pub gas_drop_per_transfer: u64,
+    /// Lamports that can be dropped off in a day.
+    pub gas_drop_daily_cap: u64,
+    /// Day of `gas_dropped_today`, in days since the unix epoch.
+    pub gas_drop_day: i64,
+    pub gas_dropped_today: u64,
+    /// Transfers of at least this amount wait out `challenge_delay` before
+    /// minting, zero disables it.
+    pub delay_threshold: u64,
+    /// Seconds a pending transfer waits before it can be finalized.
+    pub challenge_delay: i64,
+    /// Key that can cancel a pending transfer besides the owner.
+    pub pauser: Pubkey,
+    /// Seconds after which anyone can close a teleport out record, zero
+    /// disables it. It should outlast any replay of the Flow tx hash.
+    pub record_retention: i64,
+    /// Base units minted by teleport outs.
+    pub total_minted: u64,
+    /// Base units burned by teleport ins.
+    pub total_burned: u64,
+    /// Base units collected as fees. The lock and unlock fees are taken by
+    /// the Flow custody, the program takes none and it stays zero so far.
+    pub total_fees: u64,
+    /// UFix64 allowance granted to admins.
+    pub total_allowance_granted: u64,
+    pub teleport_in_limits: AmountLimits,
+    /// Checked when a teleport out is requested, a pending transfer settles
+    /// even if the limits change in between.
+    pub teleport_out_limits: AmountLimits,
+    /// Bit `1 << chain id` is set for each enabled `TeleportIn` destination
+    /// besides Flow.
+    pub enabled_chains: u64,
+    /// Mint bridged by this config, `BLT_MINT_KEY` when unset.
+    pub mint: Pubkey,
+    /// SPL Token or Token-2022, SPL Token when unset.
+    pub token_program: Pubkey,
+    /// Room for new fields without a realloc, in 8 byte words.
+    pub reserved: [u64; 3],
 }
 
 impl Config {
-    pub const LEN: usize = 162;
+    pub const LEN: usize = 411;
+
+    /// The BLT supply shouldn't exceed what the bridge minted minus what it
+    /// burned, it's lower when holders burn outside the bridge.
+    pub fn expected_supply(&self) -> Option<u64> {
+        self.total_minted.checked_sub(self.total_burned)
+    }
+
+    pub fn is_delayed(&self, amount: u64) -> bool {
+        self.delay_threshold != 0 && amount >= self.delay_threshold
+    }
+
+    /// Lamports to drop off for a transfer at `now`, zero when disabled or
+    /// when a drop-off would exceed the daily cap.
+    pub fn gas_drop(&mut self, now: i64) -> u64 {
+        let day = now / SECONDS_PER_DAY;
+        if day != self.gas_drop_day {
+            self.gas_drop_day = day;
+            self.gas_dropped_today = 0;
+        }
+
+        match self
+            .gas_dropped_today
+            .checked_add(self.gas_drop_per_transfer)
+        {
+            Some(total) if total <= self.gas_drop_daily_cap => self.gas_drop_per_transfer,
+            _ => 0,
+        }
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        if self.mint == Pubkey::default() {
+            return Pubkey::from_str(BLT_MINT_KEY).unwrap();
+        }
+        self.mint
+    }
+
+    pub fn token_program(&self) -> Pubkey {
+        if self.token_program == Pubkey::default() {
+            return spl_token::id();
+        }
+        self.token_program
+    }
+
+    pub fn is_chain_enabled(&self, chain: Chain) -> bool {
+        chain == Chain::Flow || self.enabled_chains & (1 << chain as u8) != 0
+    }
+
+    pub fn set_chain_enabled(&mut self, chain: Chain, enabled: bool) {
+        if enabled {
+            self.enabled_chains |= 1 << chain as u8;
+        } else {
+            self.enabled_chains &= !(1 << chain as u8);
+        }
+    }
 
     pub fn add_admin(&mut self, add_admin_key: &Pubkey) -> Result<(), ProgramError> {
         for admin in &mut self.admins {
@@ -63,24 +303,223 @@ impl Config {
     }
 }
 
+/// Bounds of a single transfer in UFix64 units, a zero bound is unset.
+/// Clients can run `check` against a fetched `Config` before sending.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct AmountLimits {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl AmountLimits {
+    pub fn is_valid(&self) -> bool {
+        self.max == 0 || self.min <= self.max
+    }
+
+    pub fn check(&self, amount: u64) -> Result<(), TeleportError> {
+        if amount < self.min {
+            return Err(TeleportError::AmountBelowMinimum);
+        }
+        if self.max != 0 && amount > self.max {
+            return Err(TeleportError::AmountAboveMaximum);
+        }
+        Ok(())
+    }
+}
+
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct Admin {
+    pub version: u8,
     pub is_init: bool,
     pub auth: Pubkey,
     pub allowance: u64,
+    pub reserved: [u8; 32],
 }
 
 impl Admin {
+    pub const LEN: usize = 74;
+}
+
+/// `Config` before the version byte.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ConfigV1 {
+    pub is_init: bool,
+    pub is_frozen: bool,
+    pub admins: [Pubkey; MAX_ADMIN],
+}
+
+impl ConfigV1 {
+    pub const LEN: usize = 162;
+}
+
+impl From<ConfigV1> for Config {
+    fn from(config: ConfigV1) -> Self {
+        Self {
+            version: ACCOUNT_VERSION,
+            is_init: config.is_init,
+            is_frozen: config.is_frozen,
+            admins: config.admins,
+            gas_drop_per_transfer: 0,
+            gas_drop_daily_cap: 0,
+            gas_drop_day: 0,
+            gas_dropped_today: 0,
+            delay_threshold: 0,
+            challenge_delay: 0,
+            pauser: Pubkey::default(),
+            record_retention: 0,
+            total_minted: 0,
+            total_burned: 0,
+            total_fees: 0,
+            total_allowance_granted: 0,
+            teleport_in_limits: AmountLimits::default(),
+            teleport_out_limits: AmountLimits::default(),
+            enabled_chains: 0,
+            mint: Pubkey::default(),
+            token_program: Pubkey::default(),
+            reserved: [0; 3],
+        }
+    }
+}
+
+/// `Admin` before the version byte.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct AdminV1 {
+    pub is_init: bool,
+    pub auth: Pubkey,
+    pub allowance: u64,
+}
+
+impl AdminV1 {
     pub const LEN: usize = 41;
 }
 
+impl From<AdminV1> for Admin {
+    fn from(admin: AdminV1) -> Self {
+        Self {
+            version: ACCOUNT_VERSION,
+            is_init: admin.is_init,
+            auth: admin.auth,
+            allowance: admin.allowance,
+            reserved: [0; 32],
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 pub struct TeleportOutRecord {
+    pub version: u8,
     pub is_init: bool,
+    /// Fee payer that funded the record, refunded when it's closed.
+    pub payer: Pubkey,
+    pub created_at: i64,
+    pub reserved: [u8; 16],
 }
 
 impl TeleportOutRecord {
-    pub const LEN: usize = 1;
+    pub const LEN: usize = 58;
+
+    /// Version 1 records only hold `is_init`. They are read in place, as
+    /// they are never written again.
+    pub const LEGACY_LEN: usize = 1;
+
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() == Self::LEGACY_LEN {
+            return Ok(Self {
+                version: 1,
+                is_init: bool::try_from_slice(data)?,
+                payer: Pubkey::default(),
+                created_at: 0,
+                reserved: [0; 16],
+            });
+        }
+        Ok(Self::try_from_slice(data)?)
+    }
+}
+
+/// A delayed `TeleportOut`, minted by `FinalizeTeleportOut` after
+/// `unlock_time` unless cancelled first.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct PendingTeleportOut {
+    pub version: u8,
+    pub is_init: bool,
+    pub tx_hash: [u8; 32],
+    pub admin: Pubkey,
+    /// Receives the rent back when the pending transfer is closed.
+    pub payer: Pubkey,
+    pub to: Pubkey,
+    pub to_address_type: AddressType,
+    /// In Flow UFix64 units.
+    pub amount: u64,
+    pub unlock_time: i64,
+    pub reserved: [u8; 32],
+}
+
+impl PendingTeleportOut {
+    pub const LEN: usize = 179;
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum TeleportInStatus {
+    /// Burned, the unlock on the destination isn't confirmed yet.
+    Pending,
+    /// Unlocked on the destination.
+    Unlocked,
+    /// The destination rejected it, the sender got the tokens back.
+    Refunded,
+}
+
+/// Receipt of a `TeleportIn`, settled once as either unlocked or refunded.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct TeleportInReceipt {
+    pub version: u8,
+    pub is_init: bool,
+    pub from_auth: Pubkey,
+    pub nonce: u64,
+    /// Token account the tokens were burned from, a refund mints back to it.
+    pub from: Pubkey,
+    /// In Flow UFix64 units.
+    pub amount: u64,
+    pub status: TeleportInStatus,
+    pub created_at: i64,
+    pub payer: Pubkey,
+    pub reserved: [u8; 16],
+}
+
+impl TeleportInReceipt {
+    pub const LEN: usize = 139;
+}
+
+/// Marks the Solana pubkey or the Flow address its address is derived from
+/// as denied.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct DenylistEntry {
+    pub version: u8,
+    pub is_init: bool,
+    pub reserved: [u8; 16],
+}
+
+impl DenylistEntry {
+    pub const LEN: usize = 18;
+}
+
+/// Whitelists the receiver program its address is derived from.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct ReceiverEntry {
+    pub version: u8,
+    pub is_init: bool,
+    pub reserved: [u8; 16],
+}
+
+impl ReceiverEntry {
+    pub const LEN: usize = 18;
 }
@@ -0,0 +1,311 @@
+#![cfg(feature = "test-bpf")]
+
+// Kept apart from tests.rs: the event log hook swaps the process wide syscall
+// stubs, so this binary runs a single test.
+
+use {
+    blt_teleport::{event::TeleportEvent, instruction::Chain},
+    borsh::de::BorshDeserialize,
+    solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+        program_option::COption,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        system_instruction,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        bs58,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    },
+    std::str::FromStr,
+};
+
+fn multisig_program_id() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::MULTISIG_PROGRAM_KEY).unwrap()
+}
+
+fn blt_mint() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::BLT_MINT_KEY).unwrap()
+}
+
+fn get_owner() -> Keypair {
+    Keypair::from_bytes(&[
+        216, 77, 18, 252, 166, 244, 245, 106, 13, 111, 107, 26, 108, 43, 230, 245, 113, 207, 205,
+        116, 14, 52, 64, 196, 192, 63, 41, 220, 146, 82, 66, 53, 161, 111, 219, 59, 147, 221, 7,
+        127, 88, 42, 141, 30, 211, 69, 198, 108, 142, 249, 183, 249, 92, 127, 241, 91, 118, 190,
+        46, 20, 186, 220, 132, 23,
+    ])
+    .unwrap()
+}
+
+// Same stand-in for the multisig wallet program as in tests.rs.
+fn process_mock_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    assert_eq!(input[0], 3);
+    let program_info = &accounts[input[1] as usize];
+    let total = u16::from_le_bytes([input[2], input[3]]) as usize;
+
+    let mut metas = Vec::with_capacity(total);
+    let mut infos = Vec::with_capacity(total + 1);
+    for pair in input[4..4 + total * 2].chunks_exact(2) {
+        let info = &accounts[pair[0] as usize];
+        metas.push(AccountMeta {
+            pubkey: *info.key,
+            is_signer: pair[1] & 2 != 0,
+            is_writable: pair[1] & 1 != 0,
+        });
+        infos.push(info.clone());
+    }
+    infos.push(program_info.clone());
+
+    let wallet_key = accounts[0].key;
+    let (_, bump) = Pubkey::find_program_address(&[wallet_key.as_ref()], program_id);
+    invoke_signed(
+        &Instruction {
+            program_id: *program_info.key,
+            accounts: metas,
+            data: input[4 + total * 2..].to_vec(),
+        },
+        &infos,
+        &[&[wallet_key.as_ref(), &[bump]]],
+    )
+}
+
+fn add_packable_account<T: Pack>(program_test: &mut ProgramTest, address: Pubkey, state: &T) {
+    let mut data = vec![0; T::get_packed_len()];
+    T::pack_into_slice(state, &mut data);
+    program_test.add_account(
+        address,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+}
+
+// The program-test stubs print `Program data:` entries to stdout, these
+// wrap them and log the entries as `data: <base58>` messages instead, which
+// show up in the transaction metadata.
+struct EventLogStubs(Box<dyn SyscallStubs>);
+
+struct NoStubs;
+
+impl SyscallStubs for NoStubs {}
+
+const EVENT_LOG_PREFIX: &str = "Program log: data: ";
+
+impl SyscallStubs for EventLogStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0
+            .sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.0.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.0.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        for field in fields {
+            self.0
+                .sol_log(&format!("data: {}", bs58::encode(field).into_string()));
+        }
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+// Must run after `ProgramTest::start`, which installs the stubs it wraps.
+fn hook_event_log() {
+    let stubs = set_syscall_stubs(Box::new(NoStubs));
+    set_syscall_stubs(Box::new(EventLogStubs(stubs)));
+}
+
+fn logged_events(log_messages: &[String]) -> Vec<TeleportEvent> {
+    log_messages
+        .iter()
+        .filter_map(|message| message.strip_prefix(EVENT_LOG_PREFIX))
+        .map(|data| {
+            let data = bs58::decode(data).into_vec().unwrap();
+            TeleportEvent::try_from_slice(&data).unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_teleport_in_event_amount() {
+    let mut program_test = ProgramTest::new(
+        "blt_teleport",
+        blt_teleport::id(),
+        processor!(blt_teleport::processor::Processor::process_instruction),
+    );
+    program_test.add_program(
+        "multisig",
+        multisig_program_id(),
+        processor!(process_mock_multisig),
+    );
+
+    // a 6 decimals mint, 100 UFix64 units burn one base unit
+    let wallet = Pubkey::new_unique();
+    let (mint_auth, _) = Pubkey::find_program_address(&[wallet.as_ref()], &multisig_program_id());
+    add_packable_account(
+        &mut program_test,
+        blt_mint(),
+        &spl_token::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply: 10,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+    let from_auth = Keypair::new();
+    let from = Pubkey::new_unique();
+    add_packable_account(
+        &mut program_test,
+        from,
+        &spl_token::state::Account {
+            mint: blt_mint(),
+            owner: from_auth.pubkey(),
+            amount: 10,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    hook_event_log();
+
+    let owner = get_owner();
+    let config = Keypair::new();
+    let rent = banks_client.get_rent().await.unwrap();
+    let config_len = blt_teleport::state::Config::LEN;
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &config.pubkey(),
+                rent.minimum_balance(config_len),
+                config_len as u64,
+                &blt_teleport::id(),
+            ),
+            blt_teleport::instruction::init_config(
+                &blt_teleport::id(),
+                &owner.pubkey(),
+                &config.pubkey(),
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner, &config], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let flow_address = [7; 8];
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_in(
+            &blt_teleport::id(),
+            &config.pubkey(),
+            &wallet,
+            &mint_auth,
+            &from,
+            &blt_mint(),
+            &spl_token::id(),
+            &from_auth.pubkey(),
+            &payer.pubkey(),
+            0,
+            300,
+            Chain::Flow,
+            &flow_address,
+            None,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &from_auth], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+
+    // 3 base units burned, the event keeps the UFix64 amount
+    let token_account = banks_client.get_account(from).await.unwrap().unwrap();
+    assert_eq!(
+        spl_token::state::Account::unpack(&token_account.data)
+            .unwrap()
+            .amount,
+        7
+    );
+    assert_eq!(
+        logged_events(&result.metadata.unwrap().log_messages),
+        vec![TeleportEvent::TeleportedIn {
+            from_auth: from_auth.pubkey(),
+            nonce: 0,
+            amount: 300,
+            chain: Chain::Flow,
+            to: flow_address.to_vec(),
+            payload: None,
+        }]
+    );
+}
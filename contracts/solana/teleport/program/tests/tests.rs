@@ -1,26 +1,121 @@
 #![cfg(feature = "test-bpf")]
 
 use {
-    blt_teleport::error::TeleportError,
-    borsh::de::BorshDeserialize,
+    blt_teleport::{
+        error::TeleportError,
+        instruction::{
+            AddressType, Chain, DenylistKey, ReceiverMessage, TeleportInstruction, TeleportOutEntry,
+        },
+        lockup::{
+            get_vesting_address, get_vesting_vault_address, LockupStep, Vesting, FULLY_LOCKED,
+        },
+        state::{get_teleport_in_receipt_address, TeleportInReceipt, TeleportInStatus},
+    },
+    borsh::{de::BorshDeserialize, BorshSerialize},
     solana_program::{
-        hash::Hash, instruction::InstructionError, pubkey::Pubkey, system_instruction,
+        account_info::AccountInfo,
+        clock::Clock,
+        entrypoint::ProgramResult,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        program::invoke_signed,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        system_instruction,
     },
     solana_program_test::*,
     solana_sdk::{
+        account::Account,
+        packet::PACKET_DATA_SIZE,
         signature::{Keypair, Signer},
         transaction::{Transaction, TransactionError},
     },
+    spl_associated_token_account::{
+        get_associated_token_address, get_associated_token_address_with_program_id,
+    },
+    spl_token_2022::extension::{
+        transfer_fee::{TransferFee, TransferFeeAmount, TransferFeeConfig},
+        transfer_hook::{TransferHook, TransferHookAccount},
+        BaseState, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+    },
+    std::{convert::TryInto, str::FromStr},
 };
 
 pub fn program_test() -> ProgramTest {
-    ProgramTest::new(
+    let mut program_test = ProgramTest::new(
         "blt_teleport",
         blt_teleport::id(),
         processor!(blt_teleport::processor::Processor::process_instruction),
+    );
+    program_test.add_program(
+        "multisig",
+        multisig_program_id(),
+        processor!(process_mock_multisig),
+    );
+    program_test
+}
+
+fn multisig_program_id() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::MULTISIG_PROGRAM_KEY).unwrap()
+}
+
+// Stand-in for the multisig wallet program. It decodes the invoke framing
+// (3, program idx, u16 account count, (account idx, flags)..., data) and
+// signs for the wallet's mint authority, derived from the wallet key.
+fn process_mock_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    assert_eq!(input[0], 3);
+    let program_info = &accounts[input[1] as usize];
+    let total = u16::from_le_bytes([input[2], input[3]]) as usize;
+
+    let mut metas = Vec::with_capacity(total);
+    let mut infos = Vec::with_capacity(total + 1);
+    for pair in input[4..4 + total * 2].chunks_exact(2) {
+        let info = &accounts[pair[0] as usize];
+        metas.push(AccountMeta {
+            pubkey: *info.key,
+            is_signer: pair[1] & 2 != 0,
+            is_writable: pair[1] & 1 != 0,
+        });
+        infos.push(info.clone());
+    }
+    infos.push(program_info.clone());
+
+    let wallet_key = accounts[0].key;
+    let (_, bump) = Pubkey::find_program_address(&[wallet_key.as_ref()], program_id);
+    invoke_signed(
+        &Instruction {
+            program_id: *program_info.key,
+            accounts: metas,
+            data: input[4 + total * 2..].to_vec(),
+        },
+        &infos,
+        &[&[wallet_key.as_ref(), &[bump]]],
     )
 }
 
+fn receiver_program_id() -> Pubkey {
+    Pubkey::new_from_array([9; 32])
+}
+
+// Stand-in for a receiver program, it checks the caller signature and keeps
+// the message in its third account.
+fn process_mock_receiver(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    let caller = blt_teleport::state::get_caller_address(&blt_teleport::id());
+    assert_eq!(accounts[0].key, &caller);
+    assert!(accounts[0].is_signer);
+    accounts[2].data.borrow_mut()[..input.len()].copy_from_slice(input);
+    Ok(())
+}
+
 pub fn get_owner() -> Keypair {
     Keypair::from_bytes(&[
         216, 77, 18, 252, 166, 244, 245, 106, 13, 111, 107, 26, 108, 43, 230, 245, 113, 207, 205,
@@ -86,6 +181,7 @@ async fn create_admin(
     banks_client: &mut BanksClient,
     payer: &Keypair,
     recent_blockhash: &Hash,
+    config: &Pubkey,
     auth: &Pubkey,
     allowance: u64,
 ) -> Pubkey {
@@ -106,6 +202,7 @@ async fn create_admin(
         blt_teleport::instruction::init_admin(
             &blt_teleport::id(),
             &owner_account.pubkey(),
+            config,
             &admin_account.pubkey(),
             auth,
             allowance,
@@ -121,6 +218,42 @@ async fn create_admin(
     admin_account.pubkey()
 }
 
+// Config with a single admin, returns (config, admin).
+async fn create_config_with_admin(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    auth: &Pubkey,
+    allowance: u64,
+) -> (Pubkey, Pubkey) {
+    let config_pubkey = create_config(banks_client, payer, recent_blockhash, &[]).await;
+    let admin_pubkey = create_admin(
+        banks_client,
+        payer,
+        recent_blockhash,
+        &config_pubkey,
+        auth,
+        allowance,
+    )
+    .await;
+
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::add_admin(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &config_pubkey,
+            &admin_pubkey,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer, &owner], *recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    (config_pubkey, admin_pubkey)
+}
+
 async fn get_config(
     banks_client: &mut BanksClient,
     config_pubkey: &Pubkey,
@@ -241,10 +374,12 @@ async fn test_init_admin() {
 
     let auth = Pubkey::new_unique();
     let allowance = 1_000_000_000;
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
     let admin_pubkey = create_admin(
         &mut banks_client,
         &payer,
         &recent_blockhash,
+        &config_pubkey,
         &auth,
         allowance,
     )
@@ -255,6 +390,9 @@ async fn test_init_admin() {
     assert_eq!(admin.is_init, true);
     assert_eq!(admin.auth, auth);
     assert_eq!(admin.allowance, allowance);
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_allowance_granted, allowance);
 }
 
 #[tokio::test]
@@ -277,10 +415,12 @@ async fn test_deposit_allowance() {
 
     let auth = Pubkey::new_unique();
     let allowance = 1_000_000_000;
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
     let admin_pubkey = create_admin(
         &mut banks_client,
         &payer,
         &recent_blockhash,
+        &config_pubkey,
         &auth,
         allowance,
     )
@@ -292,6 +432,7 @@ async fn test_deposit_allowance() {
         &[blt_teleport::instruction::deposit_allowance(
             &blt_teleport::id(),
             &owner.pubkey(),
+            &config_pubkey,
             &admin_pubkey,
             deposit_num,
         )
@@ -305,6 +446,9 @@ async fn test_deposit_allowance() {
     assert_eq!(admin.is_init, true);
     assert_eq!(admin.auth, auth);
     assert_eq!(admin.allowance, allowance + deposit_num);
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_allowance_granted, allowance + deposit_num);
 }
 
 #[tokio::test]
@@ -463,6 +607,105 @@ async fn test_freeze_and_unfreeze() {
     assert_eq!(config.is_frozen, false);
 }
 
+// Wallet of the mock multisig program whose PDA is the owner wallet.
+fn owner_wallet() -> Pubkey {
+    Pubkey::new_from_array([8; 32])
+}
+
+#[tokio::test]
+async fn test_owner_wallet() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+    let wallet_signer = Keypair::new();
+    let admin = Pubkey::new_unique();
+
+    // a wallet that isn't the owner's
+    let other_wallet = Pubkey::new_unique();
+    let (other_pda, _) =
+        Pubkey::find_program_address(&[other_wallet.as_ref()], &multisig_program_id());
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::wallet_invoke(
+            &other_wallet,
+            &other_pda,
+            &[wallet_signer.pubkey()],
+            &blt_teleport::instruction::add_admin(
+                &blt_teleport::id(),
+                &other_pda,
+                &config_pubkey,
+                &admin,
+            )
+            .unwrap(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet_signer], recent_blockhash);
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TeleportError::AuthFailed as u32)
+        )
+    );
+
+    let (wallet_pda, _) =
+        Pubkey::find_program_address(&[owner_wallet().as_ref()], &multisig_program_id());
+    assert_eq!(
+        wallet_pda,
+        Pubkey::from_str(blt_teleport::state::OWNER_WALLET_KEY).unwrap()
+    );
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::wallet_invoke(
+            &owner_wallet(),
+            &wallet_pda,
+            &[wallet_signer.pubkey()],
+            &blt_teleport::instruction::add_admin(
+                &blt_teleport::id(),
+                &wallet_pda,
+                &config_pubkey,
+                &admin,
+            )
+            .unwrap(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &wallet_signer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.admins, expected_admins(&[admin])[..]);
+}
+
+#[tokio::test]
+async fn test_unset_owner_wallet() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+
+    // the release value is the system program, nobody signs for it, so an
+    // owner instruction naming it comes unsigned and fails the owner check
+    let unset_wallet = Pubkey::from_str(blt_teleport::state::UNSET_OWNER_WALLET_KEY).unwrap();
+    assert_eq!(unset_wallet, solana_program::system_program::id());
+    let mut instruction = blt_teleport::instruction::add_admin(
+        &blt_teleport::id(),
+        &unset_wallet,
+        &config_pubkey,
+        &Pubkey::new_unique(),
+    )
+    .unwrap();
+    instruction.accounts[0].is_signer = false;
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    simulate_denied_transaction(&mut banks_client, transaction, TeleportError::AuthFailed).await;
+}
+
 #[tokio::test]
 async fn test_inin_teleport_out_record() {
     let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
@@ -536,7 +779,7 @@ async fn test_close_teleport_out_record() {
     let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
 
     let auth = Keypair::new();
-    let admin_pubkey = create_admin(
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
         &mut banks_client,
         &payer,
         &recent_blockhash,
@@ -545,14 +788,6 @@ async fn test_close_teleport_out_record() {
     )
     .await;
 
-    let config_pubkey = create_config(
-        &mut banks_client,
-        &payer,
-        &recent_blockhash,
-        &[admin_pubkey],
-    )
-    .await;
-
     let record_pubkey =
         create_teleport_out_record(&mut banks_client, &payer, &recent_blockhash).await;
 
@@ -576,5 +811,3603 @@ async fn test_close_teleport_out_record() {
     banks_client.process_transaction(transaction).await.unwrap();
 
     assert_eq!(banks_client.get_balance(record_pubkey).await.unwrap(), 0);
-    assert_eq!(banks_client.get_balance(auth.pubkey()).await.unwrap(), account_rent);
+    assert_eq!(
+        banks_client.get_balance(auth.pubkey()).await.unwrap(),
+        account_rent
+    );
+}
+
+struct TeleportOutAccounts {
+    wallet: Pubkey,
+    mint_auth: Pubkey,
+    recipients: Vec<Pubkey>,
+    recipient_owners: Vec<Pubkey>,
+}
+
+const BLT_DECIMALS: u8 = 8;
+
+// compute budget of an instruction that doesn't request more units
+const DEFAULT_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+
+fn blt_mint() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::BLT_MINT_KEY).unwrap()
+}
+
+fn add_packable_account<T: Pack>(program_test: &mut ProgramTest, address: Pubkey, state: &T) {
+    let mut data = vec![0; T::get_packed_len()];
+    T::pack_into_slice(state, &mut data);
+    program_test.add_account(
+        address,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+}
+
+// BLT mint owned by the mock wallet and empty token accounts to mint into.
+fn add_teleport_out_accounts(
+    program_test: &mut ProgramTest,
+    recipient_count: usize,
+) -> TeleportOutAccounts {
+    add_teleport_out_accounts_with_decimals(program_test, recipient_count, BLT_DECIMALS)
+}
+
+fn add_teleport_out_accounts_with_decimals(
+    program_test: &mut ProgramTest,
+    recipient_count: usize,
+    decimals: u8,
+) -> TeleportOutAccounts {
+    let wallet = Pubkey::new_unique();
+    let (mint_auth, _) = Pubkey::find_program_address(&[wallet.as_ref()], &multisig_program_id());
+
+    add_packable_account(
+        program_test,
+        blt_mint(),
+        &spl_token::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+
+    let mut recipients = Vec::with_capacity(recipient_count);
+    let mut recipient_owners = Vec::with_capacity(recipient_count);
+    for _ in 0..recipient_count {
+        let recipient = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        add_token_account(program_test, recipient, &owner);
+        recipients.push(recipient);
+        recipient_owners.push(owner);
+    }
+
+    TeleportOutAccounts {
+        wallet,
+        mint_auth,
+        recipients,
+        recipient_owners,
+    }
+}
+
+fn add_token_account(program_test: &mut ProgramTest, address: Pubkey, owner: &Pubkey) {
+    add_packable_account(
+        program_test,
+        address,
+        &spl_token::state::Account {
+            mint: blt_mint(),
+            owner: *owner,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+    );
+}
+
+async fn get_token_balance(banks_client: &mut BanksClient, token_account: &Pubkey) -> u64 {
+    let account: Account = banks_client
+        .get_account(*token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    // SPL Token or Token-2022 with extensions
+    StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+        .unwrap()
+        .base
+        .amount
+}
+
+fn tx_hash(seed: u8) -> [u8; 32] {
+    [seed; 32]
+}
+
+fn teleport_out_entries(recipients: &[Pubkey]) -> Vec<TeleportOutEntry> {
+    recipients
+        .iter()
+        .enumerate()
+        .map(|(i, recipient)| TeleportOutEntry {
+            tx_hash: tx_hash(i as u8 + 1),
+            amount: (i as u64 + 1) * 100,
+            recipient: *recipient,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_teleport_out() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.recipients[0],
+            AddressType::Spl,
+            &accounts.recipient_owners[0],
+            &accounts.mint_auth,
+            &tx_hash(1),
+            400,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.recipients[0]).await,
+        400
+    );
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.allowance, 600);
+    let record_pubkey =
+        blt_teleport::state::get_teleport_out_record_address(&blt_teleport::id(), &tx_hash(1));
+    let record = get_teleport_out_record(&mut banks_client, &record_pubkey).await;
+    assert!(record.is_init);
+}
+
+#[tokio::test]
+async fn test_teleport_out_batch() {
+    let limit = blt_teleport::state::MAX_TELEPORT_OUT_BATCH;
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, limit);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let allowance = 1_000_000;
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        allowance,
+    )
+    .await;
+
+    let entries = teleport_out_entries(&accounts.recipients);
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &entries,
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let mut total = 0;
+    for entry in &entries {
+        assert_eq!(
+            get_token_balance(&mut banks_client, &entry.recipient).await,
+            entry.amount
+        );
+        let record_pubkey = blt_teleport::state::get_teleport_out_record_address(
+            &blt_teleport::id(),
+            &entry.tx_hash,
+        );
+        let record = get_teleport_out_record(&mut banks_client, &record_pubkey).await;
+        assert!(record.is_init);
+        total += entry.amount;
+    }
+
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.allowance, allowance - total);
+}
+
+#[tokio::test]
+async fn test_teleport_out_batch_over_limit() {
+    let limit = blt_teleport::state::MAX_TELEPORT_OUT_BATCH;
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, limit + 1);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1_000_000,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &teleport_out_entries(&accounts.recipients),
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = TeleportError::InvalidBatchSize as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while teleporting out an oversized batch"),
+    }
+}
+
+#[tokio::test]
+async fn test_teleport_out_batch_compute_limit() {
+    let limit = blt_teleport::state::MAX_TELEPORT_OUT_BATCH;
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, limit);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1_000_000,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &teleport_out_entries(&accounts.recipients),
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    let result = banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert_eq!(result.result, Ok(()));
+
+    // only the system and token programs are metered here, the teleport and
+    // multisig programs run natively, so a full batch has to leave most of
+    // the default budget to them
+    let consumed = result.metadata.unwrap().compute_units_consumed;
+    assert!(consumed > 0);
+    assert!(consumed * 4 < DEFAULT_COMPUTE_UNIT_LIMIT);
+}
+
+// Wire size of a signed legacy transaction.
+fn transaction_size(transaction: &Transaction) -> usize {
+    1 + transaction.signatures.len() * 64 + transaction.message.serialize().len()
+}
+
+#[test]
+fn test_teleport_out_batch_transaction_size() {
+    let payer = Keypair::new();
+    let auth = Keypair::new();
+    let batch_transaction = |len: usize| {
+        let recipients: Vec<Pubkey> = (0..len).map(|_| Pubkey::new_unique()).collect();
+        let recipient_owners: Vec<Pubkey> = (0..len).map(|_| Pubkey::new_unique()).collect();
+        Transaction::new_signed_with_payer(
+            &[blt_teleport::instruction::teleport_out_batch(
+                &blt_teleport::id(),
+                &Pubkey::new_unique(),
+                &Pubkey::new_unique(),
+                &auth.pubkey(),
+                &Pubkey::new_unique(),
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &Pubkey::new_unique(),
+                &teleport_out_entries(&recipients),
+                &recipient_owners,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+            &[&payer, &auth],
+            Hash::default(),
+        )
+    };
+
+    // a full batch fits a packet, one more entry doesn't
+    let limit = blt_teleport::state::MAX_TELEPORT_OUT_BATCH;
+    assert!(transaction_size(&batch_transaction(limit)) <= PACKET_DATA_SIZE);
+    assert!(transaction_size(&batch_transaction(limit + 1)) > PACKET_DATA_SIZE);
+}
+
+#[tokio::test]
+async fn test_teleport_out_batch_replay() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 3);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let allowance = 1_000_000;
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        allowance,
+    )
+    .await;
+
+    let entries = teleport_out_entries(&accounts.recipients);
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &entries[1..2],
+            &accounts.recipient_owners[1..2],
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // the second entry is already processed, so the whole batch is rejected
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &entries,
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = TeleportError::UnexpectedError as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while replaying a teleport out"),
+    }
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.recipients[0]).await,
+        0
+    );
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.recipients[1]).await,
+        entries[1].amount
+    );
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.allowance, allowance - entries[1].amount);
+}
+
+async fn teleport_out_to_wallet(mut program_test: ProgramTest, wallet_owner: &Pubkey) {
+    let accounts = add_teleport_out_accounts(&mut program_test, 0);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            wallet_owner,
+            AddressType::Sol,
+            wallet_owner,
+            &accounts.mint_auth,
+            &tx_hash(1),
+            400,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let associated_token_account = get_associated_token_address(wallet_owner, &blt_mint());
+    let account = banks_client
+        .get_account(associated_token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let token_account = spl_token::state::Account::unpack(&account.data).unwrap();
+    assert_eq!(token_account.owner, *wallet_owner);
+    assert_eq!(token_account.amount, 400);
+}
+
+#[tokio::test]
+async fn test_teleport_out_to_wallet() {
+    let wallet_owner = Pubkey::new_unique();
+    teleport_out_to_wallet(program_test(), &wallet_owner).await;
+}
+
+#[tokio::test]
+async fn test_teleport_out_to_wallet_with_token_account() {
+    let wallet_owner = Pubkey::new_unique();
+    let mut program_test = program_test();
+    add_token_account(
+        &mut program_test,
+        get_associated_token_address(&wallet_owner, &blt_mint()),
+        &wallet_owner,
+    );
+    teleport_out_to_wallet(program_test, &wallet_owner).await;
+}
+
+const GAS_DROP: u64 = 5_000_000;
+
+async fn set_gas_drop(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    config_pubkey: &Pubkey,
+    per_transfer: u64,
+    daily_cap: u64,
+) {
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::set_gas_drop(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            config_pubkey,
+            per_transfer,
+            daily_cap,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer, &owner], *recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+fn add_owner_lamports(program_test: &mut ProgramTest, lamports: u64) {
+    program_test.add_account(
+        get_owner().pubkey(),
+        Account {
+            lamports,
+            ..Account::default()
+        },
+    );
+}
+
+async fn get_lamports(banks_client: &mut BanksClient, address: &Pubkey) -> u64 {
+    banks_client
+        .get_account(*address)
+        .await
+        .unwrap()
+        .map_or(0, |account| account.lamports)
+}
+
+#[tokio::test]
+async fn test_refill_and_withdraw_gas_tank() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let owner = get_owner();
+    let gas_tank = blt_teleport::state::get_gas_tank_address(&blt_teleport::id());
+    let target = Pubkey::new_unique();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            blt_teleport::instruction::refill_gas_tank(
+                &blt_teleport::id(),
+                &owner.pubkey(),
+                100_000_000,
+            )
+            .unwrap(),
+            blt_teleport::instruction::withdraw_gas_tank(
+                &blt_teleport::id(),
+                &owner.pubkey(),
+                &target,
+                40_000_000,
+            )
+            .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(get_lamports(&mut banks_client, &gas_tank).await, 60_000_000);
+    assert_eq!(get_lamports(&mut banks_client, &target).await, 40_000_000);
+
+    // a withdrawal over the balance leaves the rent exempt reserve
+    let reserve = banks_client.get_rent().await.unwrap().minimum_balance(0);
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::withdraw_gas_tank(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &target,
+            u64::MAX,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    assert_eq!(get_lamports(&mut banks_client, &gas_tank).await, reserve);
+    assert_eq!(
+        get_lamports(&mut banks_client, &target).await,
+        100_000_000 - reserve
+    );
+
+    // only the owner can withdraw
+    let fake_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::withdraw_gas_tank(
+            &blt_teleport::id(),
+            &fake_owner.pubkey(),
+            &target,
+            40_000_000,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &fake_owner], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+}
+
+#[tokio::test]
+async fn test_set_gas_drop_below_rent() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+    let minimum = banks_client.get_rent().await.unwrap().minimum_balance(0);
+
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::set_gas_drop(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &config_pubkey,
+            minimum - 1,
+            GAS_DROP,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TeleportError::UnexpectedError as u32)
+        )
+    );
+
+    // the minimum and zero, which turns the drop-off off, are accepted
+    for per_transfer in [minimum, 0] {
+        set_gas_drop(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &config_pubkey,
+            per_transfer,
+            GAS_DROP,
+        )
+        .await;
+        let config = get_config(&mut banks_client, &config_pubkey).await;
+        assert_eq!(config.gas_drop_per_transfer, per_transfer);
+    }
+}
+
+#[tokio::test]
+async fn test_teleport_out_gas_drop() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let funded_owner = Pubkey::new_unique();
+    program_test.add_account(
+        funded_owner,
+        Account {
+            lamports: 1_000_000,
+            ..Account::default()
+        },
+    );
+    let accounts = add_teleport_out_accounts(&mut program_test, 0);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+    set_gas_drop(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &config_pubkey,
+        GAS_DROP,
+        GAS_DROP,
+    )
+    .await;
+
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::refill_gas_tank(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            100_000_000,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // a fresh wallet gets a drop-off, a funded one and one past the daily cap don't
+    let fresh_owner = Pubkey::new_unique();
+    let capped_owner = Pubkey::new_unique();
+    for (seed, wallet_owner) in [fresh_owner, funded_owner, capped_owner].iter().enumerate() {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                wallet_owner,
+                AddressType::Sol,
+                wallet_owner,
+                &accounts.mint_auth,
+                &tx_hash(seed as u8),
+                100,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        banks_client.process_transaction(transaction).await.unwrap();
+    }
+
+    assert_eq!(
+        get_lamports(&mut banks_client, &fresh_owner).await,
+        GAS_DROP
+    );
+    assert_eq!(
+        get_lamports(&mut banks_client, &funded_owner).await,
+        1_000_000
+    );
+    assert_eq!(get_lamports(&mut banks_client, &capped_owner).await, 0);
+
+    let gas_tank = blt_teleport::state::get_gas_tank_address(&blt_teleport::id());
+    assert_eq!(
+        get_lamports(&mut banks_client, &gas_tank).await,
+        100_000_000 - GAS_DROP
+    );
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.gas_dropped_today, GAS_DROP);
+}
+
+const DELAY_THRESHOLD: u64 = 500;
+const CHALLENGE_DELAY: i64 = 3600;
+
+struct DelayedSettlement {
+    context: ProgramTestContext,
+    accounts: TeleportOutAccounts,
+    auth: Keypair,
+    admin_pubkey: Pubkey,
+    config_pubkey: Pubkey,
+}
+
+async fn setup_delayed_settlement() -> DelayedSettlement {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::set_delayed_settlement(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &config_pubkey,
+            DELAY_THRESHOLD,
+            CHALLENGE_DELAY,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    DelayedSettlement {
+        context,
+        accounts,
+        auth,
+        admin_pubkey,
+        config_pubkey,
+    }
+}
+
+impl DelayedSettlement {
+    async fn teleport_out(&mut self, seed: u8, amount: u64) {
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &self.config_pubkey,
+                &self.admin_pubkey,
+                &self.auth.pubkey(),
+                &self.accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &self.accounts.recipients[0],
+                AddressType::Spl,
+                &self.accounts.recipient_owners[0],
+                &self.accounts.mint_auth,
+                &tx_hash(seed),
+                amount,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &self.auth], self.context.last_blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    async fn finalize_teleport_out(&mut self, seed: u8) -> Result<(), BanksClientError> {
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::finalize_teleport_out(
+                &blt_teleport::id(),
+                &self.config_pubkey,
+                &payer.pubkey(),
+                &self.accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &self.accounts.recipients[0],
+                AddressType::Spl,
+                &self.accounts.recipient_owners[0],
+                &self.accounts.mint_auth,
+                &tx_hash(seed),
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        let recent_blockhash = self
+            .context
+            .banks_client
+            .get_new_latest_blockhash(&self.context.last_blockhash)
+            .await
+            .unwrap();
+        self.context.last_blockhash = recent_blockhash;
+        transaction.sign(&[payer], recent_blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn cancel_teleport_out(
+        &mut self,
+        authority: &Keypair,
+        seed: u8,
+    ) -> Result<(), BanksClientError> {
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::cancel_teleport_out(
+                &blt_teleport::id(),
+                &authority.pubkey(),
+                &self.config_pubkey,
+                &self.admin_pubkey,
+                &payer.pubkey(),
+                &tx_hash(seed),
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, authority], self.context.last_blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn pending_exists(&mut self, seed: u8) -> bool {
+        let pending = blt_teleport::state::get_pending_teleport_out_address(
+            &blt_teleport::id(),
+            &tx_hash(seed),
+        );
+        self.context
+            .banks_client
+            .get_account(pending)
+            .await
+            .unwrap()
+            .is_some()
+    }
+}
+
+#[tokio::test]
+async fn test_teleport_out_delayed() {
+    let mut test = setup_delayed_settlement().await;
+    let recipient = test.accounts.recipients[0];
+
+    // below the threshold mints right away
+    test.teleport_out(1, 400).await;
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, &recipient).await,
+        400
+    );
+    assert!(!test.pending_exists(1).await);
+
+    // a payload is handed over right away, it can't wait
+    let payer = test.context.payer.insecure_clone();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_with_payload(
+            &blt_teleport::id(),
+            &test.config_pubkey,
+            &test.admin_pubkey,
+            &test.auth.pubkey(),
+            &test.accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &recipient,
+            AddressType::Spl,
+            &test.accounts.recipient_owners[0],
+            &test.accounts.mint_auth,
+            &tx_hash(3),
+            DELAY_THRESHOLD,
+            vec![1],
+            &Pubkey::new_unique(),
+            vec![],
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &test.auth], test.context.last_blockhash);
+    simulate_denied_transaction(
+        &mut test.context.banks_client,
+        transaction,
+        TeleportError::DelayedPayload,
+    )
+    .await;
+
+    // above the threshold waits out the challenge delay
+    test.teleport_out(2, 600).await;
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, &recipient).await,
+        400
+    );
+    let admin = get_admin(&mut test.context.banks_client, &test.admin_pubkey).await;
+    assert_eq!(admin.allowance, 0);
+    assert!(test.pending_exists(2).await);
+    assert_eq!(
+        test.finalize_teleport_out(2).await.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TeleportError::ChallengeDelayNotOver as u32)
+        )
+    );
+
+    let mut clock: Clock = test.context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += CHALLENGE_DELAY;
+    test.context.set_sysvar(&clock);
+
+    test.finalize_teleport_out(2).await.unwrap();
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, &recipient).await,
+        1000
+    );
+    assert!(!test.pending_exists(2).await);
+    assert!(test.finalize_teleport_out(2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_cancel_teleport_out() {
+    let mut test = setup_delayed_settlement().await;
+    let recipient = test.accounts.recipients[0];
+
+    let pauser = Keypair::new();
+    let owner = get_owner();
+    let payer = test.context.payer.insecure_clone();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::set_pauser(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &test.config_pubkey,
+            &pauser.pubkey(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], test.context.last_blockhash);
+    test.context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    test.teleport_out(1, 600).await;
+    test.teleport_out(2, 400).await;
+    let admin = get_admin(&mut test.context.banks_client, &test.admin_pubkey).await;
+    assert_eq!(admin.allowance, 0);
+
+    // neither an admin nor a random key can cancel
+    let auth = test.auth.insecure_clone();
+    assert!(test.cancel_teleport_out(&auth, 1).await.is_err());
+    assert!(test.cancel_teleport_out(&Keypair::new(), 1).await.is_err());
+
+    test.cancel_teleport_out(&pauser, 1).await.unwrap();
+    let admin = get_admin(&mut test.context.banks_client, &test.admin_pubkey).await;
+    assert_eq!(admin.allowance, 600);
+    assert!(!test.pending_exists(1).await);
+
+    let mut clock: Clock = test.context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += CHALLENGE_DELAY;
+    test.context.set_sysvar(&clock);
+
+    assert!(test.finalize_teleport_out(1).await.is_err());
+    assert_eq!(
+        get_token_balance(&mut test.context.banks_client, &recipient).await,
+        400
+    );
+}
+
+#[tokio::test]
+async fn test_cancel_teleport_out_of_closed_admin() {
+    let mut test = setup_delayed_settlement().await;
+    test.teleport_out(1, 600).await;
+
+    let owner = get_owner();
+    let payer = test.context.payer.insecure_clone();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::close_admin(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &test.config_pubkey,
+            &test.admin_pubkey,
+            &Pubkey::new_unique(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], test.context.last_blockhash);
+    test.context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    // the transfer can still be cancelled, with nothing to refund
+    test.cancel_teleport_out(&owner, 1).await.unwrap();
+    assert!(!test.pending_exists(1).await);
+    assert!(test
+        .context
+        .banks_client
+        .get_account(test.admin_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+const RELAYER_LAMPORTS: u64 = 1_000_000_000;
+
+struct RecordRetention {
+    context: ProgramTestContext,
+    accounts: TeleportOutAccounts,
+    auth: Keypair,
+    relayer: Keypair,
+    admin_pubkey: Pubkey,
+    config_pubkey: Pubkey,
+}
+
+async fn setup_record_retention(retention: i64) -> RecordRetention {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let relayer = Keypair::new();
+    program_test.add_account(
+        relayer.pubkey(),
+        Account {
+            lamports: RELAYER_LAMPORTS,
+            ..Account::default()
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::set_record_retention(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &config_pubkey,
+            retention,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &owner], recent_blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    RecordRetention {
+        context,
+        accounts,
+        auth,
+        relayer,
+        admin_pubkey,
+        config_pubkey,
+    }
+}
+
+impl RecordRetention {
+    // the relayer funds the record, the context payer pays the tx fee
+    async fn teleport_out(&mut self, seed: u8) {
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &self.config_pubkey,
+                &self.admin_pubkey,
+                &self.auth.pubkey(),
+                &self.accounts.wallet,
+                &self.relayer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &self.accounts.recipients[0],
+                AddressType::Spl,
+                &self.accounts.recipient_owners[0],
+                &self.accounts.mint_auth,
+                &tx_hash(seed),
+                100,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[payer, &self.auth, &self.relayer],
+            self.context.last_blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap();
+    }
+
+    async fn close_record(&mut self, seed: u8, target: &Pubkey) -> Result<(), BanksClientError> {
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::close_teleport_out_record(
+                &blt_teleport::id(),
+                &self.config_pubkey,
+                &self.admin_pubkey,
+                &self.auth.pubkey(),
+                &blt_teleport::state::get_teleport_out_record_address(
+                    &blt_teleport::id(),
+                    &tx_hash(seed),
+                ),
+                target,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &self.auth], self.context.last_blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    async fn close_expired_records(&mut self, seeds: &[u8]) -> Result<(), BanksClientError> {
+        let records: Vec<(Pubkey, Pubkey)> = seeds
+            .iter()
+            .map(|seed| {
+                (
+                    blt_teleport::state::get_teleport_out_record_address(
+                        &blt_teleport::id(),
+                        &tx_hash(*seed),
+                    ),
+                    self.relayer.pubkey(),
+                )
+            })
+            .collect();
+
+        // no admin signs, anyone can close expired records
+        let payer = &self.context.payer;
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                blt_teleport::instruction::close_expired_teleport_out_records(
+                    &blt_teleport::id(),
+                    &self.config_pubkey,
+                    &records,
+                )
+                .unwrap(),
+            ],
+            Some(&payer.pubkey()),
+        );
+        let recent_blockhash = self
+            .context
+            .banks_client
+            .get_new_latest_blockhash(&self.context.last_blockhash)
+            .await
+            .unwrap();
+        self.context.last_blockhash = recent_blockhash;
+        transaction.sign(&[payer], recent_blockhash);
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+}
+
+#[tokio::test]
+async fn test_close_teleport_out_record_refunds_payer() {
+    let mut test = setup_record_retention(0).await;
+    test.teleport_out(1).await;
+
+    let record_pubkey =
+        blt_teleport::state::get_teleport_out_record_address(&blt_teleport::id(), &tx_hash(1));
+    let record = get_teleport_out_record(&mut test.context.banks_client, &record_pubkey).await;
+    assert_eq!(record.payer, test.relayer.pubkey());
+
+    let auth_pubkey = test.auth.pubkey();
+    assert!(test.close_record(1, &auth_pubkey).await.is_err());
+
+    let relayer_pubkey = test.relayer.pubkey();
+    test.close_record(1, &relayer_pubkey).await.unwrap();
+    assert_eq!(
+        get_lamports(&mut test.context.banks_client, &record_pubkey).await,
+        0
+    );
+    assert_eq!(
+        get_lamports(&mut test.context.banks_client, &relayer_pubkey).await,
+        RELAYER_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn test_close_expired_teleport_out_records() {
+    let mut test = setup_record_retention(3600).await;
+    test.teleport_out(1).await;
+    test.teleport_out(2).await;
+
+    assert!(test.close_expired_records(&[1, 2]).await.is_err());
+
+    let mut clock: Clock = test.context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += 3600;
+    test.context.set_sysvar(&clock);
+
+    test.close_expired_records(&[1, 2]).await.unwrap();
+    for seed in [1, 2] {
+        let record_pubkey = blt_teleport::state::get_teleport_out_record_address(
+            &blt_teleport::id(),
+            &tx_hash(seed),
+        );
+        assert_eq!(
+            get_lamports(&mut test.context.banks_client, &record_pubkey).await,
+            0
+        );
+    }
+    let relayer_pubkey = test.relayer.pubkey();
+    assert_eq!(
+        get_lamports(&mut test.context.banks_client, &relayer_pubkey).await,
+        RELAYER_LAMPORTS
+    );
+}
+
+#[tokio::test]
+async fn test_close_expired_teleport_out_records_disabled() {
+    let mut test = setup_record_retention(0).await;
+    test.teleport_out(1).await;
+
+    let mut clock: Clock = test.context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += 3600;
+    test.context.set_sysvar(&clock);
+
+    assert!(test.close_expired_records(&[1]).await.is_err());
+}
+
+async fn teleport_out_with_decimals(decimals: u8, amount: u64) -> Result<u64, TransactionError> {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts_with_decimals(&mut program_test, 1, decimals);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1_000_000_000,
+    )
+    .await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.recipients[0],
+            AddressType::Spl,
+            &accounts.recipient_owners[0],
+            &accounts.mint_auth,
+            &tx_hash(1),
+            amount,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())?;
+
+    Ok(get_token_balance(&mut banks_client, &accounts.recipients[0]).await)
+}
+
+#[tokio::test]
+async fn test_teleport_out_mint_precision() {
+    // UFix64 amounts have 8 decimals
+    assert_eq!(teleport_out_with_decimals(8, 300).await, Ok(300));
+    assert_eq!(teleport_out_with_decimals(9, 300).await, Ok(3000));
+    assert_eq!(teleport_out_with_decimals(6, 300).await, Ok(3));
+    assert_eq!(teleport_out_with_decimals(0, 300_000_000).await, Ok(3));
+}
+
+#[tokio::test]
+async fn test_teleport_out_rejects_dust() {
+    let not_representable = Err(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(TeleportError::AmountNotRepresentable as u32),
+    ));
+    assert_eq!(teleport_out_with_decimals(6, 350).await, not_representable);
+    assert_eq!(
+        teleport_out_with_decimals(0, 150_000_000).await,
+        not_representable
+    );
+}
+
+async fn check_invariants(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    config_pubkey: &Pubkey,
+) {
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::check_invariants(
+            &blt_teleport::id(),
+            config_pubkey,
+            &blt_mint(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer], *recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+// Mints `amount` base units to the first recipient through the mock wallet,
+// without the teleport program.
+async fn mint_around_bridge(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    accounts: &TeleportOutAccounts,
+    amount: u64,
+) {
+    let mut transaction = Transaction::new_with_payer(
+        &[Instruction {
+            program_id: multisig_program_id(),
+            accounts: vec![
+                AccountMeta::new(accounts.wallet, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new(blt_mint(), false),
+                AccountMeta::new(accounts.recipients[0], false),
+                AccountMeta::new_readonly(accounts.mint_auth, false),
+            ],
+            data: [
+                &[3, 1, 3, 0, 2, 1, 3, 1, 4, 2, 7][..],
+                &amount.to_le_bytes()[..],
+            ]
+            .concat(),
+        }],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[payer], *recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_check_invariants() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 2);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let entries = teleport_out_entries(&accounts.recipients);
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &entries,
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    check_invariants(&mut banks_client, &payer, &recent_blockhash, &config_pubkey).await;
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_minted, 300);
+    assert_eq!(config.total_burned, 0);
+    assert_eq!(config.total_allowance_granted, 1000);
+    assert!(!config.is_frozen);
+}
+
+#[tokio::test]
+async fn test_check_invariants_ignores_outside_burn() {
+    let mut program_test = program_test();
+    let mut accounts = add_teleport_out_accounts(&mut program_test, 0);
+    let holder = Keypair::new();
+    let holder_token = Pubkey::new_unique();
+    add_token_account(&mut program_test, holder_token, &holder.pubkey());
+    accounts.recipients.push(holder_token);
+    accounts.recipient_owners.push(holder.pubkey());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let entries = teleport_out_entries(&accounts.recipients);
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_out_batch(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &admin_pubkey,
+            &auth.pubkey(),
+            &accounts.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &accounts.mint_auth,
+            &entries,
+            &accounts.recipient_owners,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // burn around the bridge
+    let mut transaction = Transaction::new_with_payer(
+        &[spl_token::instruction::burn(
+            &spl_token::id(),
+            &holder_token,
+            &blt_mint(),
+            &holder.pubkey(),
+            &[],
+            40,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &holder], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    check_invariants(&mut banks_client, &payer, &recent_blockhash, &config_pubkey).await;
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_minted, 100);
+    assert!(!config.is_frozen);
+}
+
+#[tokio::test]
+async fn test_check_invariants_freezes_on_mismatch() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, _) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    mint_around_bridge(&mut banks_client, &payer, &recent_blockhash, &accounts, 100).await;
+
+    check_invariants(&mut banks_client, &payer, &recent_blockhash, &config_pubkey).await;
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert!(config.is_frozen);
+}
+
+async fn process_owner_instruction(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    instruction: Instruction,
+) -> Result<(), BanksClientError> {
+    let owner = get_owner();
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[payer, &owner], *recent_blockhash);
+    banks_client.process_transaction(transaction).await
+}
+
+#[tokio::test]
+async fn test_rotate_admin_auth() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let old_auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &old_auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    // only the owner can rotate
+    let new_auth = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::rotate_admin_auth(
+            &blt_teleport::id(),
+            &old_auth.pubkey(),
+            &admin_pubkey,
+            &new_auth.pubkey(),
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &old_auth], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_err());
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::rotate_admin_auth(
+            &blt_teleport::id(),
+            &get_owner().pubkey(),
+            &admin_pubkey,
+            &new_auth.pubkey(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.auth, new_auth.pubkey());
+
+    for (seed, auth) in [(1, &old_auth), (2, &new_auth)] {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.recipients[0],
+                AddressType::Spl,
+                &accounts.recipient_owners[0],
+                &accounts.mint_auth,
+                &tx_hash(seed),
+                100,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, auth], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        assert_eq!(result.is_ok(), seed == 2);
+    }
+
+    // initiated by the admin, only its current auth can sign
+    let owner = get_owner();
+    let next_auth = Keypair::new();
+    for auth in [&old_auth, &new_auth] {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::rotate_admin_auth_by_admin(
+                &blt_teleport::id(),
+                &owner.pubkey(),
+                &admin_pubkey,
+                &auth.pubkey(),
+                &next_auth.pubkey(),
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &owner, auth], recent_blockhash);
+        let result = banks_client.process_transaction(transaction).await;
+        if auth.pubkey() == old_auth.pubkey() {
+            assert_eq!(
+                result.unwrap_err().unwrap(),
+                TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(TeleportError::AuthFailed as u32)
+                )
+            );
+        } else {
+            result.unwrap();
+        }
+    }
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.auth, next_auth.pubkey());
+}
+
+#[tokio::test]
+async fn test_set_and_decrease_allowance() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+    let owner = get_owner().pubkey();
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_allowance(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &admin_pubkey,
+            1500,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_admin(&mut banks_client, &admin_pubkey).await.allowance,
+        1500
+    );
+    assert_eq!(
+        get_config(&mut banks_client, &config_pubkey)
+            .await
+            .total_allowance_granted,
+        1500
+    );
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::decrease_allowance(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &admin_pubkey,
+            700,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_admin(&mut banks_client, &admin_pubkey).await.allowance,
+        800
+    );
+    assert_eq!(
+        get_config(&mut banks_client, &config_pubkey)
+            .await
+            .total_allowance_granted,
+        800
+    );
+
+    // can't claw back more than what's left
+    let result = process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::decrease_allowance(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &admin_pubkey,
+            900,
+        )
+        .unwrap(),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_close_admin() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+    let admin_rent = get_lamports(&mut banks_client, &admin_pubkey).await;
+
+    // the rent can't go back to the admin being closed
+    let result = process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::close_admin(
+            &blt_teleport::id(),
+            &get_owner().pubkey(),
+            &config_pubkey,
+            &admin_pubkey,
+            &admin_pubkey,
+        )
+        .unwrap(),
+    )
+    .await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(TeleportError::UnexpectedError as u32)
+        )
+    );
+    assert_eq!(
+        get_lamports(&mut banks_client, &admin_pubkey).await,
+        admin_rent
+    );
+
+    let target = Pubkey::new_unique();
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::close_admin(
+            &blt_teleport::id(),
+            &get_owner().pubkey(),
+            &config_pubkey,
+            &admin_pubkey,
+            &target,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert!(!config.contain_admin(&admin_pubkey));
+    assert_eq!(config.total_allowance_granted, 0);
+    assert_eq!(get_lamports(&mut banks_client, &admin_pubkey).await, 0);
+    assert_eq!(get_lamports(&mut banks_client, &target).await, admin_rent);
+}
+
+// Config and admin accounts as written before the version byte.
+fn add_v1_accounts(program_test: &mut ProgramTest, admin: &Pubkey, auth: &Pubkey) -> Pubkey {
+    let rent = solana_program::rent::Rent::default();
+
+    let mut admin_data = vec![1];
+    admin_data.extend_from_slice(auth.as_ref());
+    admin_data.extend_from_slice(&1000u64.to_le_bytes());
+    assert_eq!(admin_data.len(), 41);
+    program_test.add_account(
+        *admin,
+        Account {
+            lamports: rent.minimum_balance(admin_data.len()),
+            data: admin_data,
+            owner: blt_teleport::id(),
+            ..Account::default()
+        },
+    );
+
+    let config = Pubkey::new_unique();
+    let mut config_data = vec![1, 0];
+    config_data.extend_from_slice(admin.as_ref());
+    config_data.resize(162, 0);
+    program_test.add_account(
+        config,
+        Account {
+            lamports: rent.minimum_balance(config_data.len()),
+            data: config_data,
+            owner: blt_teleport::id(),
+            ..Account::default()
+        },
+    );
+
+    config
+}
+
+#[tokio::test]
+async fn test_migrate_v1_accounts() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let admin_pubkey = Pubkey::new_unique();
+    let auth = Keypair::new();
+    let config_pubkey = add_v1_accounts(&mut program_test, &admin_pubkey, &auth.pubkey());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let teleport_out = |seed| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.recipients[0],
+                AddressType::Spl,
+                &accounts.recipient_owners[0],
+                &accounts.mint_auth,
+                &tx_hash(seed),
+                100,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    // version 1 accounts are rejected until migrated
+    assert!(banks_client
+        .process_transaction(teleport_out(1))
+        .await
+        .is_err());
+
+    let owner = get_owner().pubkey();
+    for instruction in [
+        blt_teleport::instruction::migrate_config(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+        )
+        .unwrap(),
+        blt_teleport::instruction::migrate_admin(
+            &blt_teleport::id(),
+            &owner,
+            &admin_pubkey,
+            &config_pubkey,
+        )
+        .unwrap(),
+    ] {
+        process_owner_instruction(&mut banks_client, &payer, &recent_blockhash, instruction)
+            .await
+            .unwrap();
+    }
+
+    let config_account = banks_client
+        .get_account(config_pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(config_account.data.len(), blt_teleport::state::Config::LEN);
+    let rent = banks_client.get_rent().await.unwrap();
+    assert!(rent.is_exempt(config_account.lamports, config_account.data.len()));
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.version, blt_teleport::state::ACCOUNT_VERSION);
+    assert!(config.is_init);
+    assert!(!config.is_frozen);
+    assert_eq!(config.admins, expected_admins(&[admin_pubkey])[..]);
+
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.version, blt_teleport::state::ACCOUNT_VERSION);
+    assert_eq!(admin.auth, auth.pubkey());
+    assert_eq!(admin.allowance, 1000);
+    assert_eq!(config.total_minted, 0);
+    assert_eq!(config.total_allowance_granted, 1000);
+
+    banks_client
+        .process_transaction(teleport_out(2))
+        .await
+        .unwrap();
+
+    // migrated accounts can't be migrated again
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    let result = process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::migrate_config(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+        )
+        .unwrap(),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_close_migrated_admin() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let admin_pubkey = Pubkey::new_unique();
+    let auth = Keypair::new();
+    let config_pubkey = add_v1_accounts(&mut program_test, &admin_pubkey, &auth.pubkey());
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // minted by the version 1 program
+    mint_around_bridge(&mut banks_client, &payer, &recent_blockhash, &accounts, 50).await;
+
+    let owner = get_owner().pubkey();
+    for instruction in [
+        blt_teleport::instruction::migrate_config(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+        )
+        .unwrap(),
+        blt_teleport::instruction::migrate_admin(
+            &blt_teleport::id(),
+            &owner,
+            &admin_pubkey,
+            &config_pubkey,
+        )
+        .unwrap(),
+    ] {
+        process_owner_instruction(&mut banks_client, &payer, &recent_blockhash, instruction)
+            .await
+            .unwrap();
+    }
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_minted, 50);
+    assert_eq!(config.total_burned, 0);
+    assert_eq!(config.total_allowance_granted, 1000);
+
+    // the seeded counters don't pin the route
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_token_route(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+            &spl_token::id(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_config(&mut banks_client, &config_pubkey)
+            .await
+            .total_minted,
+        50
+    );
+
+    check_invariants(&mut banks_client, &payer, &recent_blockhash, &config_pubkey).await;
+    assert!(
+        !get_config(&mut banks_client, &config_pubkey)
+            .await
+            .is_frozen
+    );
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::close_admin(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &admin_pubkey,
+            &payer.pubkey(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_allowance_granted, 0);
+    assert_eq!(config.admins, expected_admins(&[])[..]);
+    assert!(banks_client
+        .get_account(admin_pubkey)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+// Simulates a transaction that should fail with `error`, without recording
+// its signature.
+async fn simulate_denied_transaction(
+    banks_client: &mut BanksClient,
+    transaction: Transaction,
+    error: TeleportError,
+) {
+    let simulation = banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        simulation.result.unwrap(),
+        Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(error as u32)
+        ))
+    );
+}
+
+struct TeleportInAccounts {
+    wallet: Pubkey,
+    mint_auth: Pubkey,
+    from: Pubkey,
+    from_auth: Keypair,
+}
+
+// BLT mint owned by the mock wallet and a token account holding `balance` to
+// burn from.
+fn add_teleport_in_accounts(program_test: &mut ProgramTest, balance: u64) -> TeleportInAccounts {
+    let wallet = Pubkey::new_unique();
+    let (mint_auth, _) = Pubkey::find_program_address(&[wallet.as_ref()], &multisig_program_id());
+    add_packable_account(
+        program_test,
+        blt_mint(),
+        &spl_token::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply: balance,
+            decimals: BLT_DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+
+    let from_auth = Keypair::new();
+    let from = Pubkey::new_unique();
+    add_packable_account(
+        program_test,
+        from,
+        &spl_token::state::Account {
+            mint: blt_mint(),
+            owner: from_auth.pubkey(),
+            amount: balance,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+    );
+
+    TeleportInAccounts {
+        wallet,
+        mint_auth,
+        from,
+        from_auth,
+    }
+}
+
+impl TeleportInAccounts {
+    #[allow(clippy::too_many_arguments)]
+    fn teleport_in(
+        &self,
+        payer: &Keypair,
+        config: &Pubkey,
+        nonce: u64,
+        amount: u64,
+        chain: Chain,
+        to: &[u8],
+        recent_blockhash: Hash,
+    ) -> Transaction {
+        self.teleport_in_with_payload(
+            payer,
+            config,
+            nonce,
+            amount,
+            chain,
+            to,
+            None,
+            recent_blockhash,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn teleport_in_with_payload(
+        &self,
+        payer: &Keypair,
+        config: &Pubkey,
+        nonce: u64,
+        amount: u64,
+        chain: Chain,
+        to: &[u8],
+        payload: Option<Vec<u8>>,
+        recent_blockhash: Hash,
+    ) -> Transaction {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_in(
+                &blt_teleport::id(),
+                config,
+                &self.wallet,
+                &self.mint_auth,
+                &self.from,
+                &blt_mint(),
+                &spl_token::id(),
+                &self.from_auth.pubkey(),
+                &payer.pubkey(),
+                nonce,
+                amount,
+                chain,
+                to,
+                payload,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[payer, &self.from_auth], recent_blockhash);
+        transaction
+    }
+}
+
+#[tokio::test]
+async fn test_denylist_teleport_out() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_out_accounts(&mut program_test, 3);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+    let denied = accounts.recipients[0];
+    // a token account of a denylisted wallet
+    let denied_owner = accounts.recipient_owners[2];
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    for key in [denied, denied_owner] {
+        process_owner_instruction(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            blt_teleport::instruction::add_to_denylist(
+                &blt_teleport::id(),
+                &owner,
+                DenylistKey::Solana(key),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let teleport_out = |seed, recipient: usize| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.recipients[recipient],
+                AddressType::Spl,
+                &accounts.recipient_owners[recipient],
+                &accounts.mint_auth,
+                &tx_hash(seed),
+                100,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    for recipient in [0, 2] {
+        simulate_denied_transaction(
+            &mut banks_client,
+            teleport_out(1, recipient),
+            TeleportError::DeniedRecipient,
+        )
+        .await;
+    }
+
+    // a batch is rejected as a whole
+    for recipient in [0, 2] {
+        let entries =
+            teleport_out_entries(&[accounts.recipients[1], accounts.recipients[recipient]]);
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out_batch(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.mint_auth,
+                &entries,
+                &[
+                    accounts.recipient_owners[1],
+                    accounts.recipient_owners[recipient],
+                ],
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        simulate_denied_transaction(
+            &mut banks_client,
+            transaction,
+            TeleportError::DeniedRecipient,
+        )
+        .await;
+    }
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::remove_from_denylist(
+            &blt_teleport::id(),
+            &owner,
+            DenylistKey::Solana(denied),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    banks_client
+        .process_transaction(teleport_out(1, 0))
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(&mut banks_client, &denied).await, 100);
+}
+
+#[tokio::test]
+async fn test_denylist_teleport_in() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_in_accounts(&mut program_test, 1000);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+    let flow_address = [7; 8];
+    let evm_address = [9; 20];
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_chain_enabled(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            Chain::Bsc,
+            true,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let teleport_in = |chain, to: &[u8], blockhash| {
+        accounts.teleport_in(&payer, &config_pubkey, 0, 400, chain, to, blockhash)
+    };
+
+    // the rejection is only told by its error, a failed instruction emits
+    // no event
+    let keys = [
+        (
+            DenylistKey::Solana(accounts.from_auth.pubkey()),
+            Chain::Flow,
+            &flow_address[..],
+            TeleportError::DeniedSender,
+        ),
+        (
+            DenylistKey::Flow(flow_address),
+            Chain::Flow,
+            &flow_address[..],
+            TeleportError::DeniedFlowAddress,
+        ),
+        (
+            DenylistKey::Evm(evm_address),
+            Chain::Bsc,
+            &evm_address[..],
+            TeleportError::DeniedEvmAddress,
+        ),
+    ];
+    for (key, chain, to, error) in keys {
+        let recent_blockhash = banks_client
+            .get_new_latest_blockhash(&recent_blockhash)
+            .await
+            .unwrap();
+        for instruction in [
+            blt_teleport::instruction::add_to_denylist(&blt_teleport::id(), &owner, key),
+            blt_teleport::instruction::remove_from_denylist(&blt_teleport::id(), &owner, key),
+        ] {
+            process_owner_instruction(
+                &mut banks_client,
+                &payer,
+                &recent_blockhash,
+                instruction.unwrap(),
+            )
+            .await
+            .unwrap();
+            if let Some(denylist) = banks_client
+                .get_account(blt_teleport::state::get_denylist_address(
+                    &blt_teleport::id(),
+                    &key,
+                ))
+                .await
+                .unwrap()
+            {
+                simulate_denied_transaction(
+                    &mut banks_client,
+                    teleport_in(chain, to, recent_blockhash),
+                    error.clone(),
+                )
+                .await;
+                assert_eq!(denylist.data.len(), blt_teleport::state::DenylistEntry::LEN);
+            }
+        }
+    }
+
+    banks_client
+        .process_transaction(teleport_in(Chain::Flow, &flow_address, recent_blockhash))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        600
+    );
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_burned, 400);
+}
+
+#[tokio::test]
+async fn test_amount_limits() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_in_accounts(&mut program_test, 1000);
+    let recipient = Pubkey::new_unique();
+    let recipient_owner = Pubkey::new_unique();
+    add_token_account(&mut program_test, recipient, &recipient_owner);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        10_000,
+    )
+    .await;
+
+    let limits = blt_teleport::state::AmountLimits { min: 100, max: 500 };
+
+    // min above max
+    let result = process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_amount_limits(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            blt_teleport::state::AmountLimits { min: 600, max: 500 },
+            limits,
+        )
+        .unwrap(),
+    )
+    .await;
+    assert!(result.is_err());
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_amount_limits(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            limits,
+            limits,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.teleport_in_limits, limits);
+    assert_eq!(config.teleport_out_limits, limits);
+    assert_eq!(
+        config.teleport_out_limits.check(99),
+        Err(TeleportError::AmountBelowMinimum)
+    );
+
+    let teleport_out = |seed, amount| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &Pubkey::new_unique(),
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &recipient,
+                AddressType::Spl,
+                &recipient_owner,
+                &accounts.mint_auth,
+                &tx_hash(seed),
+                amount,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+    let flow_address = [7; 8];
+
+    for (amount, error) in [
+        (99, TeleportError::AmountBelowMinimum),
+        (501, TeleportError::AmountAboveMaximum),
+    ] {
+        simulate_denied_transaction(&mut banks_client, teleport_out(1, amount), error.clone())
+            .await;
+        simulate_denied_transaction(
+            &mut banks_client,
+            accounts.teleport_in(
+                &payer,
+                &config_pubkey,
+                0,
+                amount,
+                Chain::Flow,
+                &flow_address,
+                recent_blockhash,
+            ),
+            error,
+        )
+        .await;
+    }
+
+    banks_client
+        .process_transaction(accounts.teleport_in(
+            &payer,
+            &config_pubkey,
+            0,
+            500,
+            Chain::Flow,
+            &flow_address,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        500
+    );
+}
+
+#[tokio::test]
+async fn test_teleport_in_chains() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_in_accounts(&mut program_test, 1000);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+    let evm_address = [7; 20];
+
+    // EVM chains start disabled
+    simulate_denied_transaction(
+        &mut banks_client,
+        accounts.teleport_in(
+            &payer,
+            &config_pubkey,
+            0,
+            100,
+            Chain::Bsc,
+            &evm_address,
+            recent_blockhash,
+        ),
+        TeleportError::ChainDisabled,
+    )
+    .await;
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_chain_enabled(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            Chain::Bsc,
+            true,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert!(config.is_chain_enabled(Chain::Bsc));
+    assert!(!config.is_chain_enabled(Chain::Ethereum));
+
+    // the builder rejects a Flow address for an EVM chain, so swap the data
+    let mut instruction = blt_teleport::instruction::teleport_in(
+        &blt_teleport::id(),
+        &config_pubkey,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &accounts.from,
+        &blt_mint(),
+        &spl_token::id(),
+        &accounts.from_auth.pubkey(),
+        &payer.pubkey(),
+        0,
+        100,
+        Chain::Bsc,
+        &evm_address,
+        None,
+    )
+    .unwrap();
+    instruction.data = TeleportInstruction::TeleportIn {
+        amount: 100,
+        to: vec![7; 8],
+        chain: Chain::Bsc,
+        payload: None,
+        nonce: 0,
+    }
+    .try_to_vec()
+    .unwrap();
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &accounts.from_auth], recent_blockhash);
+    simulate_denied_transaction(
+        &mut banks_client,
+        transaction,
+        TeleportError::InvalidAddress,
+    )
+    .await;
+
+    simulate_denied_transaction(
+        &mut banks_client,
+        accounts.teleport_in(
+            &payer,
+            &config_pubkey,
+            0,
+            100,
+            Chain::Bsc,
+            &[0; 20],
+            recent_blockhash,
+        ),
+        TeleportError::InvalidAddress,
+    )
+    .await;
+
+    banks_client
+        .process_transaction(accounts.teleport_in(
+            &payer,
+            &config_pubkey,
+            0,
+            100,
+            Chain::Bsc,
+            &evm_address,
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        900
+    );
+}
+
+#[tokio::test]
+async fn test_teleport_in_payload() {
+    let mut program_test = program_test();
+    let accounts = add_teleport_in_accounts(&mut program_test, 1000);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let config_pubkey = create_config(&mut banks_client, &payer, &recent_blockhash, &[]).await;
+    let max_len = blt_teleport::state::MAX_PAYLOAD_LEN;
+
+    simulate_denied_transaction(
+        &mut banks_client,
+        accounts.teleport_in_with_payload(
+            &payer,
+            &config_pubkey,
+            0,
+            100,
+            Chain::Flow,
+            &[7; 8],
+            Some(vec![1; max_len + 1]),
+            recent_blockhash,
+        ),
+        TeleportError::PayloadTooLarge,
+    )
+    .await;
+
+    banks_client
+        .process_transaction(accounts.teleport_in_with_payload(
+            &payer,
+            &config_pubkey,
+            0,
+            100,
+            Chain::Flow,
+            &[7; 8],
+            Some(vec![1; max_len]),
+            recent_blockhash,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        900
+    );
+}
+
+#[tokio::test]
+async fn test_teleport_out_payload() {
+    let mut program_test = program_test();
+    program_test.add_program(
+        "receiver",
+        receiver_program_id(),
+        processor!(process_mock_receiver),
+    );
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let inbox = Pubkey::new_unique();
+    program_test.add_account(
+        inbox,
+        Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 512],
+            owner: receiver_program_id(),
+            ..Account::default()
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let teleport_out = |payload| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out_with_payload(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.recipients[0],
+                AddressType::Spl,
+                &accounts.recipient_owners[0],
+                &accounts.mint_auth,
+                &tx_hash(1),
+                400,
+                payload,
+                &receiver_program_id(),
+                vec![AccountMeta::new(inbox, false)],
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    simulate_denied_transaction(
+        &mut banks_client,
+        teleport_out(b"stake".to_vec()),
+        TeleportError::ReceiverNotAllowed,
+    )
+    .await;
+
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::add_receiver(
+            &blt_teleport::id(),
+            &owner,
+            &receiver_program_id(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    simulate_denied_transaction(
+        &mut banks_client,
+        teleport_out(vec![1; blt_teleport::state::MAX_PAYLOAD_LEN + 1]),
+        TeleportError::PayloadTooLarge,
+    )
+    .await;
+
+    banks_client
+        .process_transaction(teleport_out(b"stake".to_vec()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.recipients[0]).await,
+        400
+    );
+    let inbox = banks_client.get_account(inbox).await.unwrap().unwrap();
+    let message = ReceiverMessage::deserialize(&mut &inbox.data[..]).unwrap();
+    assert_eq!(
+        message,
+        ReceiverMessage {
+            tx_hash: tx_hash(1),
+            amount: 400,
+            to: accounts.recipients[0],
+            payload: b"stake".to_vec(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_teleport_out_vesting() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_out_accounts(&mut program_test, 1);
+    let beneficiary = Keypair::new();
+    let beneficiary_token = Pubkey::new_unique();
+    add_token_account(&mut program_test, beneficiary_token, &beneficiary.pubkey());
+    let mut context = program_test.start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let recent_blockhash = context.last_blockhash;
+    let owner = get_owner().pubkey();
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let start = clock.unix_timestamp;
+    let steps = vec![
+        LockupStep {
+            timestamp: start,
+            locked_ratio: FULLY_LOCKED,
+        },
+        LockupStep {
+            timestamp: start + 1000,
+            locked_ratio: FULLY_LOCKED / 2,
+        },
+        LockupStep {
+            timestamp: start + 2000,
+            locked_ratio: 0,
+        },
+    ];
+
+    // out of order steps
+    let mut unordered = steps.clone();
+    unordered.swap(0, 1);
+    assert!(process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_lockup_schedule(&blt_teleport::id(), &owner, 1, unordered)
+            .unwrap(),
+    )
+    .await
+    .is_err());
+
+    let teleport_out_vesting = |seed, schedule_id| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out_vesting(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &accounts.mint_auth,
+                &tx_hash(seed),
+                400,
+                &beneficiary.pubkey(),
+                schedule_id,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    simulate_denied_transaction(
+        &mut context.banks_client,
+        teleport_out_vesting(1, 1),
+        TeleportError::UninitializedAccount,
+    )
+    .await;
+
+    process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_lockup_schedule(
+            &blt_teleport::id(),
+            &owner,
+            1,
+            steps.clone(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    context
+        .banks_client
+        .process_transaction(teleport_out_vesting(1, 1))
+        .await
+        .unwrap();
+
+    let vault = get_vesting_vault_address(&blt_teleport::id(), &blt_mint());
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &vault).await,
+        400
+    );
+    let admin = get_admin(&mut context.banks_client, &admin_pubkey).await;
+    assert_eq!(admin.allowance, 600);
+    let vesting_account = context
+        .banks_client
+        .get_account(get_vesting_address(&blt_teleport::id(), &tx_hash(1)))
+        .await
+        .unwrap()
+        .unwrap();
+    let vesting = Vesting::try_from_slice(&vesting_account.data).unwrap();
+    assert_eq!(vesting.mint, blt_mint());
+    assert_eq!(vesting.amount, 400);
+    assert_eq!(vesting.steps(), &steps[..]);
+
+    // the escrow keeps the steps it was made with, unlocking the schedule
+    // now doesn't unlock it
+    process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_lockup_schedule(
+            &blt_teleport::id(),
+            &owner,
+            1,
+            vec![LockupStep {
+                timestamp: start,
+                locked_ratio: 0,
+            }],
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    // the escrow keeps its mint across a route change
+    process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_token_route(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+            &spl_token::id(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let claim_vesting = |signer: &Keypair, recent_blockhash| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::claim_vesting(
+                &blt_teleport::id(),
+                &signer.pubkey(),
+                &tx_hash(1),
+                &blt_mint(),
+                &beneficiary_token,
+                &payer.pubkey(),
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, signer], recent_blockhash);
+        transaction
+    };
+
+    simulate_denied_transaction(
+        &mut context.banks_client,
+        claim_vesting(&beneficiary, recent_blockhash),
+        TeleportError::NothingToClaim,
+    )
+    .await;
+    simulate_denied_transaction(
+        &mut context.banks_client,
+        claim_vesting(&auth, recent_blockhash),
+        TeleportError::AuthFailed,
+    )
+    .await;
+
+    // half unlocked
+    clock.unix_timestamp = start + 1000;
+    context.set_sysvar(&clock);
+
+    // a denylisted beneficiary can't claim
+    let denylist = |add| {
+        let key = DenylistKey::Solana(beneficiary.pubkey());
+        if add {
+            blt_teleport::instruction::add_to_denylist(&blt_teleport::id(), &owner, key)
+        } else {
+            blt_teleport::instruction::remove_from_denylist(&blt_teleport::id(), &owner, key)
+        }
+        .unwrap()
+    };
+    process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        denylist(true),
+    )
+    .await
+    .unwrap();
+    simulate_denied_transaction(
+        &mut context.banks_client,
+        claim_vesting(&beneficiary, recent_blockhash),
+        TeleportError::DeniedRecipient,
+    )
+    .await;
+    process_owner_instruction(
+        &mut context.banks_client,
+        &payer,
+        &recent_blockhash,
+        denylist(false),
+    )
+    .await
+    .unwrap();
+
+    context
+        .banks_client
+        .process_transaction(claim_vesting(&beneficiary, recent_blockhash))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &beneficiary_token).await,
+        200
+    );
+
+    let recent_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    simulate_denied_transaction(
+        &mut context.banks_client,
+        claim_vesting(&beneficiary, recent_blockhash),
+        TeleportError::NothingToClaim,
+    )
+    .await;
+
+    // fully unlocked, the escrow is closed
+    clock.unix_timestamp = start + 2000;
+    context.set_sysvar(&clock);
+    context
+        .banks_client
+        .process_transaction(claim_vesting(&beneficiary, recent_blockhash))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &beneficiary_token).await,
+        400
+    );
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &vault).await,
+        0
+    );
+    let vesting = get_vesting_address(&blt_teleport::id(), &tx_hash(1));
+    assert_eq!(get_lamports(&mut context.banks_client, &vesting).await, 0);
+}
+
+#[tokio::test]
+async fn test_refund_teleport_in() {
+    let mut program_test = program_test();
+    add_owner_lamports(&mut program_test, 1_000_000_000);
+    let accounts = add_teleport_in_accounts(&mut program_test, 1000);
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        500,
+    )
+    .await;
+
+    for nonce in [1, 2, 3] {
+        banks_client
+            .process_transaction(accounts.teleport_in(
+                &payer,
+                &config_pubkey,
+                nonce,
+                300,
+                Chain::Flow,
+                &[7; 8],
+                recent_blockhash,
+            ))
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        100
+    );
+
+    // a nonce keys a single receipt
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    simulate_denied_transaction(
+        &mut banks_client,
+        accounts.teleport_in(
+            &payer,
+            &config_pubkey,
+            1,
+            300,
+            Chain::Flow,
+            &[7; 8],
+            recent_blockhash,
+        ),
+        TeleportError::AlreadyInUse,
+    )
+    .await;
+
+    let refund = |nonce, recent_blockhash| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::refund_teleport_in(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.wallet,
+                &payer.pubkey(),
+                &blt_mint(),
+                &spl_token::id(),
+                &accounts.mint_auth,
+                &accounts.from_auth.pubkey(),
+                nonce,
+                &accounts.from,
+                &accounts.from_auth.pubkey(),
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+    let mark_unlocked = |nonce, recent_blockhash| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::mark_teleport_in_unlocked(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &accounts.from_auth.pubkey(),
+                nonce,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    banks_client
+        .process_transaction(refund(1, recent_blockhash))
+        .await
+        .unwrap();
+    assert_eq!(
+        get_token_balance(&mut banks_client, &accounts.from).await,
+        400
+    );
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_burned, 600);
+    let admin = get_admin(&mut banks_client, &admin_pubkey).await;
+    assert_eq!(admin.allowance, 200);
+
+    banks_client
+        .process_transaction(mark_unlocked(2, recent_blockhash))
+        .await
+        .unwrap();
+
+    // a receipt settles once, either way
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    for transaction in [
+        refund(1, recent_blockhash),
+        mark_unlocked(1, recent_blockhash),
+        refund(2, recent_blockhash),
+    ] {
+        simulate_denied_transaction(
+            &mut banks_client,
+            transaction,
+            TeleportError::TeleportInSettled,
+        )
+        .await;
+    }
+
+    let receipt = banks_client
+        .get_account(get_teleport_in_receipt_address(
+            &blt_teleport::id(),
+            &accounts.from_auth.pubkey(),
+            1,
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    let receipt = TeleportInReceipt::try_from_slice(&receipt.data).unwrap();
+    assert_eq!(receipt.status, TeleportInStatus::Refunded);
+    assert_eq!(receipt.amount, 300);
+    assert_eq!(receipt.from, accounts.from);
+
+    // a denylisted owner gets nothing back, then the allowance runs out
+    let key = DenylistKey::Solana(accounts.from_auth.pubkey());
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::add_to_denylist(&blt_teleport::id(), &get_owner().pubkey(), key)
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    simulate_denied_transaction(
+        &mut banks_client,
+        refund(3, recent_blockhash),
+        TeleportError::DeniedSender,
+    )
+    .await;
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::remove_from_denylist(
+            &blt_teleport::id(),
+            &get_owner().pubkey(),
+            key,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    simulate_denied_transaction(
+        &mut banks_client,
+        refund(3, recent_blockhash),
+        TeleportError::UnexpectedError,
+    )
+    .await;
+}
+
+// Token-2022 state with `extensions`, owned by the Token-2022 program.
+fn add_token_2022_account<S: BaseState + Pack>(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    base: S,
+    extensions: &[ExtensionType],
+    init_extensions: impl FnOnce(&mut StateWithExtensionsMut<S>),
+) {
+    let len = ExtensionType::try_calculate_account_len::<S>(extensions).unwrap();
+    let mut data = vec![0; len];
+    let mut state = StateWithExtensionsMut::<S>::unpack_uninitialized(&mut data).unwrap();
+    init_extensions(&mut state);
+    state.base = base;
+    state.pack_base();
+    state.init_account_type().unwrap();
+    program_test.add_account(
+        address,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token_2022::id(),
+            ..Account::default()
+        },
+    );
+}
+
+fn add_token_2022_token_account(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+) {
+    add_token_2022_account(
+        program_test,
+        address,
+        spl_token_2022::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token_2022::state::AccountState::Initialized,
+            ..spl_token_2022::state::Account::default()
+        },
+        &[
+            ExtensionType::TransferFeeAmount,
+            ExtensionType::TransferHookAccount,
+        ],
+        |state| {
+            state.init_extension::<TransferFeeAmount>(true).unwrap();
+            state.init_extension::<TransferHookAccount>(true).unwrap();
+        },
+    );
+}
+
+#[tokio::test]
+async fn test_token_2022_route() {
+    let mut program_test = program_test();
+    let wallet = Pubkey::new_unique();
+    let (mint_auth, _) = Pubkey::find_program_address(&[wallet.as_ref()], &multisig_program_id());
+    let token_program = spl_token_2022::id();
+
+    // a 1% transfer fee and a transfer hook, neither applies to mint or burn
+    let mint = Pubkey::new_unique();
+    add_token_2022_account(
+        &mut program_test,
+        mint,
+        spl_token_2022::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply: 1000,
+            decimals: BLT_DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+        &[
+            ExtensionType::TransferFeeConfig,
+            ExtensionType::TransferHook,
+        ],
+        |state| {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: u64::MAX.into(),
+                transfer_fee_basis_points: 100.into(),
+            };
+            let fee_config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            fee_config.older_transfer_fee = fee;
+            fee_config.newer_transfer_fee = fee;
+            let hook = state.init_extension::<TransferHook>(true).unwrap();
+            hook.program_id = Some(Pubkey::new_unique()).try_into().unwrap();
+        },
+    );
+    let from_auth = Keypair::new();
+    let from = Pubkey::new_unique();
+    add_token_2022_token_account(&mut program_test, from, &mint, &from_auth.pubkey(), 1000);
+    let recipient = Pubkey::new_unique();
+    let recipient_owner = Pubkey::new_unique();
+    add_token_2022_token_account(&mut program_test, recipient, &mint, &recipient_owner, 0);
+    // the route moves back to BLT at the end
+    add_packable_account(
+        &mut program_test,
+        blt_mint(),
+        &spl_token::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply: 500,
+            decimals: BLT_DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+    let owner = get_owner().pubkey();
+
+    let auth = Keypair::new();
+    let (config_pubkey, admin_pubkey) = create_config_with_admin(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &auth.pubkey(),
+        1000,
+    )
+    .await;
+
+    // the mint has to be owned by the token program
+    assert!(process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_token_route(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &mint,
+            &spl_token::id(),
+        )
+        .unwrap(),
+    )
+    .await
+    .is_err());
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_token_route(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &mint,
+            &token_program,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.mint(), mint);
+    assert_eq!(config.token_program(), token_program);
+    assert_eq!(config.total_minted, 1000);
+    assert_eq!(config.total_burned, 0);
+
+    // the supply minted before the route was set isn't a mismatch
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            blt_teleport::instruction::check_invariants(&blt_teleport::id(), &config_pubkey, &mint)
+                .unwrap(),
+        ],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert!(
+        !get_config(&mut banks_client, &config_pubkey)
+            .await
+            .is_frozen
+    );
+
+    let teleport_out = |seed, token_program: &Pubkey, to: &Pubkey, to_address_type, to_owner| {
+        let mut transaction = Transaction::new_with_payer(
+            &[blt_teleport::instruction::teleport_out(
+                &blt_teleport::id(),
+                &config_pubkey,
+                &admin_pubkey,
+                &auth.pubkey(),
+                &wallet,
+                &payer.pubkey(),
+                &mint,
+                token_program,
+                to,
+                to_address_type,
+                to_owner,
+                &mint_auth,
+                &tx_hash(seed),
+                300,
+            )
+            .unwrap()],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &auth], recent_blockhash);
+        transaction
+    };
+
+    simulate_denied_transaction(
+        &mut banks_client,
+        teleport_out(
+            1,
+            &spl_token::id(),
+            &recipient,
+            AddressType::Spl,
+            &recipient_owner,
+        ),
+        TeleportError::InvalidTokenRoute,
+    )
+    .await;
+
+    banks_client
+        .process_transaction(teleport_out(
+            1,
+            &token_program,
+            &recipient,
+            AddressType::Spl,
+            &recipient_owner,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(get_token_balance(&mut banks_client, &recipient).await, 300);
+
+    // a wallet recipient gets a Token-2022 associated token account
+    let to_wallet = Pubkey::new_unique();
+    banks_client
+        .process_transaction(teleport_out(
+            2,
+            &token_program,
+            &to_wallet,
+            AddressType::Sol,
+            &to_wallet,
+        ))
+        .await
+        .unwrap();
+    let to_token_account =
+        get_associated_token_address_with_program_id(&to_wallet, &mint, &token_program);
+    assert_eq!(
+        get_token_balance(&mut banks_client, &to_token_account).await,
+        300
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[blt_teleport::instruction::teleport_in(
+            &blt_teleport::id(),
+            &config_pubkey,
+            &wallet,
+            &mint_auth,
+            &from,
+            &mint,
+            &token_program,
+            &from_auth.pubkey(),
+            &payer.pubkey(),
+            0,
+            400,
+            Chain::Flow,
+            &[7; 8],
+            None,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &from_auth], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+    assert_eq!(get_token_balance(&mut banks_client, &from).await, 600);
+
+    let mint_account = banks_client.get_account(mint).await.unwrap().unwrap();
+    let supply = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_account.data)
+        .unwrap()
+        .base
+        .supply;
+    assert_eq!(supply, 1000 + 600 - 400);
+
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.total_minted, 1000 + 600);
+    assert_eq!(config.total_burned, 400);
+
+    // the counters restart from the supply of the next mint
+    process_owner_instruction(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        blt_teleport::instruction::set_token_route(
+            &blt_teleport::id(),
+            &owner,
+            &config_pubkey,
+            &blt_mint(),
+            &spl_token::id(),
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    let config = get_config(&mut banks_client, &config_pubkey).await;
+    assert_eq!(config.mint(), blt_mint());
+    assert_eq!(config.total_minted, 500);
+    assert_eq!(config.total_burned, 0);
 }
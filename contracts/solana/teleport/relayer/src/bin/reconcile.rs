@@ -0,0 +1,127 @@
+use {
+    blt_teleport::state::BLT_MINT_KEY,
+    blt_teleport_client::TeleportClient,
+    blt_teleport_relayer::{
+        custody::parse_ufix64, reconcile, reconcile::fetch_lock_vault_balance, FileSource,
+        FlowLedger, HttpSource, RelayerError, SolanaLedger,
+    },
+    clap::{ArgGroup, Parser},
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+    },
+    std::{path::PathBuf, process::exit},
+};
+
+/// Checks that the Flow lock vault holds the Solana supply and the transfers
+/// in flight, printing a JSON report with the unmatched transfers. Exits
+/// with 2 on a mismatch.
+#[derive(Debug, Parser)]
+#[clap(name = "blt-teleport-reconcile", version)]
+#[clap(group(ArgGroup::new("source").required(true).args(&["events-file", "flow-url"])))]
+struct Args {
+    /// JSON RPC URL of the Solana cluster.
+    #[clap(long, short = 'u', default_value = "http://127.0.0.1:8899")]
+    url: String,
+    #[clap(long, default_value_t = blt_teleport::id())]
+    program_id: Pubkey,
+    #[clap(long, default_value = BLT_MINT_KEY)]
+    mint: Pubkey,
+    /// `Locked` and `Unlocked` events in the `/v1/events` response format of
+    /// the Flow REST API.
+    #[clap(long, requires = "lock-vault-balance")]
+    events_file: Option<PathBuf>,
+    /// Flow access node REST API.
+    #[clap(long)]
+    flow_url: Option<String>,
+    /// UFix64 balance of the lock vault, read with
+    /// `getLockVaultBalanceSolana.cdc` from the Flow URL if not given.
+    #[clap(long, parse(try_from_str = parse_balance))]
+    lock_vault_balance: Option<u64>,
+    /// Type id of the custody contract.
+    #[clap(long, default_value = "A.0ac14a822e54cc4e.TeleportCustodySolana")]
+    custody: String,
+    /// Flow block height to read events from.
+    #[clap(long, default_value_t = 0)]
+    start_height: u64,
+    /// Solana transaction to read the history after, the whole history if
+    /// not given.
+    #[clap(long)]
+    start_after: Option<Signature>,
+}
+
+fn parse_balance(value: &str) -> Result<u64, String> {
+    parse_ufix64(value).ok_or_else(|| format!("{} isn't a UFix64", value))
+}
+
+async fn run(args: Args) -> Result<bool, RelayerError> {
+    let locked_type = format!("{}.Locked", args.custody);
+    let unlocked_type = format!("{}.Unlocked", args.custody);
+    let flow = match (&args.events_file, &args.flow_url) {
+        (Some(path), _) => {
+            FlowLedger::collect(
+                // clap requires it with the events file
+                args.lock_vault_balance.unwrap(),
+                &mut FileSource::new(path.clone(), locked_type),
+                &mut FileSource::new(path.clone(), unlocked_type),
+                args.start_height,
+            )
+            .await?
+        }
+        (_, Some(url)) => {
+            let lock_vault_balance = match args.lock_vault_balance {
+                Some(balance) => balance,
+                None => {
+                    let address = args
+                        .custody
+                        .split('.')
+                        .nth(1)
+                        .ok_or_else(|| RelayerError::Source("custody type id".to_string()))?;
+                    let source = HttpSource::new(url, String::new());
+                    fetch_lock_vault_balance(&source, &format!("0x{}", address)).await?
+                }
+            };
+            FlowLedger::collect(
+                lock_vault_balance,
+                &mut HttpSource::new(url, locked_type),
+                &mut HttpSource::new(url, unlocked_type),
+                args.start_height,
+            )
+            .await?
+        }
+        // clap requires one of them
+        _ => unreachable!(),
+    };
+
+    let rpc = RpcClient::new_with_commitment(args.url.clone(), CommitmentConfig::confirmed());
+    let mut source = RpcClient::new_with_commitment(args.url, CommitmentConfig::confirmed());
+    // only reads, the payer never signs
+    let mut client = TeleportClient::new(rpc, args.program_id, Keypair::new());
+    let solana = SolanaLedger::collect(
+        &mut client,
+        &mut source,
+        &args.mint,
+        &flow.locks,
+        args.start_after.as_ref(),
+    )
+    .await?;
+
+    let reconciliation = reconcile(&flow, &solana);
+    println!("{}", serde_json::to_string(&reconciliation).unwrap());
+    Ok(reconciliation.is_consistent())
+}
+
+#[tokio::main]
+async fn main() {
+    match run(Args::parse()).await {
+        Ok(true) => {}
+        Ok(false) => exit(2),
+        Err(e) => {
+            let output = serde_json::json!({ "error": e.to_string() });
+            eprintln!("{}", output);
+            exit(1);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use {
+    blt_teleport_relayer::{RelayerError, Watcher},
+    clap::Parser,
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+    std::{path::PathBuf, process::exit, time::Duration},
+};
+
+/// Watches `TeleportIn` transactions on Solana, printing a JSON line per
+/// teleport in with the arguments of `unlockTokensSolana.cdc`.
+#[derive(Debug, Parser)]
+#[clap(name = "blt-teleport-watcher", version)]
+struct Args {
+    /// JSON RPC URL of the Solana cluster.
+    #[clap(long, short = 'u', default_value = "http://127.0.0.1:8899")]
+    url: String,
+    #[clap(long, default_value_t = blt_teleport::id())]
+    program_id: Pubkey,
+    /// Slots a confirmed transaction waits behind the tip before it's
+    /// reported.
+    #[clap(long, default_value_t = 32)]
+    finality_depth: u64,
+    /// Progress file, created on the first run.
+    #[clap(long)]
+    cursor: PathBuf,
+    /// Transaction to start after without a cursor, the whole history if
+    /// not given.
+    #[clap(long)]
+    start_after: Option<Signature>,
+    /// Seconds between polls.
+    #[clap(long, default_value_t = 10)]
+    poll_interval: u64,
+    /// Poll once and exit.
+    #[clap(long)]
+    once: bool,
+}
+
+async fn run(args: Args) -> Result<(), RelayerError> {
+    let rpc = RpcClient::new_with_commitment(args.url, CommitmentConfig::confirmed());
+    let mut watcher = Watcher::new(
+        rpc,
+        args.program_id,
+        args.finality_depth,
+        args.cursor,
+        args.start_after,
+    )?;
+
+    loop {
+        watcher
+            .poll(|report| println!("{}", serde_json::to_string(&report).unwrap()))
+            .await?;
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(args.poll_interval)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Args::parse()).await {
+        let output = serde_json::json!({ "error": e.to_string() });
+        eprintln!("{}", output);
+        exit(1);
+    }
+}
@@ -0,0 +1,96 @@
+//! Persistent relayer and watcher progress
+
+use {
+    crate::error::RelayerError,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+    std::{fs, io::ErrorKind, path::Path},
+};
+
+/// Where an event is on Flow, events are handled in this order.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Position {
+    pub block_height: u64,
+    pub transaction_index: u64,
+    pub event_index: u64,
+}
+
+/// A `Locked` event that can't be relayed, left to the operator.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Failure {
+    pub transaction_id: String,
+    pub position: Position,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Checkpoint {
+    /// The next block height to read events from.
+    pub next_height: u64,
+    /// The last event handled, events up to it are skipped.
+    pub position: Option<Position>,
+    pub failures: Vec<Failure>,
+}
+
+impl Checkpoint {
+    pub fn new(start_height: u64) -> Self {
+        Self {
+            next_height: start_height,
+            ..Self::default()
+        }
+    }
+
+    /// The checkpoint at `path`, `None` if there's no file yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, RelayerError> {
+        load(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), RelayerError> {
+        save(self, path)
+    }
+
+    pub fn is_handled(&self, position: &Position) -> bool {
+        self.position.is_some_and(|last| *position <= last)
+    }
+}
+
+/// Where the watcher is on Solana, transactions are handled oldest first.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Cursor {
+    /// The last transaction handled, the next scan stops at it.
+    pub signature: Option<String>,
+    pub slot: u64,
+}
+
+impl Cursor {
+    /// The cursor at `path`, `None` if there's no file yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, RelayerError> {
+        load(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), RelayerError> {
+        save(self, path)
+    }
+}
+
+fn load<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, RelayerError> {
+    match fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| RelayerError::Checkpoint(format!("{}: {}", path.display(), e))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(RelayerError::Checkpoint(format!(
+            "{}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Writes `value` to `path`, through a rename so a crash leaves either the
+/// old or the new one.
+fn save<T: Serialize>(value: &T, path: &Path) -> Result<(), RelayerError> {
+    let error = |e: std::io::Error| RelayerError::Checkpoint(format!("{}: {}", path.display(), e));
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(value).unwrap()).map_err(error)?;
+    fs::rename(&tmp, path).map_err(error)
+}
@@ -0,0 +1,253 @@
+//! `TeleportCustodySolana` events in JSON-CDC
+
+use {
+    crate::error::RelayerError,
+    blt_teleport::{
+        instruction::AddressType,
+        state::{self, FLOW_DECIMALS},
+    },
+    serde_json::Value,
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    std::convert::TryInto,
+};
+
+/// An event of `TeleportCustodySolana`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CustodyEvent {
+    Locked(LockedEvent),
+    Unlocked(UnlockedEvent),
+    FeeCollected(FeeCollectedEvent),
+}
+
+impl CustodyEvent {
+    pub fn decode(payload: &Value) -> Result<Self, RelayerError> {
+        let event = CdcEvent::new(payload)?;
+        match event.name {
+            "Locked" => LockedEvent::from_cdc(&event).map(CustodyEvent::Locked),
+            "Unlocked" => UnlockedEvent::from_cdc(&event).map(CustodyEvent::Unlocked),
+            "FeeCollected" => FeeCollectedEvent::from_cdc(&event).map(CustodyEvent::FeeCollected),
+            name => Err(RelayerError::InvalidEvent(format!(
+                "unknown event {}",
+                name
+            ))),
+        }
+    }
+}
+
+/// `Locked(amount: UFix64, to: [UInt8], toAddressType: String)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockedEvent {
+    /// In Flow UFix64 units, 8 decimals.
+    pub amount: u64,
+    pub to: Pubkey,
+    pub to_address_type: AddressType,
+}
+
+impl LockedEvent {
+    pub fn decode(payload: &Value) -> Result<Self, RelayerError> {
+        Self::from_cdc(&CdcEvent::named(payload, "Locked")?)
+    }
+
+    fn from_cdc(event: &CdcEvent) -> Result<Self, RelayerError> {
+        let to_address_type = match event.field("toAddressType", "String")?.as_str() {
+            Some("SOL") => AddressType::Sol,
+            Some("SPL") => AddressType::Spl,
+            _ => return Err(event.invalid("toAddressType")),
+        };
+        Ok(Self {
+            amount: event.ufix64("amount")?,
+            to: Pubkey::new_from_array(event.bytes("to")?),
+            to_address_type,
+        })
+    }
+
+    /// `amount` in base units of a mint with `decimals`.
+    pub fn base_units(&self, decimals: u8) -> Result<u64, RelayerError> {
+        to_base_units(self.amount, decimals)
+    }
+}
+
+/// `Unlocked(amount: UFix64, from: [UInt8], txHash: String)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnlockedEvent {
+    /// In Flow UFix64 units, 8 decimals.
+    pub amount: u64,
+    pub from: Pubkey,
+    /// The `TeleportIn` transaction, `txHash` is its signature in hex.
+    pub tx_hash: Signature,
+}
+
+impl UnlockedEvent {
+    pub fn decode(payload: &Value) -> Result<Self, RelayerError> {
+        Self::from_cdc(&CdcEvent::named(payload, "Unlocked")?)
+    }
+
+    fn from_cdc(event: &CdcEvent) -> Result<Self, RelayerError> {
+        let tx_hash: [u8; 64] = event
+            .field("txHash", "String")?
+            .as_str()
+            .and_then(|tx_hash| hex::decode(tx_hash).ok())
+            .and_then(|tx_hash| tx_hash.try_into().ok())
+            .ok_or_else(|| event.invalid("txHash"))?;
+        Ok(Self {
+            amount: event.ufix64("amount")?,
+            from: Pubkey::new_from_array(event.bytes("from")?),
+            tx_hash: Signature::from(tx_hash),
+        })
+    }
+
+    /// `amount` in base units of a mint with `decimals`.
+    pub fn base_units(&self, decimals: u8) -> Result<u64, RelayerError> {
+        to_base_units(self.amount, decimals)
+    }
+}
+
+/// Which call the fee of a `FeeCollected` event was taken on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeeType {
+    Lock,
+    Unlock,
+}
+
+/// `FeeCollected(amount: UFix64, type: UInt8)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeCollectedEvent {
+    /// In Flow UFix64 units, 8 decimals.
+    pub amount: u64,
+    pub fee_type: FeeType,
+}
+
+impl FeeCollectedEvent {
+    pub fn decode(payload: &Value) -> Result<Self, RelayerError> {
+        Self::from_cdc(&CdcEvent::named(payload, "FeeCollected")?)
+    }
+
+    fn from_cdc(event: &CdcEvent) -> Result<Self, RelayerError> {
+        let fee_type = match event.field("type", "UInt8")?.as_str() {
+            Some("0") => FeeType::Lock,
+            Some("1") => FeeType::Unlock,
+            _ => return Err(event.invalid("type")),
+        };
+        Ok(Self {
+            amount: event.ufix64("amount")?,
+            fee_type,
+        })
+    }
+}
+
+/// The fields of a JSON-CDC event of `TeleportCustodySolana`.
+struct CdcEvent<'a> {
+    /// The event name, after the contract name of the type id.
+    name: &'a str,
+    fields: &'a [Value],
+}
+
+impl<'a> CdcEvent<'a> {
+    fn new(payload: &'a Value) -> Result<Self, RelayerError> {
+        let invalid =
+            || RelayerError::InvalidEvent("not a TeleportCustodySolana event".to_string());
+        if payload["type"] != "Event" {
+            return Err(invalid());
+        }
+        let name = payload["value"]["id"]
+            .as_str()
+            .and_then(|id| id.rsplit_once(".TeleportCustodySolana."))
+            .map(|(_, name)| name)
+            .ok_or_else(invalid)?;
+        let fields = payload["value"]["fields"].as_array().ok_or_else(invalid)?;
+        Ok(Self { name, fields })
+    }
+
+    fn named(payload: &'a Value, name: &str) -> Result<Self, RelayerError> {
+        let event = Self::new(payload)?;
+        if event.name != name {
+            return Err(event.invalid("event type"));
+        }
+        Ok(event)
+    }
+
+    fn invalid(&self, what: &str) -> RelayerError {
+        RelayerError::InvalidEvent(format!("{} {}", self.name, what))
+    }
+
+    /// The value of the field `name`, which must be of `cdc_type`.
+    fn field(&self, name: &str, cdc_type: &str) -> Result<&'a Value, RelayerError> {
+        self.fields
+            .iter()
+            .find(|field| field["name"] == name)
+            .map(|field| &field["value"])
+            .filter(|value| value["type"] == cdc_type)
+            .map(|value| &value["value"])
+            .ok_or_else(|| self.invalid(name))
+    }
+
+    fn ufix64(&self, name: &str) -> Result<u64, RelayerError> {
+        self.field(name, "UFix64")?
+            .as_str()
+            .and_then(parse_ufix64)
+            .ok_or_else(|| self.invalid(name))
+    }
+
+    /// A `[UInt8]` field holding a Solana address.
+    fn bytes(&self, name: &str) -> Result<[u8; 32], RelayerError> {
+        let bytes: Vec<u8> = self
+            .field(name, "Array")?
+            .as_array()
+            .ok_or_else(|| self.invalid(name))?
+            .iter()
+            .map(|byte| match (&byte["type"], byte["value"].as_str()) {
+                (Value::String(t), Some(value)) if t == "UInt8" => value.parse().ok(),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(|| self.invalid(name))?;
+        bytes
+            .try_into()
+            .map_err(|_| self.invalid(&format!("{} length", name)))
+    }
+}
+
+/// A UFix64 string in its integer units, `None` past 8 decimals or `u64`.
+pub fn parse_ufix64(value: &str) -> Option<u64> {
+    let (integer, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(integer) || (!fraction.is_empty() && !digits(fraction)) || fraction.len() > 8 {
+        return None;
+    }
+    let fraction = format!("{:0<8}", fraction).parse::<u64>().ok()?;
+    integer
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(100_000_000)?
+        .checked_add(fraction)
+}
+
+/// A UFix64 string of an amount in its integer units, all 8 decimals
+/// written out.
+pub fn format_ufix64(value: u64) -> String {
+    format!("{}.{:08}", value / 100_000_000, value % 100_000_000)
+}
+
+// Dust below the mint precision is an error, not rounded, as in the program.
+fn to_base_units(amount: u64, decimals: u8) -> Result<u64, RelayerError> {
+    state::to_base_units(amount, decimals).map_err(|_| {
+        RelayerError::InvalidEvent(format!(
+            "{} isn't a whole amount of a {} decimals mint",
+            format_ufix64(amount),
+            decimals
+        ))
+    })
+}
+
+/// Base units of a mint with `decimals` as a UFix64 amount, `None` if they
+/// have dust below the UFix64 precision or overflow it.
+pub fn from_base_units(base_units: u64, decimals: u8) -> Option<u64> {
+    if decimals <= FLOW_DECIMALS {
+        return 10u64
+            .checked_pow((FLOW_DECIMALS - decimals) as u32)
+            .and_then(|scale| base_units.checked_mul(scale));
+    }
+    let scale = 10u64.checked_pow((decimals - FLOW_DECIMALS) as u32)?;
+    let amount = base_units / scale;
+    (amount * scale == base_units).then_some(amount)
+}
@@ -0,0 +1,28 @@
+//! Error types
+
+use {blt_teleport_client::ClientError, thiserror::Error};
+
+#[derive(Debug, Error)]
+pub enum RelayerError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    /// The event source couldn't be read.
+    #[error("event source error: {0}")]
+    Source(String),
+    /// An event that isn't a valid `Locked` event.
+    #[error("invalid event: {0}")]
+    InvalidEvent(String),
+    /// A transaction the node returned that can't be read.
+    #[error("invalid transaction: {0}")]
+    InvalidTransaction(String),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(String),
+    #[error("can't read keypair {0}: {1}")]
+    Keypair(String, String),
+}
+
+impl From<reqwest::Error> for RelayerError {
+    fn from(e: reqwest::Error) -> Self {
+        RelayerError::Source(e.to_string())
+    }
+}
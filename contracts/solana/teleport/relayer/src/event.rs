@@ -0,0 +1,99 @@
+//! Flow events as the access node REST API serves them
+
+use {
+    crate::{checkpoint::Position, error::RelayerError},
+    base64::{engine::general_purpose::STANDARD, Engine},
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::convert::TryInto,
+};
+
+/// Flow REST API numbers are strings.
+mod string_u64 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Events of a block, as `/v1/events` of the Flow access node REST API
+/// returns them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BlockEvents {
+    pub block_id: String,
+    #[serde(with = "string_u64")]
+    pub block_height: u64,
+    pub events: Vec<RawEvent>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RawEvent {
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub transaction_id: String,
+    #[serde(with = "string_u64")]
+    pub transaction_index: u64,
+    #[serde(with = "string_u64")]
+    pub event_index: u64,
+    /// JSON-CDC of the event, base64 encoded.
+    pub payload: String,
+}
+
+/// A Flow event with its JSON-CDC payload decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowEvent {
+    pub event_type: String,
+    pub transaction_id: String,
+    pub position: Position,
+    pub payload: Value,
+}
+
+impl FlowEvent {
+    /// Events of `blocks` in order, the payloads decoded.
+    pub fn from_blocks(blocks: Vec<BlockEvents>) -> Result<Vec<Self>, RelayerError> {
+        let mut events = vec![];
+        for block in blocks {
+            for event in block.events {
+                let payload = STANDARD.decode(&event.payload).map_err(|_| {
+                    RelayerError::InvalidEvent(format!(
+                        "payload of {} isn't base64",
+                        event.transaction_id
+                    ))
+                })?;
+                let payload = serde_json::from_slice(&payload).map_err(|_| {
+                    RelayerError::InvalidEvent(format!(
+                        "payload of {} isn't JSON-CDC",
+                        event.transaction_id
+                    ))
+                })?;
+                events.push(FlowEvent {
+                    event_type: event.event_type,
+                    transaction_id: event.transaction_id,
+                    position: Position {
+                        block_height: block.block_height,
+                        transaction_index: event.transaction_index,
+                        event_index: event.event_index,
+                    },
+                    payload,
+                });
+            }
+        }
+        events.sort_by_key(|event| event.position);
+        Ok(events)
+    }
+
+    /// The Flow tx hash, which keys the teleport out record.
+    pub fn tx_hash(&self) -> Result<[u8; 32], RelayerError> {
+        hex::decode(&self.transaction_id)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| RelayerError::InvalidEvent(format!("tx hash {}", self.transaction_id)))
+    }
+}
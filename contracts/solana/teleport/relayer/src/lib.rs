@@ -0,0 +1,24 @@
+pub mod checkpoint;
+pub mod custody;
+pub mod error;
+pub mod event;
+pub mod reconcile;
+pub mod relayer;
+pub mod solana;
+pub mod source;
+pub mod watcher;
+
+pub use {
+    checkpoint::{Checkpoint, Cursor, Failure, Position},
+    custody::{CustodyEvent, FeeCollectedEvent, FeeType, LockedEvent, UnlockedEvent},
+    error::RelayerError,
+    event::FlowEvent,
+    reconcile::{
+        reconcile, FlowLedger, FlowLock, Mismatch, Reconciliation, SolanaLedger, SolanaTeleportIn,
+        SolanaTeleportOut, Unmatched,
+    },
+    relayer::{Outcome, Relayer, RelayerAdmin, Report, Retry},
+    solana::{ProgramTransaction, SignatureStatus, TransactionSource},
+    source::{Batch, EventSource, FileSource, HttpSource},
+    watcher::{TeleportIn, UnlockArguments, WatchOutcome, WatchReport, Watcher},
+};
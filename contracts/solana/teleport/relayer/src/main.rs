@@ -0,0 +1,145 @@
+use {
+    blt_teleport::state::BLT_MINT_KEY,
+    blt_teleport_client::{MintAuthority, TeleportClient},
+    blt_teleport_relayer::{
+        EventSource, FileSource, HttpSource, Relayer, RelayerAdmin, RelayerError, Retry,
+    },
+    clap::{ArgGroup, Parser},
+    solana_rpc_client::nonblocking::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        pubkey::Pubkey,
+        signature::{read_keypair_file, Keypair},
+    },
+    std::{
+        path::{Path, PathBuf},
+        process::exit,
+        time::Duration,
+    },
+};
+
+/// Relays `TeleportCustodySolana.Locked` events from Flow to `TeleportOut`,
+/// printing a JSON line per event.
+#[derive(Debug, Parser)]
+#[clap(name = "blt-teleport-relayer", version)]
+#[clap(group(ArgGroup::new("source").required(true).args(&["events-file", "flow-url"])))]
+struct Args {
+    /// JSON RPC URL of the Solana cluster.
+    #[clap(long, short = 'u', default_value = "http://127.0.0.1:8899")]
+    url: String,
+    /// Keypair file of the fee payer.
+    #[clap(long, short = 'k')]
+    keypair: PathBuf,
+    #[clap(long, default_value_t = blt_teleport::id())]
+    program_id: Pubkey,
+    #[clap(long)]
+    config: Pubkey,
+    #[clap(long)]
+    admin: Pubkey,
+    /// Keypair file of the admin auth.
+    #[clap(long)]
+    auth: PathBuf,
+    /// The multisig wallet holding the mint authority.
+    #[clap(long)]
+    wallet: Pubkey,
+    #[clap(long)]
+    mint_auth: Pubkey,
+    #[clap(long, default_value = BLT_MINT_KEY)]
+    mint: Pubkey,
+    #[clap(long, default_value_t = spl_token::id())]
+    token_program: Pubkey,
+    /// Events in the `/v1/events` response format of the Flow REST API.
+    #[clap(long)]
+    events_file: Option<PathBuf>,
+    /// Flow access node REST API.
+    #[clap(long)]
+    flow_url: Option<String>,
+    #[clap(
+        long,
+        default_value = "A.0ac14a822e54cc4e.TeleportCustodySolana.Locked"
+    )]
+    event_type: String,
+    /// Progress file, created on the first run.
+    #[clap(long)]
+    checkpoint: PathBuf,
+    /// Flow block height to start from without a checkpoint.
+    #[clap(long, default_value_t = 0)]
+    start_height: u64,
+    /// Seconds between polls.
+    #[clap(long, default_value_t = 10)]
+    poll_interval: u64,
+    #[clap(long, default_value_t = 5)]
+    retries: u32,
+    /// Milliseconds between retries.
+    #[clap(long, default_value_t = 1000)]
+    retry_delay: u64,
+    /// Poll once and exit.
+    #[clap(long)]
+    once: bool,
+}
+
+fn read_keypair(path: &Path) -> Result<Keypair, RelayerError> {
+    read_keypair_file(path)
+        .map_err(|e| RelayerError::Keypair(path.display().to_string(), e.to_string()))
+}
+
+async fn run<S: EventSource + Send>(args: Args, source: S) -> Result<(), RelayerError> {
+    let rpc = RpcClient::new_with_commitment(args.url, CommitmentConfig::confirmed());
+    let client = TeleportClient::new(rpc, args.program_id, read_keypair(&args.keypair)?);
+    let admin = RelayerAdmin {
+        config: args.config,
+        admin: args.admin,
+        auth: read_keypair(&args.auth)?,
+    };
+    let mint_authority = MintAuthority {
+        wallet: args.wallet,
+        mint_auth: args.mint_auth,
+        mint: args.mint,
+        token_program: args.token_program,
+    };
+    let retry = Retry {
+        attempts: args.retries.max(1),
+        delay: Duration::from_millis(args.retry_delay),
+    };
+    let mut relayer = Relayer::new(
+        client,
+        source,
+        admin,
+        mint_authority,
+        retry,
+        args.checkpoint,
+        args.start_height,
+    )?;
+
+    loop {
+        for report in relayer.poll().await? {
+            println!("{}", serde_json::to_string(&report).unwrap());
+        }
+        if args.once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(args.poll_interval)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let result = match (&args.events_file, &args.flow_url) {
+        (Some(path), _) => {
+            let source = FileSource::new(path.clone(), args.event_type.clone());
+            run(args, source).await
+        }
+        (_, Some(url)) => {
+            let source = HttpSource::new(url, args.event_type.clone());
+            run(args, source).await
+        }
+        // clap requires one of them
+        _ => unreachable!(),
+    };
+    if let Err(e) = result {
+        let output = serde_json::json!({ "error": e.to_string() });
+        eprintln!("{}", output);
+        exit(1);
+    }
+}
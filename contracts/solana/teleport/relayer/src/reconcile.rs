@@ -0,0 +1,426 @@
+//! Reconciles the Flow custody with the Solana supply
+
+use {
+    crate::{
+        custody::{format_ufix64, from_base_units, parse_ufix64, UnlockedEvent},
+        error::RelayerError,
+        event::FlowEvent,
+        solana::TransactionSource,
+        source::{EventSource, HttpSource},
+        watcher::TeleportIn,
+        LockedEvent,
+    },
+    blt_teleport::{
+        instruction::{Chain, TeleportInstruction},
+        state::{self, TeleportInStatus},
+    },
+    blt_teleport_client::{ClientError, TeleportClient, TeleportRpc},
+    borsh::BorshDeserialize,
+    serde::{Serialize, Serializer},
+    solana_sdk::{program_pack::Pack, pubkey::Pubkey, signature::Signature},
+    std::collections::{HashMap, HashSet},
+};
+
+/// `scripts/teleport/getLockVaultBalanceSolana.cdc`, imported from `{}`.
+const LOCK_VAULT_BALANCE_SCRIPT: &str = "import TeleportCustodySolana from {}
+
+pub fun main(): UFix64 {
+    return TeleportCustodySolana.getLockVaultBalance()
+}
+";
+
+/// `TeleportCustodySolana.getLockVaultBalance()` of the contract at
+/// `custody_address`, in UFix64 units.
+pub async fn fetch_lock_vault_balance(
+    source: &HttpSource,
+    custody_address: &str,
+) -> Result<u64, RelayerError> {
+    let script = LOCK_VAULT_BALANCE_SCRIPT.replace("{}", custody_address);
+    let result = source.execute_script(&script).await?;
+    match (&result["type"], result["value"].as_str()) {
+        (serde_json::Value::String(t), Some(value)) if t == "UFix64" => parse_ufix64(value),
+        _ => None,
+    }
+    .ok_or_else(|| RelayerError::Source(format!("lock vault balance: {}", result)))
+}
+
+/// A `Locked` event, keyed by its Flow transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowLock {
+    pub tx_hash: [u8; 32],
+    /// In Flow UFix64 units.
+    pub amount: u64,
+}
+
+/// The Flow side of the bridge, amounts in UFix64 units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FlowLedger {
+    pub lock_vault_balance: u64,
+    pub locks: Vec<FlowLock>,
+    pub unlocks: Vec<UnlockedEvent>,
+}
+
+impl FlowLedger {
+    /// Reads the `Locked` and `Unlocked` events from `start_height` until
+    /// the sources have no more blocks.
+    pub async fn collect<L: EventSource + Send, U: EventSource + Send>(
+        lock_vault_balance: u64,
+        locked: &mut L,
+        unlocked: &mut U,
+        start_height: u64,
+    ) -> Result<Self, RelayerError> {
+        let mut locks = vec![];
+        for event in fetch_all(locked, start_height).await? {
+            locks.push(FlowLock {
+                tx_hash: event.tx_hash()?,
+                amount: LockedEvent::decode(&event.payload)?.amount,
+            });
+        }
+        let mut unlocks = vec![];
+        for event in fetch_all(unlocked, start_height).await? {
+            unlocks.push(UnlockedEvent::decode(&event.payload)?);
+        }
+        Ok(Self {
+            lock_vault_balance,
+            locks,
+            unlocks,
+        })
+    }
+}
+
+async fn fetch_all<S: EventSource + Send>(
+    source: &mut S,
+    start_height: u64,
+) -> Result<Vec<FlowEvent>, RelayerError> {
+    let mut events = vec![];
+    let mut height = start_height;
+    loop {
+        let batch = source.fetch(height).await?;
+        events.extend(batch.events);
+        match batch.end_height {
+            Some(end_height) => height = end_height + 1,
+            None => return Ok(events),
+        }
+    }
+}
+
+/// A teleport out on Solana of a Flow transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolanaTeleportOut {
+    pub tx_hash: [u8; 32],
+    /// In Flow UFix64 units, `None` when only the record was found.
+    pub amount: Option<u64>,
+    /// Delayed and not minted yet.
+    pub pending: bool,
+    /// Delayed and cancelled, never to be minted.
+    pub cancelled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolanaTeleportIn {
+    pub signature: Signature,
+    /// In Flow UFix64 units.
+    pub amount: u64,
+    /// Minted back to the sender, never to be unlocked.
+    pub refunded: bool,
+}
+
+/// The Solana side of the bridge, amounts in UFix64 units.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolanaLedger {
+    pub supply: u64,
+    pub teleport_outs: Vec<SolanaTeleportOut>,
+    pub teleport_ins: Vec<SolanaTeleportIn>,
+}
+
+impl SolanaLedger {
+    /// Reads the supply of `mint`, the program transactions after `until`
+    /// and the records of the `locks` not in those transactions. Closed
+    /// records read as missing, see `Config::record_retention`, and only the
+    /// cancels after `until` are seen.
+    pub async fn collect<R: TeleportRpc + Send, S: TransactionSource + Send>(
+        client: &mut TeleportClient<R>,
+        source: &mut S,
+        mint: &Pubkey,
+        locks: &[FlowLock],
+        until: Option<&Signature>,
+    ) -> Result<Self, RelayerError> {
+        let account = client
+            .rpc()
+            .get_account(mint)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*mint))?;
+        // the base layout of SPL Token and Token-2022 mints
+        let mint_state = account
+            .data
+            .get(..spl_token::state::Mint::LEN)
+            .and_then(|data| spl_token::state::Mint::unpack_from_slice(data).ok())
+            .ok_or(ClientError::InvalidAccount(*mint, "mint"))?;
+        let supply = from_base_units(mint_state.supply, mint_state.decimals).ok_or_else(|| {
+            RelayerError::InvalidTransaction(format!("supply of {} isn't a UFix64", mint))
+        })?;
+
+        let program_id = *client.program_id();
+        let mut teleport_outs = vec![];
+        let mut teleport_ins = vec![];
+        // the pending accounts of the cancelled teleport outs
+        let mut cancelled = HashSet::new();
+        for status in source.signatures(&program_id, until).await? {
+            if status.failed {
+                continue;
+            }
+            let transaction = source.transaction(&status.signature).await?;
+            for instruction in &transaction.instructions {
+                if instruction.program_id != program_id {
+                    continue;
+                }
+                let entries = match TeleportInstruction::try_from_slice(&instruction.data) {
+                    Ok(TeleportInstruction::TeleportOut {
+                        tx_hash, amount, ..
+                    })
+                    | Ok(TeleportInstruction::TeleportOutVesting {
+                        tx_hash, amount, ..
+                    }) => vec![(tx_hash, amount)],
+                    Ok(TeleportInstruction::TeleportOutBatch { entries }) => entries
+                        .iter()
+                        .map(|entry| (entry.tx_hash, entry.amount))
+                        .collect(),
+                    Ok(TeleportInstruction::CancelTeleportOut) => {
+                        cancelled.extend(instruction.accounts.get(3).map(|meta| meta.pubkey));
+                        vec![]
+                    }
+                    _ => vec![],
+                };
+                for (tx_hash, amount) in entries {
+                    teleport_outs.push(SolanaTeleportOut {
+                        tx_hash,
+                        amount: Some(amount),
+                        pending: false,
+                        cancelled: false,
+                    });
+                }
+            }
+            // teleport ins to other chains never unlock through the custody
+            for teleport_in in TeleportIn::decode_all(&transaction, &program_id)
+                .into_iter()
+                .filter(|teleport_in| teleport_in.chain == Chain::Flow)
+            {
+                let refunded = client
+                    .fetch_teleport_in_receipt(&teleport_in.from_auth, teleport_in.nonce)
+                    .await?
+                    .is_some_and(|receipt| receipt.status == TeleportInStatus::Refunded);
+                teleport_ins.push(SolanaTeleportIn {
+                    signature: transaction.signature,
+                    amount: teleport_in.amount,
+                    refunded,
+                });
+            }
+        }
+
+        let seen: HashSet<[u8; 32]> = teleport_outs.iter().map(|out| out.tx_hash).collect();
+        for lock in locks.iter().filter(|lock| !seen.contains(&lock.tx_hash)) {
+            if client.is_tx_hash_processed(&lock.tx_hash).await? {
+                teleport_outs.push(SolanaTeleportOut {
+                    tx_hash: lock.tx_hash,
+                    amount: None,
+                    pending: false,
+                    cancelled: false,
+                });
+            }
+        }
+        for teleport_out in &mut teleport_outs {
+            teleport_out.pending = client
+                .fetch_pending_teleport_out(&teleport_out.tx_hash)
+                .await?
+                .is_some_and(|pending| pending.is_init);
+            teleport_out.cancelled = cancelled.contains(&state::get_pending_teleport_out_address(
+                &program_id,
+                &teleport_out.tx_hash,
+            ));
+        }
+
+        Ok(Self {
+            supply,
+            teleport_outs,
+            teleport_ins,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mismatch {
+    /// Locked on Flow, not minted on Solana yet.
+    PendingTeleportOut,
+    /// Locked on Flow, the delayed teleport out was cancelled on Solana. The
+    /// lock vault holds it until it's refunded on Flow.
+    CancelledTeleportOut,
+    /// Burned on Solana, not unlocked on Flow yet.
+    PendingUnlock,
+    /// Minted on Solana without a `Locked` event.
+    MintedWithoutLock,
+    /// Unlocked on Flow without a `TeleportIn`.
+    UnlockedWithoutTeleportIn,
+    /// Unlocked on Flow after the `TeleportIn` was refunded.
+    UnlockedRefund,
+    /// Both sides have the transfer with different amounts.
+    AmountMismatch,
+}
+
+impl Mismatch {
+    /// In-flight transfers, the balance accounts for them.
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Mismatch::PendingTeleportOut | Mismatch::PendingUnlock)
+    }
+}
+
+fn serialize_ufix64<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_ufix64(*value))
+}
+
+/// A transfer only one side has, or has differently.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Unmatched {
+    /// The Flow tx hash of teleport outs, the Solana signature of teleport
+    /// ins, in hex.
+    pub tx_hash: String,
+    pub mismatch: Mismatch,
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub amount: u64,
+}
+
+/// Amounts are UFix64 strings.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Reconciliation {
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub lock_vault_balance: u64,
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub supply: u64,
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub pending_teleport_outs: u64,
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub pending_unlocks: u64,
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub cancelled_teleport_outs: u64,
+    /// Held by the lock vault above the rest, burned on Solana without an
+    /// unlock: by holders, or by teleport ins to other chains.
+    #[serde(serialize_with = "serialize_ufix64")]
+    pub burned_outside: u64,
+    /// The lock vault holds the supply, the transfers in flight and the
+    /// cancelled teleport outs. Like `CheckInvariants`, burns outside the
+    /// bridge only lower the supply.
+    pub balanced: bool,
+    pub unmatched: Vec<Unmatched>,
+}
+
+impl Reconciliation {
+    /// Balanced, with nothing unmatched but transfers in flight.
+    pub fn is_consistent(&self) -> bool {
+        self.balanced
+            && self
+                .unmatched
+                .iter()
+                .all(|unmatched| unmatched.mismatch.is_pending())
+    }
+}
+
+pub fn reconcile(flow: &FlowLedger, solana: &SolanaLedger) -> Reconciliation {
+    let mut unmatched = vec![];
+    let mut pending_teleport_outs = 0u128;
+    let mut pending_unlocks = 0u128;
+    let mut cancelled_teleport_outs = 0u128;
+
+    let teleport_outs: HashMap<[u8; 32], &SolanaTeleportOut> = solana
+        .teleport_outs
+        .iter()
+        .map(|out| (out.tx_hash, out))
+        .collect();
+    let mut locked = HashSet::new();
+    for lock in &flow.locks {
+        locked.insert(lock.tx_hash);
+        let mut mismatch = |mismatch, amount| {
+            unmatched.push(Unmatched {
+                tx_hash: hex::encode(lock.tx_hash),
+                mismatch,
+                amount,
+            })
+        };
+        match teleport_outs.get(&lock.tx_hash) {
+            Some(out) if out.cancelled => {
+                cancelled_teleport_outs += lock.amount as u128;
+                mismatch(Mismatch::CancelledTeleportOut, lock.amount)
+            }
+            Some(out) if out.amount.is_some_and(|amount| amount != lock.amount) => {
+                mismatch(Mismatch::AmountMismatch, lock.amount)
+            }
+            Some(out) if !out.pending => {}
+            _ => {
+                pending_teleport_outs += lock.amount as u128;
+                mismatch(Mismatch::PendingTeleportOut, lock.amount)
+            }
+        }
+    }
+    // a cancelled teleport out minted nothing
+    for out in &solana.teleport_outs {
+        if !locked.contains(&out.tx_hash) && !out.cancelled {
+            unmatched.push(Unmatched {
+                tx_hash: hex::encode(out.tx_hash),
+                mismatch: Mismatch::MintedWithoutLock,
+                amount: out.amount.unwrap_or_default(),
+            });
+        }
+    }
+
+    let unlocks: HashMap<Signature, &UnlockedEvent> = flow
+        .unlocks
+        .iter()
+        .map(|unlock| (unlock.tx_hash, unlock))
+        .collect();
+    let mut teleported_in = HashSet::new();
+    for teleport_in in &solana.teleport_ins {
+        teleported_in.insert(teleport_in.signature);
+        let mut mismatch = |mismatch, amount| {
+            unmatched.push(Unmatched {
+                tx_hash: hex::encode(teleport_in.signature),
+                mismatch,
+                amount,
+            })
+        };
+        match unlocks.get(&teleport_in.signature) {
+            Some(_) if teleport_in.refunded => {
+                mismatch(Mismatch::UnlockedRefund, teleport_in.amount)
+            }
+            Some(unlock) if unlock.amount != teleport_in.amount => {
+                mismatch(Mismatch::AmountMismatch, teleport_in.amount)
+            }
+            Some(_) => {}
+            None if teleport_in.refunded => {}
+            None => {
+                pending_unlocks += teleport_in.amount as u128;
+                mismatch(Mismatch::PendingUnlock, teleport_in.amount)
+            }
+        }
+    }
+    for unlock in &flow.unlocks {
+        if !teleported_in.contains(&unlock.tx_hash) {
+            unmatched.push(Unmatched {
+                tx_hash: hex::encode(unlock.tx_hash),
+                mismatch: Mismatch::UnlockedWithoutTeleportIn,
+                amount: unlock.amount,
+            });
+        }
+    }
+
+    let expected =
+        solana.supply as u128 + pending_teleport_outs + pending_unlocks + cancelled_teleport_outs;
+    Reconciliation {
+        lock_vault_balance: flow.lock_vault_balance,
+        supply: solana.supply,
+        pending_teleport_outs: pending_teleport_outs.min(u64::MAX as u128) as u64,
+        pending_unlocks: pending_unlocks.min(u64::MAX as u128) as u64,
+        cancelled_teleport_outs: cancelled_teleport_outs.min(u64::MAX as u128) as u64,
+        burned_outside: (flow.lock_vault_balance as u128).saturating_sub(expected) as u64,
+        balanced: expected <= flow.lock_vault_balance as u128,
+        unmatched,
+    }
+}
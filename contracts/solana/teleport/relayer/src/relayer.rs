@@ -0,0 +1,239 @@
+//! Relays `Locked` events to `TeleportOut`
+
+use {
+    crate::{
+        checkpoint::{Checkpoint, Failure, Position},
+        custody::LockedEvent,
+        error::RelayerError,
+        event::FlowEvent,
+        source::EventSource,
+    },
+    blt_teleport::error::TeleportError,
+    blt_teleport_client::{ClientError, MintAuthority, TeleportClient, TeleportRpc},
+    serde::Serialize,
+    solana_sdk::{pubkey::Pubkey, signature::Keypair, transaction::TransactionError},
+    std::{path::PathBuf, time::Duration},
+};
+
+/// How many times a teleport out is tried when the node doesn't take it, and
+/// the wait between tries.
+#[derive(Clone, Copy, Debug)]
+pub struct Retry {
+    pub attempts: u32,
+    pub delay: Duration,
+}
+
+/// The admin teleporting out and its auth keypair.
+pub struct RelayerAdmin {
+    pub config: Pubkey,
+    pub admin: Pubkey,
+    pub auth: Keypair,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    Relayed {
+        signature: String,
+    },
+    /// The tx hash has a teleport out record already.
+    AlreadyProcessed,
+    /// The event can't be relayed, it's kept in the checkpoint failures.
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub transaction_id: String,
+    pub position: Position,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+pub struct Relayer<R, S> {
+    client: TeleportClient<R>,
+    source: S,
+    admin: RelayerAdmin,
+    mint_authority: MintAuthority,
+    retry: Retry,
+    checkpoint_path: PathBuf,
+    checkpoint: Checkpoint,
+}
+
+impl<R: TeleportRpc + Send, S: EventSource + Send> Relayer<R, S> {
+    /// Resumes from the checkpoint at `checkpoint_path`, from `start_height`
+    /// if there's none.
+    pub fn new(
+        client: TeleportClient<R>,
+        source: S,
+        admin: RelayerAdmin,
+        mint_authority: MintAuthority,
+        retry: Retry,
+        checkpoint_path: PathBuf,
+        start_height: u64,
+    ) -> Result<Self, RelayerError> {
+        let checkpoint =
+            Checkpoint::load(&checkpoint_path)?.unwrap_or_else(|| Checkpoint::new(start_height));
+        Ok(Self {
+            client,
+            source,
+            admin,
+            mint_authority,
+            retry,
+            checkpoint_path,
+            checkpoint,
+        })
+    }
+
+    pub fn checkpoint(&self) -> &Checkpoint {
+        &self.checkpoint
+    }
+
+    pub fn client(&mut self) -> &mut TeleportClient<R> {
+        &mut self.client
+    }
+
+    /// Relays the events after the checkpoint, saving it after each one. An
+    /// error leaves the event for the next poll.
+    pub async fn poll(&mut self) -> Result<Vec<Report>, RelayerError> {
+        let batch = self.source.fetch(self.checkpoint.next_height).await?;
+
+        let mut reports = vec![];
+        for event in batch.events {
+            if self.checkpoint.is_handled(&event.position) {
+                continue;
+            }
+            let outcome = self.relay(&event).await?;
+            if let Outcome::Failed { error } = &outcome {
+                self.checkpoint.failures.push(Failure {
+                    transaction_id: event.transaction_id.clone(),
+                    position: event.position,
+                    error: error.clone(),
+                });
+            }
+            self.checkpoint.position = Some(event.position);
+            self.checkpoint.save(&self.checkpoint_path)?;
+            reports.push(Report {
+                transaction_id: event.transaction_id,
+                position: event.position,
+                outcome,
+            });
+        }
+
+        if let Some(end_height) = batch.end_height {
+            self.checkpoint.next_height = self.checkpoint.next_height.max(end_height + 1);
+            self.checkpoint.save(&self.checkpoint_path)?;
+        }
+        Ok(reports)
+    }
+
+    async fn relay(&mut self, event: &FlowEvent) -> Result<Outcome, RelayerError> {
+        let (tx_hash, locked) = match event
+            .tx_hash()
+            .and_then(|tx_hash| Ok((tx_hash, LockedEvent::decode(&event.payload)?)))
+        {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                return Ok(Outcome::Failed {
+                    error: e.to_string(),
+                })
+            }
+        };
+
+        let mut attempt = 1;
+        loop {
+            match self.try_relay(event, &tx_hash, &locked).await {
+                Ok(outcome) => return Ok(outcome),
+                Err(e) if is_transient(&e) && attempt < self.retry.attempts => {
+                    attempt += 1;
+                    tokio::time::sleep(self.retry.delay).await;
+                }
+                Err(e) => {
+                    return if self.rejects_event(&e, locked.amount).await? {
+                        Ok(Outcome::Failed {
+                            error: e.to_string(),
+                        })
+                    } else {
+                        Err(e.into())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the program rejected the event itself, an instruction error
+    /// doesn't go away with another try. A frozen config, an exhausted
+    /// allowance, an admin that doesn't check out or a stale token route
+    /// holds every event, so those stop the relayer instead.
+    async fn rejects_event(&mut self, e: &ClientError, amount: u64) -> Result<bool, ClientError> {
+        match e {
+            ClientError::Teleport(
+                TeleportError::Freeze
+                | TeleportError::AuthFailed
+                | TeleportError::UninitializedAccount
+                | TeleportError::IncorrectProgramAccount
+                | TeleportError::InvalidTokenRoute,
+            ) => Ok(false),
+            // also what an exhausted allowance fails with
+            ClientError::Teleport(TeleportError::UnexpectedError) => {
+                let admin = self.client.fetch_admin(&self.admin.admin).await?;
+                Ok(admin.allowance >= amount)
+            }
+            ClientError::Teleport(_)
+            | ClientError::Transaction(TransactionError::InstructionError(..)) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    async fn try_relay(
+        &mut self,
+        event: &FlowEvent,
+        tx_hash: &[u8; 32],
+        locked: &LockedEvent,
+    ) -> Result<Outcome, ClientError> {
+        // checked before every try, one that timed out may have landed
+        if self.client.is_tx_hash_processed(tx_hash).await? {
+            // the record keys the whole Flow transaction
+            let same_transaction = self.checkpoint.position.is_some_and(|last| {
+                last.block_height == event.position.block_height
+                    && last.transaction_index == event.position.transaction_index
+            });
+            return Ok(if same_transaction {
+                Outcome::Failed {
+                    error: "another Locked event of the transaction was relayed".to_string(),
+                }
+            } else {
+                Outcome::AlreadyProcessed
+            });
+        }
+
+        let signature = self
+            .client
+            .teleport_out(
+                &self.admin.auth,
+                &self.admin.config,
+                &self.admin.admin,
+                &self.mint_authority,
+                &locked.to,
+                locked.to_address_type,
+                tx_hash,
+                locked.amount,
+            )
+            .await?;
+        Ok(Outcome::Relayed {
+            signature: signature.to_string(),
+        })
+    }
+}
+
+/// Errors worth another try, the node didn't process the transaction.
+fn is_transient(e: &ClientError) -> bool {
+    matches!(
+        e,
+        ClientError::Rpc(_)
+            | ClientError::Transaction(TransactionError::BlockhashNotFound)
+            | ClientError::Transaction(TransactionError::AlreadyProcessed)
+    )
+}
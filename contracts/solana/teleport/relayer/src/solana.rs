@@ -0,0 +1,200 @@
+//! Sources of teleport program transactions
+
+use {
+    crate::error::RelayerError,
+    async_trait::async_trait,
+    blt_teleport_client::ClientError,
+    solana_rpc_client::{
+        nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    },
+    solana_rpc_client_api::config::RpcTransactionConfig,
+    solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        pubkey::Pubkey,
+        signature::Signature,
+    },
+    solana_transaction_status::{
+        option_serializer::OptionSerializer, UiInstruction, UiTransactionEncoding,
+    },
+    std::str::FromStr,
+};
+
+/// A transaction that called the program, as the signature list has it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureStatus {
+    pub signature: Signature,
+    pub slot: u64,
+    /// Failed transactions changed nothing but their fee.
+    pub failed: bool,
+}
+
+/// A transaction with its instructions resolved, the inner ones right after
+/// the top level instruction that made them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramTransaction {
+    pub signature: Signature,
+    pub slot: u64,
+    pub instructions: Vec<Instruction>,
+}
+
+#[async_trait]
+pub trait TransactionSource {
+    /// The slot the source has seen up to.
+    async fn slot(&mut self) -> Result<u64, RelayerError>;
+
+    /// Transactions of `program_id` after `until`, oldest first.
+    async fn signatures(
+        &mut self,
+        program_id: &Pubkey,
+        until: Option<&Signature>,
+    ) -> Result<Vec<SignatureStatus>, RelayerError>;
+
+    async fn transaction(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<ProgramTransaction, RelayerError>;
+}
+
+/// Most signatures a `getSignaturesForAddress` call returns.
+const SIGNATURES_LIMIT: usize = 1000;
+
+#[async_trait]
+impl TransactionSource for RpcClient {
+    async fn slot(&mut self) -> Result<u64, RelayerError> {
+        Ok(self
+            .get_slot_with_commitment(self.commitment())
+            .await
+            .map_err(ClientError::from)?)
+    }
+
+    async fn signatures(
+        &mut self,
+        program_id: &Pubkey,
+        until: Option<&Signature>,
+    ) -> Result<Vec<SignatureStatus>, RelayerError> {
+        let mut statuses = vec![];
+        let mut before = None;
+        loop {
+            let page = self
+                .get_signatures_for_address_with_config(
+                    program_id,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: until.cloned(),
+                        limit: Some(SIGNATURES_LIMIT),
+                        commitment: Some(self.commitment()),
+                    },
+                )
+                .await
+                .map_err(ClientError::from)?;
+            let len = page.len();
+            for status in page {
+                let signature = Signature::from_str(&status.signature)
+                    .map_err(|e| ClientError::InvalidSignature(e.to_string()))?;
+                statuses.push(SignatureStatus {
+                    signature,
+                    slot: status.slot,
+                    failed: status.err.is_some(),
+                });
+            }
+            if len < SIGNATURES_LIMIT {
+                break;
+            }
+            before = statuses.last().map(|status| status.signature);
+        }
+        // the RPC pages newest first, callers get them oldest first
+        statuses.reverse();
+        Ok(statuses)
+    }
+
+    async fn transaction(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<ProgramTransaction, RelayerError> {
+        let invalid =
+            |what: &str| RelayerError::InvalidTransaction(format!("{}: {}", signature, what));
+        let confirmed = self
+            .get_transaction_with_config(
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(self.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .map_err(ClientError::from)?;
+        let transaction = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| invalid("can't decode"))?;
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or_else(|| invalid("no status meta"))?;
+
+        // static keys, then the writable and readonly keys of lookup tables
+        let message = &transaction.message;
+        let mut keys = message.static_account_keys().to_vec();
+        if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+            for key in loaded.writable.iter().chain(&loaded.readonly) {
+                keys.push(Pubkey::from_str(key).map_err(|_| invalid("loaded address"))?);
+            }
+        }
+        let meta_of = |index: u8| -> Result<AccountMeta, RelayerError> {
+            let index = index as usize;
+            let pubkey = *keys.get(index).ok_or_else(|| invalid("account index"))?;
+            Ok(AccountMeta {
+                pubkey,
+                is_signer: message.is_signer(index),
+                is_writable: message.is_maybe_writable(index),
+            })
+        };
+        let resolve = |program_id_index: u8, accounts: &[u8], data: Vec<u8>| {
+            Ok::<_, RelayerError>(Instruction {
+                program_id: meta_of(program_id_index)?.pubkey,
+                accounts: accounts
+                    .iter()
+                    .map(|index| meta_of(*index))
+                    .collect::<Result<_, RelayerError>>()?,
+                data,
+            })
+        };
+
+        let inner = match meta.inner_instructions {
+            OptionSerializer::Some(inner) => inner,
+            _ => vec![],
+        };
+        let mut instructions = vec![];
+        for (index, instruction) in message.instructions().iter().enumerate() {
+            instructions.push(resolve(
+                instruction.program_id_index,
+                &instruction.accounts,
+                instruction.data.clone(),
+            )?);
+            for inner in inner.iter().filter(|inner| inner.index as usize == index) {
+                for instruction in &inner.instructions {
+                    let instruction = match instruction {
+                        UiInstruction::Compiled(instruction) => instruction,
+                        UiInstruction::Parsed(_) => return Err(invalid("parsed instruction")),
+                    };
+                    let data = bs58::decode(&instruction.data)
+                        .into_vec()
+                        .map_err(|_| invalid("inner instruction data"))?;
+                    instructions.push(resolve(
+                        instruction.program_id_index,
+                        &instruction.accounts,
+                        data,
+                    )?);
+                }
+            }
+        }
+
+        Ok(ProgramTransaction {
+            signature: *signature,
+            slot: confirmed.slot,
+            instructions,
+        })
+    }
+}
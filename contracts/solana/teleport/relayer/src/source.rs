@@ -0,0 +1,154 @@
+//! Sources of Flow events
+
+use {
+    crate::{
+        error::RelayerError,
+        event::{BlockEvents, FlowEvent},
+    },
+    async_trait::async_trait,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    serde_json::{json, Value},
+    std::{fs, path::PathBuf},
+};
+
+/// Flow events from a block height on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Batch {
+    pub events: Vec<FlowEvent>,
+    /// The last block height the batch covers, `None` if no block past the
+    /// start height is there yet.
+    pub end_height: Option<u64>,
+}
+
+#[async_trait]
+pub trait EventSource {
+    /// Events of the relayed type from `start_height` on, in order.
+    async fn fetch(&mut self, start_height: u64) -> Result<Batch, RelayerError>;
+}
+
+/// A file in the `/v1/events` response format of the Flow REST API, read
+/// again on every fetch so events can be appended.
+pub struct FileSource {
+    path: PathBuf,
+    event_type: String,
+}
+
+impl FileSource {
+    pub fn new(path: PathBuf, event_type: String) -> Self {
+        Self { path, event_type }
+    }
+}
+
+#[async_trait]
+impl EventSource for FileSource {
+    async fn fetch(&mut self, start_height: u64) -> Result<Batch, RelayerError> {
+        let data = fs::read(&self.path)
+            .map_err(|e| RelayerError::Source(format!("{}: {}", self.path.display(), e)))?;
+        let blocks: Vec<BlockEvents> = serde_json::from_slice(&data)
+            .map_err(|e| RelayerError::Source(format!("{}: {}", self.path.display(), e)))?;
+
+        let blocks: Vec<BlockEvents> = blocks
+            .into_iter()
+            .filter(|block| block.block_height >= start_height)
+            .collect();
+        let end_height = blocks.iter().map(|block| block.block_height).max();
+        let events = FlowEvent::from_blocks(blocks)?
+            .into_iter()
+            .filter(|event| event.event_type == self.event_type)
+            .collect();
+        Ok(Batch { events, end_height })
+    }
+}
+
+/// A Flow access node REST API, or a stand-in serving the same endpoints.
+pub struct HttpSource {
+    client: reqwest::Client,
+    url: String,
+    event_type: String,
+    /// Most blocks a single `/v1/events` query may cover.
+    max_range: u64,
+}
+
+impl HttpSource {
+    /// Access nodes cap an events query at 250 blocks.
+    pub const MAX_RANGE: u64 = 250;
+
+    pub fn new(url: &str, event_type: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            event_type,
+            max_range: Self::MAX_RANGE,
+        }
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Vec<u8>, RelayerError> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Runs a Cadence script without arguments, its result in JSON-CDC.
+    pub async fn execute_script(&self, script: &str) -> Result<Value, RelayerError> {
+        let response = self
+            .client
+            .post(format!("{}/v1/scripts", self.url))
+            .json(&json!({ "script": STANDARD.encode(script), "arguments": [] }))
+            .send()
+            .await?
+            .error_for_status()?;
+        // a JSON string of the base64 encoded result
+        let result: String = serde_json::from_slice(&response.bytes().await?)
+            .map_err(|e| RelayerError::Source(format!("script result: {}", e)))?;
+        let result = STANDARD
+            .decode(result)
+            .map_err(|e| RelayerError::Source(format!("script result: {}", e)))?;
+        serde_json::from_slice(&result)
+            .map_err(|e| RelayerError::Source(format!("script result: {}", e)))
+    }
+
+    async fn sealed_height(&self) -> Result<u64, RelayerError> {
+        let data = self
+            .get("/v1/blocks", &[("height", "sealed".to_string())])
+            .await?;
+        let blocks: Value = serde_json::from_slice(&data)
+            .map_err(|e| RelayerError::Source(format!("sealed block: {}", e)))?;
+        blocks[0]["header"]["height"]
+            .as_str()
+            .and_then(|height| height.parse().ok())
+            .ok_or_else(|| RelayerError::Source("sealed block without a height".to_string()))
+    }
+}
+
+#[async_trait]
+impl EventSource for HttpSource {
+    async fn fetch(&mut self, start_height: u64) -> Result<Batch, RelayerError> {
+        let sealed_height = self.sealed_height().await?;
+        if sealed_height < start_height {
+            return Ok(Batch::default());
+        }
+        let end_height = sealed_height.min(start_height + self.max_range - 1);
+
+        let data = self
+            .get(
+                "/v1/events",
+                &[
+                    ("type", self.event_type.clone()),
+                    ("start_height", start_height.to_string()),
+                    ("end_height", end_height.to_string()),
+                ],
+            )
+            .await?;
+        let blocks: Vec<BlockEvents> = serde_json::from_slice(&data)
+            .map_err(|e| RelayerError::Source(format!("events: {}", e)))?;
+        Ok(Batch {
+            events: FlowEvent::from_blocks(blocks)?,
+            end_height: Some(end_height),
+        })
+    }
+}
@@ -0,0 +1,231 @@
+//! Watches `TeleportIn` and produces the Flow unlock arguments
+
+use {
+    crate::{
+        checkpoint::Cursor,
+        custody::format_ufix64,
+        error::RelayerError,
+        solana::{ProgramTransaction, TransactionSource},
+    },
+    blt_teleport::instruction::{Chain, TeleportInstruction},
+    borsh::BorshDeserialize,
+    serde::Serialize,
+    serde_json::{json, Value},
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    std::{path::PathBuf, str::FromStr},
+};
+
+/// A `TeleportIn` instruction of a transaction, top level or inner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TeleportIn {
+    /// In Flow UFix64 units, 8 decimals.
+    pub amount: u64,
+    pub chain: Chain,
+    pub to: Vec<u8>,
+    pub payload: Option<Vec<u8>>,
+    pub nonce: u64,
+    pub from: Pubkey,
+    pub from_auth: Pubkey,
+}
+
+impl TeleportIn {
+    /// The `TeleportIn` instructions of `transaction` sent to `program_id`,
+    /// in execution order.
+    pub fn decode_all(transaction: &ProgramTransaction, program_id: &Pubkey) -> Vec<Self> {
+        transaction
+            .instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == *program_id)
+            .filter_map(|instruction| {
+                match TeleportInstruction::try_from_slice(&instruction.data).ok()? {
+                    TeleportInstruction::TeleportIn {
+                        amount,
+                        to,
+                        chain,
+                        payload,
+                        nonce,
+                    } => Some(Self {
+                        amount,
+                        chain,
+                        to,
+                        payload,
+                        nonce,
+                        // account order of `process_teleport_in`
+                        from: instruction.accounts.get(5)?.pubkey,
+                        from_auth: instruction.accounts.get(7)?.pubkey,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Arguments of `unlockTokensSolana.cdc`,
+/// `(amount: UFix64, target: Address, from: String, hash: String)`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct UnlockArguments {
+    pub amount: String,
+    pub target: String,
+    /// Hex of the token owner, 32 bytes.
+    pub from: String,
+    /// Hex of the transaction signature, 128 characters.
+    pub hash: String,
+}
+
+impl UnlockArguments {
+    /// `None` if `to` isn't a Flow address.
+    pub fn new(teleport_in: &TeleportIn, signature: &Signature) -> Option<Self> {
+        if teleport_in.chain != Chain::Flow || teleport_in.to.len() != 8 {
+            return None;
+        }
+        Some(Self {
+            amount: format_ufix64(teleport_in.amount),
+            target: format!("0x{}", hex::encode(&teleport_in.to)),
+            from: hex::encode(teleport_in.from_auth),
+            hash: hex::encode(signature),
+        })
+    }
+
+    /// The arguments in JSON-CDC, as `flow transactions send --args-json`
+    /// takes them.
+    pub fn json_cdc(&self) -> Value {
+        json!([
+            { "type": "UFix64", "value": self.amount },
+            { "type": "Address", "value": self.target },
+            { "type": "String", "value": self.from },
+            { "type": "String", "value": self.hash },
+        ])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum WatchOutcome {
+    Unlock {
+        arguments: UnlockArguments,
+        args_json: Value,
+    },
+    /// The destination isn't Flow, `to` in hex.
+    OtherChain { chain: String, to: String },
+    /// The teleport in can't be unlocked on Flow, left to the operator.
+    Failed { error: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WatchReport {
+    pub signature: String,
+    pub slot: u64,
+    pub from_auth: String,
+    pub nonce: u64,
+    #[serde(flatten)]
+    pub outcome: WatchOutcome,
+}
+
+pub struct Watcher<S> {
+    source: S,
+    program_id: Pubkey,
+    /// Slots a transaction waits behind the tip before it's handled.
+    finality_depth: u64,
+    cursor_path: PathBuf,
+    cursor: Cursor,
+}
+
+impl<S: TransactionSource + Send> Watcher<S> {
+    /// Resumes from the cursor at `cursor_path`, after `start` if there's
+    /// none.
+    pub fn new(
+        source: S,
+        program_id: Pubkey,
+        finality_depth: u64,
+        cursor_path: PathBuf,
+        start: Option<Signature>,
+    ) -> Result<Self, RelayerError> {
+        let cursor = Cursor::load(&cursor_path)?.unwrap_or_else(|| Cursor {
+            signature: start.map(|signature| signature.to_string()),
+            slot: 0,
+        });
+        Ok(Self {
+            source,
+            program_id,
+            finality_depth,
+            cursor_path,
+            cursor,
+        })
+    }
+
+    pub fn cursor(&self) -> &Cursor {
+        &self.cursor
+    }
+
+    /// Passes the teleport ins of the transactions after the cursor that are
+    /// `finality_depth` slots deep to `emit`, saving the cursor after each
+    /// transaction. A transaction is emitted before the cursor passes it, so
+    /// an error drops no report.
+    pub async fn poll(&mut self, mut emit: impl FnMut(WatchReport)) -> Result<(), RelayerError> {
+        let until = self
+            .cursor
+            .signature
+            .as_deref()
+            .map(Signature::from_str)
+            .transpose()
+            .map_err(|e| RelayerError::Checkpoint(format!("cursor signature: {}", e)))?;
+        let tip = self.source.slot().await?;
+        let statuses = self
+            .source
+            .signatures(&self.program_id, until.as_ref())
+            .await?;
+
+        for status in statuses {
+            if tip.saturating_sub(status.slot) < self.finality_depth {
+                break;
+            }
+            if !status.failed {
+                let transaction = self.source.transaction(&status.signature).await?;
+                self.watch(&transaction).into_iter().for_each(&mut emit);
+            }
+            self.cursor = Cursor {
+                signature: Some(status.signature.to_string()),
+                slot: status.slot,
+            };
+            self.cursor.save(&self.cursor_path)?;
+        }
+        Ok(())
+    }
+
+    fn watch(&self, transaction: &ProgramTransaction) -> Vec<WatchReport> {
+        let mut unlocked = false;
+        TeleportIn::decode_all(transaction, &self.program_id)
+            .into_iter()
+            .map(|teleport_in| {
+                let outcome = match UnlockArguments::new(&teleport_in, &transaction.signature) {
+                    // Flow keys unlocks by the tx hash
+                    Some(_) if unlocked => WatchOutcome::Failed {
+                        error: "another TeleportIn of the transaction is unlocked".to_string(),
+                    },
+                    Some(arguments) => {
+                        unlocked = true;
+                        WatchOutcome::Unlock {
+                            args_json: arguments.json_cdc(),
+                            arguments,
+                        }
+                    }
+                    None if teleport_in.chain == Chain::Flow => WatchOutcome::Failed {
+                        error: format!("to {} isn't a Flow address", hex::encode(&teleport_in.to)),
+                    },
+                    None => WatchOutcome::OtherChain {
+                        chain: format!("{:?}", teleport_in.chain),
+                        to: hex::encode(&teleport_in.to),
+                    },
+                };
+                WatchReport {
+                    signature: transaction.signature.to_string(),
+                    slot: transaction.slot,
+                    from_auth: teleport_in.from_auth.to_string(),
+                    nonce: teleport_in.nonce,
+                    outcome,
+                }
+            })
+            .collect()
+    }
+}
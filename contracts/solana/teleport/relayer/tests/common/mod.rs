@@ -0,0 +1,311 @@
+//! Harness shared by the integration tests: the program with a mock
+//! multisig wallet, the BLT mint and a transaction source.
+
+#![allow(dead_code)]
+
+use {
+    async_trait::async_trait,
+    blt_teleport::instruction::Chain,
+    blt_teleport_client::TeleportClient,
+    blt_teleport_relayer::{ProgramTransaction, RelayerError, SignatureStatus, TransactionSource},
+    serde_json::Value,
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+        program_option::COption,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        transaction::Transaction,
+    },
+    std::{path::PathBuf, str::FromStr},
+};
+
+pub fn get_owner() -> Keypair {
+    Keypair::from_bytes(&[
+        216, 77, 18, 252, 166, 244, 245, 106, 13, 111, 107, 26, 108, 43, 230, 245, 113, 207, 205,
+        116, 14, 52, 64, 196, 192, 63, 41, 220, 146, 82, 66, 53, 161, 111, 219, 59, 147, 221, 7,
+        127, 88, 42, 141, 30, 211, 69, 198, 108, 142, 249, 183, 249, 92, 127, 241, 91, 118, 190,
+        46, 20, 186, 220, 132, 23,
+    ])
+    .unwrap()
+}
+
+pub fn multisig_program_id() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::MULTISIG_PROGRAM_KEY).unwrap()
+}
+
+// Stand-in for the multisig wallet program, it runs the invoke instruction
+// signed by the PDA of the wallet without checking the wallet signers.
+pub fn process_mock_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    input: &[u8],
+) -> ProgramResult {
+    assert_eq!(input[0], 3);
+    let program_info = &accounts[input[1] as usize];
+    let total = u16::from_le_bytes([input[2], input[3]]) as usize;
+
+    let mut metas = Vec::with_capacity(total);
+    let mut infos = Vec::with_capacity(total + 1);
+    for pair in input[4..4 + total * 2].chunks_exact(2) {
+        let info = &accounts[pair[0] as usize];
+        metas.push(AccountMeta {
+            pubkey: *info.key,
+            is_signer: pair[1] & 2 != 0,
+            is_writable: pair[1] & 1 != 0,
+        });
+        infos.push(info.clone());
+    }
+    infos.push(program_info.clone());
+
+    let wallet_key = accounts[0].key;
+    let (_, bump) = Pubkey::find_program_address(&[wallet_key.as_ref()], program_id);
+    invoke_signed(
+        &Instruction {
+            program_id: *program_info.key,
+            accounts: metas,
+            data: input[4 + total * 2..].to_vec(),
+        },
+        &infos,
+        &[&[wallet_key.as_ref(), &[bump]]],
+    )
+}
+
+pub fn blt_mint() -> Pubkey {
+    Pubkey::from_str(blt_teleport::state::BLT_MINT_KEY).unwrap()
+}
+
+pub fn add_packable_account<T: Pack>(program_test: &mut ProgramTest, address: Pubkey, state: &T) {
+    let mut data = vec![0; T::get_packed_len()];
+    T::pack_into_slice(state, &mut data);
+    program_test.add_account(
+        address,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: spl_token::id(),
+            ..Account::default()
+        },
+    );
+}
+
+pub fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new(
+        "blt_teleport",
+        blt_teleport::id(),
+        processor!(blt_teleport::processor::Processor::process_instruction),
+    );
+    program_test.add_program(
+        "multisig",
+        multisig_program_id(),
+        processor!(process_mock_multisig),
+    );
+    program_test
+}
+
+/// Adds the BLT mint owned by a mock wallet, returning the wallet and its
+/// PDA, the mint authority.
+pub fn add_blt_mint(program_test: &mut ProgramTest, supply: u64) -> (Pubkey, Pubkey) {
+    let wallet = Pubkey::new_unique();
+    let (mint_auth, _) = Pubkey::find_program_address(&[wallet.as_ref()], &multisig_program_id());
+    add_packable_account(
+        program_test,
+        blt_mint(),
+        &spl_token::state::Mint {
+            mint_authority: COption::Some(mint_auth),
+            supply,
+            decimals: 8,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        },
+    );
+    (wallet, mint_auth)
+}
+
+pub struct Setup {
+    pub config: Pubkey,
+    pub wallet: Pubkey,
+    pub mint_auth: Pubkey,
+    pub from: Pubkey,
+    pub from_auth: Keypair,
+}
+
+impl Setup {
+    pub fn teleport_in(
+        &self,
+        payer: &Keypair,
+        nonce: u64,
+        amount: u64,
+        chain: Chain,
+        to: &[u8],
+    ) -> Instruction {
+        blt_teleport::instruction::teleport_in(
+            &blt_teleport::id(),
+            &self.config,
+            &self.wallet,
+            &self.mint_auth,
+            &self.from,
+            &blt_mint(),
+            &spl_token::id(),
+            &self.from_auth.pubkey(),
+            &payer.pubkey(),
+            nonce,
+            amount,
+            chain,
+            to,
+            None,
+        )
+        .unwrap()
+    }
+}
+
+// Config with Bsc enabled, the BLT mint owned by the mock wallet with a
+// supply of 1000 and a token account holding it to teleport in from.
+pub async fn start() -> (TeleportClient<BanksClient>, Setup) {
+    let mut program_test = program_test();
+    let balance = 100_000_000_000;
+    let (wallet, mint_auth) = add_blt_mint(&mut program_test, balance);
+    let from_auth = Keypair::new();
+    let from = Pubkey::new_unique();
+    add_packable_account(
+        &mut program_test,
+        from,
+        &spl_token::state::Account {
+            mint: blt_mint(),
+            owner: from_auth.pubkey(),
+            amount: balance,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+    );
+
+    let (banks_client, payer, _) = program_test.start().await;
+    let mut client = TeleportClient::new(banks_client, blt_teleport::id(), payer);
+    let owner = get_owner();
+    let config = Keypair::new();
+    client.init_config(&owner, &config).await.unwrap();
+    let instruction = blt_teleport::instruction::set_chain_enabled(
+        &blt_teleport::id(),
+        &owner.pubkey(),
+        &config.pubkey(),
+        Chain::Bsc,
+        true,
+    )
+    .unwrap();
+    client.send(&[instruction], &[&owner]).await.unwrap();
+
+    let setup = Setup {
+        config: config.pubkey(),
+        wallet,
+        mint_auth,
+        from,
+        from_auth,
+    };
+    (client, setup)
+}
+
+/// Transactions of the program at their slots, as an RPC node would list
+/// them.
+#[derive(Default)]
+pub struct MockSource {
+    pub tip: u64,
+    pub transactions: Vec<(SignatureStatus, ProgramTransaction)>,
+    /// Listed transactions the node fails to return.
+    pub unavailable: Vec<Signature>,
+}
+
+impl MockSource {
+    pub fn push(&mut self, transaction: &Transaction, slot: u64, failed: bool) {
+        let message = &transaction.message;
+        let instructions = message
+            .instructions
+            .iter()
+            .map(|instruction| Instruction {
+                program_id: message.account_keys[instruction.program_id_index as usize],
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|index| {
+                        let index = *index as usize;
+                        AccountMeta {
+                            pubkey: message.account_keys[index],
+                            is_signer: message.is_signer(index),
+                            is_writable: message.is_writable(index),
+                        }
+                    })
+                    .collect(),
+                data: instruction.data.clone(),
+            })
+            .collect();
+        let signature = transaction.signatures[0];
+        self.transactions.push((
+            SignatureStatus {
+                signature,
+                slot,
+                failed,
+            },
+            ProgramTransaction {
+                signature,
+                slot,
+                instructions,
+            },
+        ));
+    }
+}
+
+#[async_trait]
+impl TransactionSource for &mut MockSource {
+    async fn slot(&mut self) -> Result<u64, RelayerError> {
+        Ok(self.tip)
+    }
+
+    async fn signatures(
+        &mut self,
+        program_id: &Pubkey,
+        until: Option<&Signature>,
+    ) -> Result<Vec<SignatureStatus>, RelayerError> {
+        assert_eq!(program_id, &blt_teleport::id());
+        let start = until.map_or(0, |until| {
+            self.transactions
+                .iter()
+                .position(|(status, _)| status.signature == *until)
+                .unwrap()
+                + 1
+        });
+        Ok(self.transactions[start..]
+            .iter()
+            .map(|(status, _)| status.clone())
+            .collect())
+    }
+
+    async fn transaction(
+        &mut self,
+        signature: &Signature,
+    ) -> Result<ProgramTransaction, RelayerError> {
+        if self.unavailable.contains(signature) {
+            return Err(RelayerError::Source(format!("{} unavailable", signature)));
+        }
+        Ok(self
+            .transactions
+            .iter()
+            .find(|(status, _)| status.signature == *signature)
+            .unwrap()
+            .1
+            .clone())
+    }
+}
+
+// `Pubkey::new_unique` repeats across runs, a random key doesn't.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("blt-relayer-{}-{}", Keypair::new().pubkey(), name))
+}
+
+pub fn fixture(name: &str) -> Value {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
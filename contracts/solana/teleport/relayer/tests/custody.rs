@@ -0,0 +1,162 @@
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use {
+    blt_teleport::instruction::AddressType,
+    blt_teleport_relayer::{
+        custody::{format_ufix64, parse_ufix64},
+        CustodyEvent, FeeCollectedEvent, FeeType, LockedEvent, UnlockedEvent,
+    },
+    common::fixture,
+    serde_json::Value,
+    solana_sdk::{pubkey::Pubkey, signature::Signature},
+    std::{convert::TryInto, str::FromStr},
+};
+
+fn set_field(payload: &mut Value, name: &str, value: Value) {
+    let fields = payload["value"]["fields"].as_array_mut().unwrap();
+    let field = fields
+        .iter_mut()
+        .find(|field| field["name"] == name)
+        .unwrap();
+    field["value"] = value;
+}
+
+#[test]
+fn test_decode_locked() {
+    let locked = LockedEvent::decode(&fixture("locked.json")).unwrap();
+    assert_eq!(
+        locked,
+        LockedEvent {
+            amount: 125_050_000_000,
+            to: Pubkey::from_str("BLT2FgFauegpkQPXmT9dSKtHevX1dDNPgj4KtKFiDbPq").unwrap(),
+            to_address_type: AddressType::Sol,
+        }
+    );
+    assert_eq!(locked.base_units(8).unwrap(), 125_050_000_000);
+    assert_eq!(locked.base_units(6).unwrap(), 1_250_500_000);
+    assert_eq!(locked.base_units(9).unwrap(), 1_250_500_000_000);
+
+    let locked = LockedEvent::decode(&fixture("locked_spl.json")).unwrap();
+    assert_eq!(locked.amount, 1);
+    assert_eq!(locked.to_address_type, AddressType::Spl);
+    // dust below the mint precision
+    assert!(locked.base_units(6).is_err());
+    assert_eq!(locked.base_units(19).unwrap(), 100_000_000_000);
+}
+
+#[test]
+fn test_decode_unlocked_and_fee_collected() {
+    let payload = fixture("unlocked.json");
+    let tx_hash = payload["value"]["fields"][2]["value"]["value"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(tx_hash.len(), 128);
+    let unlocked = UnlockedEvent::decode(&payload).unwrap();
+    let tx_hash: [u8; 64] = hex::decode(tx_hash).unwrap().try_into().unwrap();
+    assert_eq!(
+        unlocked,
+        UnlockedEvent {
+            amount: 9_999_000_000,
+            from: Pubkey::from_str("Ad8pZMQCznufvH85mVhyUyuchhEvBRMMMSawJTPCBVZq").unwrap(),
+            tx_hash: Signature::from(tx_hash),
+        }
+    );
+    assert_eq!(unlocked.base_units(6).unwrap(), 99_990_000);
+
+    assert_eq!(
+        FeeCollectedEvent::decode(&fixture("fee_collected.json")).unwrap(),
+        FeeCollectedEvent {
+            amount: 1_000_000,
+            fee_type: FeeType::Unlock,
+        }
+    );
+
+    for (name, expected) in [
+        ("locked.json", "Locked"),
+        ("unlocked.json", "Unlocked"),
+        ("fee_collected.json", "FeeCollected"),
+    ] {
+        let event = CustodyEvent::decode(&fixture(name)).unwrap();
+        let decoded = match event {
+            CustodyEvent::Locked(_) => "Locked",
+            CustodyEvent::Unlocked(_) => "Unlocked",
+            CustodyEvent::FeeCollected(_) => "FeeCollected",
+        };
+        assert_eq!(decoded, expected);
+    }
+}
+
+#[test]
+fn test_decode_invalid_events() {
+    // another event type
+    assert!(LockedEvent::decode(&fixture("unlocked.json")).is_err());
+
+    // another custody contract
+    let mut payload = fixture("locked.json");
+    payload["value"]["id"] = "A.0ac14a822e54cc4e.TeleportCustodyBSC.Locked".into();
+    assert!(CustodyEvent::decode(&payload).is_err());
+
+    let mut payload = fixture("locked.json");
+    set_field(
+        &mut payload,
+        "toAddressType",
+        serde_json::json!({ "type": "String", "value": "FLOW" }),
+    );
+    assert!(LockedEvent::decode(&payload).is_err());
+
+    // a Flow address isn't 32 bytes
+    let mut payload = fixture("locked.json");
+    let to: Vec<Value> = (0..8)
+        .map(|byte| serde_json::json!({ "type": "UInt8", "value": byte.to_string() }))
+        .collect();
+    set_field(
+        &mut payload,
+        "to",
+        serde_json::json!({ "type": "Array", "value": to }),
+    );
+    assert!(LockedEvent::decode(&payload).is_err());
+
+    for amount in ["1.000000001", "-1.0", "1e8", "184467440737.09551616"] {
+        let mut payload = fixture("locked.json");
+        set_field(
+            &mut payload,
+            "amount",
+            serde_json::json!({ "type": "UFix64", "value": amount }),
+        );
+        assert!(LockedEvent::decode(&payload).is_err(), "{}", amount);
+    }
+
+    let mut payload = fixture("unlocked.json");
+    set_field(
+        &mut payload,
+        "txHash",
+        serde_json::json!({ "type": "String", "value": "ab".repeat(32) }),
+    );
+    assert!(UnlockedEvent::decode(&payload).is_err());
+
+    let mut payload = fixture("fee_collected.json");
+    set_field(
+        &mut payload,
+        "type",
+        serde_json::json!({ "type": "UInt8", "value": "2" }),
+    );
+    assert!(FeeCollectedEvent::decode(&payload).is_err());
+}
+
+#[test]
+fn test_ufix64() {
+    assert_eq!(parse_ufix64("0.00000001"), Some(1));
+    assert_eq!(parse_ufix64("184467440737.09551615"), Some(u64::MAX));
+    assert_eq!(parse_ufix64("1.5"), Some(150_000_000));
+    assert_eq!(format_ufix64(1), "0.00000001");
+    assert_eq!(format_ufix64(u64::MAX), "184467440737.09551615");
+    let locked = LockedEvent {
+        amount: u64::MAX,
+        to: Pubkey::new_unique(),
+        to_address_type: AddressType::Sol,
+    };
+    assert!(locked.base_units(9).is_err());
+}
@@ -0,0 +1,468 @@
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use {
+    base64::{engine::general_purpose::STANDARD, Engine},
+    blt_teleport::instruction::{AddressType, Chain},
+    blt_teleport_relayer::{
+        reconcile, FileSource, FlowLedger, FlowLock, Mismatch, SolanaLedger, SolanaTeleportIn,
+        SolanaTeleportOut, UnlockedEvent, Unmatched,
+    },
+    common::{blt_mint, fixture, get_owner, start, temp_path, MockSource},
+    serde_json::{json, Value},
+    solana_sdk::{
+        hash::Hash,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        transaction::Transaction,
+    },
+    std::path::Path,
+};
+
+const CUSTODY: &str = "A.0ac14a822e54cc4e.TeleportCustodySolana";
+
+const FLOW_ADDRESS: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+// An `Unlocked` event of the fixture with another amount and tx hash.
+fn unlocked_payload(amount: &str, tx_hash: &Signature) -> Value {
+    let mut payload = fixture("unlocked.json");
+    payload["value"]["fields"][0]["value"]["value"] = amount.into();
+    payload["value"]["fields"][2]["value"]["value"] = hex::encode(tx_hash).into();
+    payload
+}
+
+fn block(height: u64, events: &[(u8, &str, Value)]) -> Value {
+    let events: Vec<Value> = events
+        .iter()
+        .enumerate()
+        .map(|(index, (seed, name, payload))| {
+            json!({
+                "type": format!("{}.{}", CUSTODY, name),
+                "transaction_id": hex::encode([*seed; 32]),
+                "transaction_index": "0",
+                "event_index": index.to_string(),
+                "payload": STANDARD.encode(payload.to_string()),
+            })
+        })
+        .collect();
+    json!({
+        "block_id": hex::encode([height as u8; 32]),
+        "block_height": height.to_string(),
+        "block_timestamp": "2024-01-01T00:00:00Z",
+        "events": events,
+    })
+}
+
+async fn collect_flow(path: &Path, lock_vault_balance: u64, start_height: u64) -> FlowLedger {
+    FlowLedger::collect(
+        lock_vault_balance,
+        &mut FileSource::new(path.to_path_buf(), format!("{}.Locked", CUSTODY)),
+        &mut FileSource::new(path.to_path_buf(), format!("{}.Unlocked", CUSTODY)),
+        start_height,
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_reconcile_ledgers() {
+    let (mut client, setup) = start().await;
+    let payer = client.payer().insecure_clone();
+    let mut source = MockSource::default();
+
+    let unlocked = client
+        .transaction(
+            &[setup.teleport_in(&payer, 0, 150_000_000, Chain::Flow, &FLOW_ADDRESS)],
+            &[&payer, &setup.from_auth],
+        )
+        .await
+        .unwrap();
+    client.broadcast(&unlocked).await.unwrap();
+    source.push(&unlocked, 1, false);
+    let pending = client
+        .transaction(
+            &[setup.teleport_in(&payer, 1, 50_000_000, Chain::Flow, &FLOW_ADDRESS)],
+            &[&payer, &setup.from_auth],
+        )
+        .await
+        .unwrap();
+    client.broadcast(&pending).await.unwrap();
+    source.push(&pending, 2, false);
+    // never unlocked on Flow, it doesn't run so the supply stays
+    let other_chain = Transaction::new_signed_with_payer(
+        &[setup.teleport_in(&payer, 2, 20_000_000, Chain::Bsc, &[9; 20])],
+        Some(&payer.pubkey()),
+        &[&payer, &setup.from_auth],
+        Hash::default(),
+    );
+    source.push(&other_chain, 3, false);
+
+    // a lock not teleported out yet, the unlock of the first teleport in and
+    // one of a transaction the program never saw
+    let stray = Signature::from([7; 64]);
+    let events_path = temp_path("events.json");
+    let blocks = json!([
+        block(10, &[(1, "Locked", fixture("locked.json"))]),
+        block(
+            11,
+            &[(
+                2,
+                "Unlocked",
+                unlocked_payload("1.5", &unlocked.signatures[0])
+            )]
+        ),
+        block(12, &[(3, "Unlocked", unlocked_payload("3.0", &stray))]),
+    ]);
+    std::fs::write(&events_path, blocks.to_string()).unwrap();
+
+    // 998 left on Solana, 1250.5 locked and 0.5 to unlock
+    let lock_vault_balance = 224_900_000_000;
+    let flow = collect_flow(&events_path, lock_vault_balance, 0).await;
+    assert_eq!(
+        flow.locks,
+        vec![FlowLock {
+            tx_hash: [1; 32],
+            amount: 125_050_000_000,
+        }]
+    );
+    assert_eq!(flow.unlocks.len(), 2);
+    let solana = SolanaLedger::collect(
+        &mut client,
+        &mut &mut source,
+        &blt_mint(),
+        &flow.locks,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(solana.supply, 99_800_000_000);
+    assert!(solana.teleport_outs.is_empty());
+    assert_eq!(
+        solana.teleport_ins,
+        vec![
+            SolanaTeleportIn {
+                signature: unlocked.signatures[0],
+                amount: 150_000_000,
+                refunded: false,
+            },
+            SolanaTeleportIn {
+                signature: pending.signatures[0],
+                amount: 50_000_000,
+                refunded: false,
+            },
+        ]
+    );
+
+    let reconciliation = reconcile(&flow, &solana);
+    assert!(reconciliation.balanced);
+    assert!(!reconciliation.is_consistent());
+    assert_eq!(
+        serde_json::to_value(&reconciliation).unwrap(),
+        json!({
+            "lock_vault_balance": "2249.00000000",
+            "supply": "998.00000000",
+            "pending_teleport_outs": "1250.50000000",
+            "pending_unlocks": "0.50000000",
+            "cancelled_teleport_outs": "0.00000000",
+            "burned_outside": "0.00000000",
+            "balanced": true,
+            "unmatched": [
+                {
+                    "tx_hash": hex::encode([1; 32]),
+                    "mismatch": "pending_teleport_out",
+                    "amount": "1250.50000000",
+                },
+                {
+                    "tx_hash": hex::encode(pending.signatures[0]),
+                    "mismatch": "pending_unlock",
+                    "amount": "0.50000000",
+                },
+                {
+                    "tx_hash": hex::encode(stray),
+                    "mismatch": "unlocked_without_teleport_in",
+                    "amount": "3.00000000",
+                },
+            ],
+        })
+    );
+
+    // without the stray unlock only transfers in flight are left
+    let flow = FlowLedger {
+        unlocks: flow.unlocks[..1].to_vec(),
+        ..flow
+    };
+    assert!(reconcile(&flow, &solana).is_consistent());
+
+    // a holder burned outside the bridge, the lock vault keeps the amount
+    let reconciliation = reconcile(
+        &FlowLedger {
+            lock_vault_balance: lock_vault_balance + 1,
+            ..flow.clone()
+        },
+        &solana,
+    );
+    assert_eq!(reconciliation.burned_outside, 1);
+    assert!(reconciliation.is_consistent());
+
+    let flow = FlowLedger {
+        lock_vault_balance: lock_vault_balance - 1,
+        ..flow
+    };
+    let reconciliation = reconcile(&flow, &solana);
+    assert_eq!(reconciliation.burned_outside, 0);
+    assert!(!reconciliation.balanced);
+    assert!(!reconciliation.is_consistent());
+
+    // the events from a later height on
+    let flow = collect_flow(&events_path, 0, 11).await;
+    assert!(flow.locks.is_empty());
+    assert_eq!(flow.unlocks.len(), 2);
+}
+
+#[test]
+fn test_reconcile_mismatches() {
+    let unlock = |amount, tx_hash| UnlockedEvent {
+        amount,
+        from: Pubkey::new_unique(),
+        tx_hash: Signature::from([tx_hash; 64]),
+    };
+    let flow = FlowLedger {
+        lock_vault_balance: 1_000,
+        locks: vec![
+            FlowLock {
+                tx_hash: [1; 32],
+                amount: 100,
+            },
+            FlowLock {
+                tx_hash: [2; 32],
+                amount: 200,
+            },
+            // only its record is left, the amount is unknown
+            FlowLock {
+                tx_hash: [3; 32],
+                amount: 300,
+            },
+            // delayed by the program
+            FlowLock {
+                tx_hash: [4; 32],
+                amount: 400,
+            },
+            FlowLock {
+                tx_hash: [6; 32],
+                amount: 60,
+            },
+        ],
+        unlocks: vec![unlock(10, 1), unlock(20, 2)],
+    };
+    let solana = SolanaLedger {
+        supply: 600,
+        teleport_outs: vec![
+            SolanaTeleportOut {
+                tx_hash: [1; 32],
+                amount: Some(100),
+                pending: false,
+                cancelled: false,
+            },
+            SolanaTeleportOut {
+                tx_hash: [2; 32],
+                amount: Some(250),
+                pending: false,
+                cancelled: false,
+            },
+            SolanaTeleportOut {
+                tx_hash: [3; 32],
+                amount: None,
+                pending: false,
+                cancelled: false,
+            },
+            SolanaTeleportOut {
+                tx_hash: [4; 32],
+                amount: Some(400),
+                pending: true,
+                cancelled: false,
+            },
+            SolanaTeleportOut {
+                tx_hash: [5; 32],
+                amount: Some(50),
+                pending: false,
+                cancelled: false,
+            },
+            // delayed and cancelled
+            SolanaTeleportOut {
+                tx_hash: [6; 32],
+                amount: Some(60),
+                pending: false,
+                cancelled: true,
+            },
+        ],
+        teleport_ins: vec![
+            SolanaTeleportIn {
+                signature: Signature::from([1; 64]),
+                amount: 10,
+                refunded: true,
+            },
+            SolanaTeleportIn {
+                signature: Signature::from([2; 64]),
+                amount: 25,
+                refunded: false,
+            },
+            // burned, not unlocked yet
+            SolanaTeleportIn {
+                signature: Signature::from([3; 64]),
+                amount: 30,
+                refunded: false,
+            },
+        ],
+    };
+    let reconciliation = reconcile(&flow, &solana);
+    assert_eq!(reconciliation.pending_teleport_outs, 400);
+    assert_eq!(reconciliation.pending_unlocks, 30);
+    assert_eq!(reconciliation.cancelled_teleport_outs, 60);
+    // 600 + 400 + 30 + 60
+    assert!(!reconciliation.balanced);
+    let unmatched = |tx_hash: String, mismatch, amount| Unmatched {
+        tx_hash,
+        mismatch,
+        amount,
+    };
+    assert_eq!(
+        reconciliation.unmatched,
+        vec![
+            unmatched(hex::encode([2; 32]), Mismatch::AmountMismatch, 200),
+            unmatched(hex::encode([4; 32]), Mismatch::PendingTeleportOut, 400),
+            unmatched(hex::encode([6; 32]), Mismatch::CancelledTeleportOut, 60),
+            unmatched(hex::encode([5; 32]), Mismatch::MintedWithoutLock, 50),
+            unmatched(hex::encode([1; 64]), Mismatch::UnlockedRefund, 10),
+            unmatched(hex::encode([2; 64]), Mismatch::AmountMismatch, 25),
+            unmatched(hex::encode([3; 64]), Mismatch::PendingUnlock, 30),
+        ]
+    );
+
+    let solana = SolanaLedger {
+        teleport_ins: vec![SolanaTeleportIn {
+            signature: Signature::from([3; 64]),
+            amount: 30,
+            refunded: true,
+        }],
+        ..solana
+    };
+    // refunded and never unlocked, nothing to match
+    assert_eq!(reconcile(&flow, &solana).pending_unlocks, 0);
+}
+
+#[tokio::test]
+async fn test_reconcile_cancelled_teleport_out() {
+    let (mut client, setup) = start().await;
+    let payer = client.payer().insecure_clone();
+    let mut source = MockSource::default();
+
+    // the reconciler only reads the instructions of the transactions the
+    // node lists, these never run
+    let admin = Pubkey::new_unique();
+    let auth = Keypair::new();
+    let to = Pubkey::new_unique();
+    let cancelled_hash = [1; 32];
+    let minted_hash = [2; 32];
+    let teleport_out = |tx_hash: &[u8; 32], amount| {
+        blt_teleport::instruction::teleport_out(
+            &blt_teleport::id(),
+            &setup.config,
+            &admin,
+            &auth.pubkey(),
+            &setup.wallet,
+            &payer.pubkey(),
+            &blt_mint(),
+            &spl_token::id(),
+            &to,
+            AddressType::Sol,
+            &to,
+            &setup.mint_auth,
+            tx_hash,
+            amount,
+        )
+        .unwrap()
+    };
+    let owner = get_owner();
+    let teleport_outs = Transaction::new_signed_with_payer(
+        &[
+            teleport_out(&cancelled_hash, 100),
+            teleport_out(&minted_hash, 200),
+        ],
+        Some(&payer.pubkey()),
+        &[&payer, &auth],
+        Hash::default(),
+    );
+    source.push(&teleport_outs, 1, false);
+    let cancel = Transaction::new_signed_with_payer(
+        &[blt_teleport::instruction::cancel_teleport_out(
+            &blt_teleport::id(),
+            &owner.pubkey(),
+            &setup.config,
+            &admin,
+            &payer.pubkey(),
+            &cancelled_hash,
+        )
+        .unwrap()],
+        Some(&payer.pubkey()),
+        &[&payer, &owner],
+        Hash::default(),
+    );
+    source.push(&cancel, 2, false);
+
+    // the transactions never ran, the supply is the one of the setup
+    let flow = FlowLedger {
+        lock_vault_balance: 100_000_000_100,
+        locks: vec![
+            FlowLock {
+                tx_hash: cancelled_hash,
+                amount: 100,
+            },
+            FlowLock {
+                tx_hash: minted_hash,
+                amount: 200,
+            },
+        ],
+        unlocks: vec![],
+    };
+    let solana = SolanaLedger::collect(
+        &mut client,
+        &mut &mut source,
+        &blt_mint(),
+        &flow.locks,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        solana.teleport_outs,
+        vec![
+            SolanaTeleportOut {
+                tx_hash: cancelled_hash,
+                amount: Some(100),
+                pending: false,
+                cancelled: true,
+            },
+            SolanaTeleportOut {
+                tx_hash: minted_hash,
+                amount: Some(200),
+                pending: false,
+                cancelled: false,
+            },
+        ]
+    );
+
+    // held by the lock vault, yet not consistent until refunded on Flow
+    let reconciliation = reconcile(&flow, &solana);
+    assert_eq!(reconciliation.cancelled_teleport_outs, 100);
+    assert!(reconciliation.balanced);
+    assert!(!reconciliation.is_consistent());
+    assert_eq!(
+        reconciliation.unmatched,
+        vec![Unmatched {
+            tx_hash: hex::encode(cancelled_hash),
+            mismatch: Mismatch::CancelledTeleportOut,
+            amount: 100,
+        }]
+    );
+}
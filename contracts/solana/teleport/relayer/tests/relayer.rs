@@ -0,0 +1,563 @@
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use {
+    async_trait::async_trait,
+    base64::{engine::general_purpose::STANDARD, Engine},
+    blt_teleport_client::{ClientError, MintAuthority, Simulation, TeleportClient, TeleportRpc},
+    blt_teleport_relayer::{
+        Checkpoint, EventSource, FileSource, HttpSource, Outcome, Relayer, RelayerAdmin, Retry,
+    },
+    common::{add_blt_mint, add_packable_account, blt_mint, get_owner, program_test, temp_path},
+    serde_json::{json, Value},
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+        transaction::Transaction,
+    },
+    std::{
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    },
+};
+
+const EVENT_TYPE: &str = "A.0ac14a822e54cc4e.TeleportCustodySolana.Locked";
+
+struct Setup {
+    relayer_admin: RelayerAdmin,
+    mint_authority: MintAuthority,
+    // an SPL token account and a wallet to create the associated account of
+    token_account: Pubkey,
+    wallet: Pubkey,
+}
+
+// Config with an admin, the BLT mint owned by the mock wallet and the
+// recipients.
+async fn start() -> (BanksClient, Keypair, Setup) {
+    let mut program_test = program_test();
+    let (wallet, mint_auth) = add_blt_mint(&mut program_test, 0);
+    let token_account = Pubkey::new_unique();
+    add_packable_account(
+        &mut program_test,
+        token_account,
+        &spl_token::state::Account {
+            mint: blt_mint(),
+            owner: Pubkey::new_unique(),
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+    );
+
+    let (banks_client, payer, _) = program_test.start().await;
+    let mut client = TeleportClient::new(
+        banks_client.clone(),
+        blt_teleport::id(),
+        payer.insecure_clone(),
+    );
+    let owner = get_owner();
+    let config = Keypair::new();
+    client.init_config(&owner, &config).await.unwrap();
+    let admin = Keypair::new();
+    let auth = Keypair::new();
+    client
+        .init_admin(
+            &owner,
+            &config.pubkey(),
+            &admin,
+            &auth.pubkey(),
+            1_000_000_000_000,
+        )
+        .await
+        .unwrap();
+    client
+        .add_admin(&owner, &config.pubkey(), &admin.pubkey())
+        .await
+        .unwrap();
+
+    let setup = Setup {
+        relayer_admin: RelayerAdmin {
+            config: config.pubkey(),
+            admin: admin.pubkey(),
+            auth,
+        },
+        mint_authority: MintAuthority {
+            wallet,
+            mint_auth,
+            mint: blt_mint(),
+            token_program: spl_token::id(),
+        },
+        token_account,
+        wallet: Pubkey::new_unique(),
+    };
+    (banks_client, payer, setup)
+}
+
+fn retry() -> Retry {
+    Retry {
+        attempts: 3,
+        delay: Duration::from_millis(10),
+    }
+}
+
+fn new_relayer<R: TeleportRpc + Send, S: EventSource + Send>(
+    rpc: R,
+    payer: &Keypair,
+    setup: &Setup,
+    source: S,
+    checkpoint: PathBuf,
+    retry: Retry,
+) -> Relayer<R, S> {
+    let client = TeleportClient::new(rpc, blt_teleport::id(), payer.insecure_clone());
+    let admin = RelayerAdmin {
+        config: setup.relayer_admin.config,
+        admin: setup.relayer_admin.admin,
+        auth: setup.relayer_admin.auth.insecure_clone(),
+    };
+    Relayer::new(
+        client,
+        source,
+        admin,
+        setup.mint_authority.clone(),
+        retry,
+        checkpoint,
+        0,
+    )
+    .unwrap()
+}
+
+// JSON-CDC of a `Locked` event.
+fn locked_payload(amount: &str, to: &Pubkey, to_address_type: &str) -> String {
+    let to: Vec<Value> = to
+        .to_bytes()
+        .iter()
+        .map(|byte| json!({ "type": "UInt8", "value": byte.to_string() }))
+        .collect();
+    let payload = json!({
+        "type": "Event",
+        "value": {
+            "id": EVENT_TYPE,
+            "fields": [
+                { "name": "amount", "value": { "type": "UFix64", "value": amount } },
+                { "name": "to", "value": { "type": "Array", "value": to } },
+                { "name": "toAddressType", "value": { "type": "String", "value": to_address_type } },
+            ],
+        },
+    });
+    STANDARD.encode(payload.to_string())
+}
+
+// A block of `/v1/events`, the events as (tx id seed, tx index, event index,
+// payload).
+fn block(height: u64, events: &[(u8, u64, u64, String)]) -> Value {
+    let events: Vec<Value> = events
+        .iter()
+        .map(|(seed, transaction_index, event_index, payload)| {
+            json!({
+                "type": EVENT_TYPE,
+                "transaction_id": hex::encode([*seed; 32]),
+                "transaction_index": transaction_index.to_string(),
+                "event_index": event_index.to_string(),
+                "payload": payload,
+            })
+        })
+        .collect();
+    json!({
+        "block_id": hex::encode([height as u8; 32]),
+        "block_height": height.to_string(),
+        "block_timestamp": "2024-01-01T00:00:00Z",
+        "events": events,
+    })
+}
+
+async fn get_token_balance(banks_client: &mut BanksClient, token_account: &Pubkey) -> u64 {
+    let account = banks_client
+        .get_account(*token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+fn outcomes(reports: &[blt_teleport_relayer::Report]) -> Vec<&Outcome> {
+    reports.iter().map(|report| &report.outcome).collect()
+}
+
+fn is_relayed(outcome: &Outcome) -> bool {
+    matches!(outcome, Outcome::Relayed { .. })
+}
+
+#[tokio::test]
+async fn test_relay_events_file() {
+    let (mut banks_client, payer, setup) = start().await;
+    let events_path = temp_path("events.json");
+    let checkpoint_path = temp_path("checkpoint.json");
+
+    let blocks = json!([
+        block(
+            10,
+            &[(1, 0, 0, locked_payload("1.5", &setup.token_account, "SPL"))]
+        ),
+        block(
+            12,
+            &[
+                (2, 0, 1, locked_payload("2.00000001", &setup.wallet, "SOL")),
+                // a second Locked event of the same transaction
+                (2, 0, 3, locked_payload("1.0", &setup.token_account, "SPL")),
+                (
+                    3,
+                    1,
+                    0,
+                    locked_payload("1.000000001", &setup.token_account, "SPL")
+                ),
+            ],
+        ),
+    ]);
+    std::fs::write(&events_path, blocks.to_string()).unwrap();
+
+    let source = FileSource::new(events_path.clone(), EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        banks_client.clone(),
+        &payer,
+        &setup,
+        source,
+        checkpoint_path.clone(),
+        retry(),
+    );
+    let reports = relayer.poll().await.unwrap();
+    let relayed = outcomes(&reports);
+    assert_eq!(relayed.len(), 4);
+    assert!(is_relayed(relayed[0]));
+    assert!(is_relayed(relayed[1]));
+    assert!(matches!(relayed[2], Outcome::Failed { .. }));
+    // more than 8 decimals
+    assert!(matches!(relayed[3], Outcome::Failed { .. }));
+
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        150_000_000
+    );
+    let associated =
+        spl_associated_token_account::get_associated_token_address(&setup.wallet, &blt_mint());
+    assert_eq!(
+        get_token_balance(&mut banks_client, &associated).await,
+        200_000_001
+    );
+
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap().unwrap();
+    assert_eq!(checkpoint.next_height, 13);
+    assert_eq!(checkpoint.failures.len(), 2);
+    assert_eq!(&checkpoint, relayer.checkpoint());
+
+    // resumed from the checkpoint, nothing left
+    let source = FileSource::new(events_path.clone(), EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        banks_client.clone(),
+        &payer,
+        &setup,
+        source,
+        checkpoint_path.clone(),
+        retry(),
+    );
+    assert!(relayer.poll().await.unwrap().is_empty());
+
+    // without a checkpoint the records stop a replay
+    let source = FileSource::new(events_path.clone(), EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        banks_client.clone(),
+        &payer,
+        &setup,
+        source,
+        temp_path("checkpoint.json"),
+        retry(),
+    );
+    let reports = relayer.poll().await.unwrap();
+    let replayed = outcomes(&reports);
+    assert_eq!(replayed[0], &Outcome::AlreadyProcessed);
+    assert_eq!(replayed[1], &Outcome::AlreadyProcessed);
+    assert!(matches!(replayed[2], Outcome::Failed { .. }));
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        150_000_000
+    );
+}
+
+#[tokio::test]
+async fn test_relay_invalid_recipient() {
+    let (mut banks_client, payer, setup) = start().await;
+    let events_path = temp_path("events.json");
+    let checkpoint_path = temp_path("checkpoint.json");
+
+    let blocks = json!([
+        block(
+            10,
+            &[
+                // no account and an account that isn't a token account
+                (1, 0, 0, locked_payload("1.0", &Pubkey::new_unique(), "SPL")),
+                (2, 1, 0, locked_payload("1.0", &blt_mint(), "SPL")),
+                (3, 2, 0, locked_payload("1.5", &setup.token_account, "SPL")),
+            ]
+        ),
+        // above the allowance, which holds every event
+        block(
+            11,
+            &[(
+                4,
+                0,
+                0,
+                locked_payload("20000.0", &setup.token_account, "SPL")
+            )]
+        ),
+    ]);
+    std::fs::write(&events_path, blocks.to_string()).unwrap();
+
+    let source = FileSource::new(events_path.clone(), EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        banks_client.clone(),
+        &payer,
+        &setup,
+        source,
+        checkpoint_path.clone(),
+        retry(),
+    );
+    assert!(relayer.poll().await.is_err());
+
+    // the failures are recorded and the relayer moved past them
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap().unwrap();
+    assert_eq!(checkpoint.failures.len(), 2);
+    assert_eq!(checkpoint.failures[0].transaction_id, hex::encode([1; 32]));
+    assert_eq!(checkpoint.failures[1].transaction_id, hex::encode([2; 32]));
+    assert_eq!(checkpoint.position.unwrap().block_height, 10);
+    assert_eq!(checkpoint.next_height, 0);
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        150_000_000
+    );
+}
+
+/// Banks client whose sends fail, before or after the transaction lands.
+#[derive(Clone)]
+struct FlakyRpc {
+    inner: BanksClient,
+    drop_sends: Arc<AtomicU32>,
+    lose_confirmations: Arc<AtomicU32>,
+}
+
+fn take(counter: &AtomicU32) -> bool {
+    counter
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok()
+}
+
+#[async_trait]
+impl TeleportRpc for FlakyRpc {
+    async fn get_account(&mut self, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        TeleportRpc::get_account(&mut self.inner, address).await
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        TeleportRpc::get_latest_blockhash(&mut self.inner).await
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError> {
+        self.inner
+            .get_minimum_balance_for_rent_exemption(data_len)
+            .await
+    }
+
+    async fn send_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        if take(&self.drop_sends) {
+            return Err(ClientError::Rpc("connection refused".to_string()));
+        }
+        let signature = TeleportRpc::send_transaction(&mut self.inner, transaction).await?;
+        if take(&self.lose_confirmations) {
+            return Err(ClientError::Rpc("confirmation timed out".to_string()));
+        }
+        Ok(signature)
+    }
+
+    async fn simulate_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Simulation, ClientError> {
+        TeleportRpc::simulate_transaction(&mut self.inner, transaction).await
+    }
+}
+
+#[tokio::test]
+async fn test_relay_retries() {
+    let (mut banks_client, payer, setup) = start().await;
+    let events_path = temp_path("events.json");
+    let checkpoint_path = temp_path("checkpoint.json");
+    let rpc = FlakyRpc {
+        inner: banks_client.clone(),
+        drop_sends: Arc::new(AtomicU32::new(0)),
+        lose_confirmations: Arc::new(AtomicU32::new(0)),
+    };
+
+    let mut blocks = vec![block(
+        1,
+        &[(1, 0, 0, locked_payload("1.0", &setup.token_account, "SPL"))],
+    )];
+    std::fs::write(&events_path, Value::from(blocks.clone()).to_string()).unwrap();
+    let source = FileSource::new(events_path.clone(), EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        rpc.clone(),
+        &payer,
+        &setup,
+        source,
+        checkpoint_path.clone(),
+        retry(),
+    );
+
+    // the first send never reaches the node
+    rpc.drop_sends.store(1, Ordering::SeqCst);
+    let reports = relayer.poll().await.unwrap();
+    assert!(is_relayed(&reports[0].outcome));
+
+    // the send lands but its confirmation is lost, the retry finds the record
+    blocks.push(block(
+        2,
+        &[(2, 0, 0, locked_payload("1.0", &setup.token_account, "SPL"))],
+    ));
+    std::fs::write(&events_path, Value::from(blocks.clone()).to_string()).unwrap();
+    rpc.lose_confirmations.store(1, Ordering::SeqCst);
+    let reports = relayer.poll().await.unwrap();
+    assert_eq!(reports[0].outcome, Outcome::AlreadyProcessed);
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        200_000_000
+    );
+
+    // out of retries, the event waits for the next poll
+    blocks.push(block(
+        3,
+        &[(3, 0, 0, locked_payload("1.0", &setup.token_account, "SPL"))],
+    ));
+    std::fs::write(&events_path, Value::from(blocks).to_string()).unwrap();
+    rpc.drop_sends.store(3, Ordering::SeqCst);
+    assert!(relayer.poll().await.is_err());
+    let checkpoint = Checkpoint::load(&checkpoint_path).unwrap().unwrap();
+    assert_eq!(checkpoint.next_height, 3);
+    let reports = relayer.poll().await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(is_relayed(&reports[0].outcome));
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        300_000_000
+    );
+}
+
+// Serves `/v1/blocks?height=sealed` and `/v1/events` of the Flow REST API
+// from `blocks`, sealed up to `sealed_height`.
+async fn serve_flow_api(blocks: Vec<Value>, sealed_height: u64) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let request = String::from_utf8_lossy(&request);
+            let target = request.split_whitespace().nth(1).unwrap_or_default();
+            let query = |name: &str| -> u64 {
+                target
+                    .split(['?', '&'])
+                    .find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+                    .unwrap()
+                    .parse()
+                    .unwrap()
+            };
+
+            let body = if target.starts_with("/v1/blocks") {
+                json!([{ "header": { "height": sealed_height.to_string() } }])
+            } else {
+                let (start, end) = (query("start_height"), query("end_height"));
+                assert!(end <= sealed_height);
+                let blocks: Vec<&Value> = blocks
+                    .iter()
+                    .filter(|block| {
+                        let height: u64 = block["block_height"].as_str().unwrap().parse().unwrap();
+                        start <= height && height <= end
+                    })
+                    .collect();
+                json!(blocks)
+            }
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn test_relay_http_source() {
+    let (mut banks_client, payer, setup) = start().await;
+    let checkpoint_path = temp_path("checkpoint.json");
+
+    // the last block isn't sealed yet
+    let url = serve_flow_api(
+        vec![
+            block(
+                5,
+                &[(1, 0, 0, locked_payload("3.0", &setup.token_account, "SPL"))],
+            ),
+            block(
+                9,
+                &[(2, 0, 0, locked_payload("4.0", &setup.token_account, "SPL"))],
+            ),
+        ],
+        8,
+    )
+    .await;
+    let source = HttpSource::new(&url, EVENT_TYPE.to_string());
+    let mut relayer = new_relayer(
+        banks_client.clone(),
+        &payer,
+        &setup,
+        source,
+        checkpoint_path.clone(),
+        retry(),
+    );
+    let reports = relayer.poll().await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert!(is_relayed(&reports[0].outcome));
+    assert_eq!(relayer.checkpoint().next_height, 9);
+    assert!(relayer.poll().await.unwrap().is_empty());
+    assert_eq!(
+        get_token_balance(&mut banks_client, &setup.token_account).await,
+        300_000_000
+    );
+}
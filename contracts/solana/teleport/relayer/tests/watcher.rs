@@ -0,0 +1,243 @@
+#![cfg(feature = "test-bpf")]
+
+mod common;
+
+use {
+    blt_teleport::instruction::Chain,
+    blt_teleport_client::TeleportClient,
+    blt_teleport_relayer::{
+        Cursor, RelayerError, UnlockArguments, WatchOutcome, WatchReport, Watcher,
+    },
+    common::{start, temp_path, MockSource, Setup},
+    serde_json::json,
+    solana_program_test::*,
+    solana_sdk::{instruction::Instruction, signature::Signer, transaction::Transaction},
+};
+
+async fn poll(watcher: &mut Watcher<&mut MockSource>) -> Result<Vec<WatchReport>, RelayerError> {
+    let mut reports = vec![];
+    watcher.poll(|report| reports.push(report)).await?;
+    Ok(reports)
+}
+
+async fn send(
+    client: &mut TeleportClient<BanksClient>,
+    setup: &Setup,
+    instructions: &[Instruction],
+) -> Transaction {
+    let payer = client.payer().insecure_clone();
+    let transaction = client
+        .transaction(instructions, &[&payer, &setup.from_auth])
+        .await
+        .unwrap();
+    client.broadcast(&transaction).await.unwrap();
+    transaction
+}
+
+#[tokio::test]
+async fn test_watch_teleport_in() {
+    let (mut client, setup) = start().await;
+    let payer = client.payer().insecure_clone();
+    let flow_address = [1, 2, 3, 4, 5, 6, 7, 8];
+    let mut source = MockSource::default();
+
+    let single = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 0, 150_000_000, Chain::Flow, &flow_address)],
+    )
+    .await;
+    source.push(&single, 10, false);
+    // Flow unlocks one teleport in per transaction
+    let double = send(
+        &mut client,
+        &setup,
+        &[
+            setup.teleport_in(&payer, 1, 1, Chain::Flow, &flow_address),
+            setup.teleport_in(&payer, 2, 2, Chain::Flow, &flow_address),
+        ],
+    )
+    .await;
+    source.push(&double, 20, false);
+
+    // the second transaction isn't deep enough yet
+    source.tip = 25;
+    let cursor_path = temp_path("cursor.json");
+    let mut watcher = Watcher::new(
+        &mut source,
+        blt_teleport::id(),
+        10,
+        cursor_path.clone(),
+        None,
+    )
+    .unwrap();
+    let reports = poll(&mut watcher).await.unwrap();
+    assert_eq!(reports.len(), 1);
+    let arguments = UnlockArguments {
+        amount: "1.50000000".to_string(),
+        target: "0x0102030405060708".to_string(),
+        from: hex::encode(setup.from_auth.pubkey()),
+        hash: hex::encode(single.signatures[0]),
+    };
+    assert_eq!(arguments.hash.len(), 128);
+    assert_eq!(
+        reports[0].outcome,
+        WatchOutcome::Unlock {
+            args_json: json!([
+                { "type": "UFix64", "value": "1.50000000" },
+                { "type": "Address", "value": "0x0102030405060708" },
+                { "type": "String", "value": arguments.from },
+                { "type": "String", "value": arguments.hash },
+            ]),
+            arguments,
+        }
+    );
+    assert_eq!(reports[0].nonce, 0);
+    assert_eq!(
+        Cursor::load(&cursor_path).unwrap().unwrap(),
+        Cursor {
+            signature: Some(single.signatures[0].to_string()),
+            slot: 10,
+        }
+    );
+    drop(watcher);
+
+    source.tip = 30;
+    let mut watcher = Watcher::new(
+        &mut source,
+        blt_teleport::id(),
+        10,
+        cursor_path.clone(),
+        None,
+    )
+    .unwrap();
+    let reports = poll(&mut watcher).await.unwrap();
+    assert_eq!(reports.len(), 2);
+    match &reports[0].outcome {
+        WatchOutcome::Unlock { arguments, .. } => {
+            assert_eq!(arguments.amount, "0.00000001");
+            assert_eq!(arguments.hash, hex::encode(double.signatures[0]));
+        }
+        outcome => panic!("unexpected {:?}", outcome),
+    }
+    assert!(matches!(reports[1].outcome, WatchOutcome::Failed { .. }));
+    assert_eq!(reports[1].nonce, 2);
+    assert!(poll(&mut watcher).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_watch_other_chains_and_failures() {
+    let (mut client, setup) = start().await;
+    let payer = client.payer().insecure_clone();
+    let mut source = MockSource::default();
+
+    let bsc = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 0, 100, Chain::Bsc, &[7; 20])],
+    )
+    .await;
+    source.push(&bsc, 1, false);
+    // listed as failed, its instructions are never read
+    let failed = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 1, 100, Chain::Flow, &[9; 8])],
+    )
+    .await;
+    source.push(&failed, 2, true);
+    let flow = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 2, 100, Chain::Flow, &[8; 8])],
+    )
+    .await;
+    source.push(&flow, 3, false);
+    source.tip = 3;
+
+    // starts after the first transaction
+    let cursor_path = temp_path("cursor.json");
+    let mut watcher = Watcher::new(
+        &mut source,
+        blt_teleport::id(),
+        0,
+        cursor_path.clone(),
+        Some(bsc.signatures[0]),
+    )
+    .unwrap();
+    let reports = poll(&mut watcher).await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].signature, flow.signatures[0].to_string());
+    assert_eq!(watcher.cursor().slot, 3);
+    drop(watcher);
+
+    let mut watcher = Watcher::new(
+        &mut source,
+        blt_teleport::id(),
+        0,
+        temp_path("cursor.json"),
+        None,
+    )
+    .unwrap();
+    let reports = poll(&mut watcher).await.unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(
+        reports[0].outcome,
+        WatchOutcome::OtherChain {
+            chain: "Bsc".to_string(),
+            to: hex::encode([7; 20]),
+        }
+    );
+    assert_eq!(reports[1].signature, flow.signatures[0].to_string());
+}
+
+#[tokio::test]
+async fn test_watch_emits_before_error() {
+    let (mut client, setup) = start().await;
+    let payer = client.payer().insecure_clone();
+    let mut source = MockSource::default();
+
+    let first = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 0, 100, Chain::Flow, &[1; 8])],
+    )
+    .await;
+    source.push(&first, 1, false);
+    let second = send(
+        &mut client,
+        &setup,
+        &[setup.teleport_in(&payer, 1, 100, Chain::Flow, &[2; 8])],
+    )
+    .await;
+    source.push(&second, 2, false);
+    source.unavailable.push(second.signatures[0]);
+    source.tip = 2;
+
+    // the first report is out before the second transaction fails
+    let cursor_path = temp_path("cursor.json");
+    let mut watcher = Watcher::new(
+        &mut source,
+        blt_teleport::id(),
+        0,
+        cursor_path.clone(),
+        None,
+    )
+    .unwrap();
+    let mut reports = vec![];
+    let result = watcher.poll(|report| reports.push(report)).await;
+    assert!(matches!(result, Err(RelayerError::Source(_))));
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].signature, first.signatures[0].to_string());
+    assert_eq!(
+        watcher.cursor().signature,
+        Some(first.signatures[0].to_string())
+    );
+    drop(watcher);
+
+    source.unavailable.clear();
+    let mut watcher = Watcher::new(&mut source, blt_teleport::id(), 0, cursor_path, None).unwrap();
+    let reports = poll(&mut watcher).await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].signature, second.signatures[0].to_string());
+}